@@ -0,0 +1,124 @@
+// Bug report 压缩包支持：列出 zip / tar(.gz/.zst/.xz/.bz2) 成员，并将选中成员解出为可索引的临时文件
+use std::collections::HashSet;
+use std::fs;
+use std::io::{self, BufWriter, Read, Write};
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::decompress::{self, Compression, TempLogFile};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArchiveKind {
+    Zip,
+    Tar(Option<Compression>), // 外层可选压缩，例如 .tar.gz
+}
+
+#[derive(Debug, Serialize, Clone)]
+pub struct ArchiveMember {
+    name: String,
+    size: u64, // 解压后的大小
+}
+
+fn is_tar_header(block: &[u8]) -> bool {
+    block.len() >= 262 && &block[257..262] == b"ustar"
+}
+
+// 识别压缩包格式；普通日志或单文件压缩日志返回 None
+pub fn detect_archive(path: &Path) -> io::Result<Option<ArchiveKind>> {
+    let mut head = [0u8; 4];
+    let n = fs::File::open(path)?.read(&mut head)?;
+    if n >= 4 && (head == *b"PK\x03\x04" || head == *b"PK\x05\x06") {
+        return Ok(Some(ArchiveKind::Zip));
+    }
+
+    // tar 头部的 "ustar" 魔数在第 257 字节；外层压缩时先解压出第一个块再判断
+    let compression = decompress::detect_compression(path)?;
+    let file = fs::File::open(path)?;
+    let mut reader: Box<dyn Read> = match compression {
+        Some(kind) => decompress::open_decoder(file, kind)?,
+        None => Box::new(file),
+    };
+    let mut block = Vec::with_capacity(512);
+    reader.by_ref().take(512).read_to_end(&mut block)?;
+
+    Ok(if is_tar_header(&block) { Some(ArchiveKind::Tar(compression)) } else { None })
+}
+
+fn open_tar(path: &Path, compression: Option<Compression>) -> Result<tar::Archive<Box<dyn Read>>, String> {
+    let file = fs::File::open(path).map_err(|e| e.to_string())?;
+    let reader: Box<dyn Read> = match compression {
+        Some(kind) => decompress::open_decoder(file, kind).map_err(|e| e.to_string())?,
+        None => Box::new(file),
+    };
+    Ok(tar::Archive::new(reader))
+}
+
+// 列出压缩包内的普通文件成员（跳过目录）
+pub fn list_members(path: &Path, kind: ArchiveKind) -> Result<Vec<ArchiveMember>, String> {
+    let mut members = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let file = fs::File::open(path).map_err(|e| e.to_string())?;
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for i in 0..zip.len() {
+                let entry = zip.by_index(i).map_err(|e| e.to_string())?;
+                if entry.is_file() {
+                    members.push(ArchiveMember { name: entry.name().to_string(), size: entry.size() });
+                }
+            }
+        }
+        ArchiveKind::Tar(compression) => {
+            let mut archive = open_tar(path, compression)?;
+            for entry in archive.entries().map_err(|e| e.to_string())? {
+                let entry = entry.map_err(|e| e.to_string())?;
+                if entry.header().entry_type().is_file() {
+                    let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+                    members.push(ArchiveMember { name, size: entry.size() });
+                }
+            }
+        }
+    }
+    Ok(members)
+}
+
+fn copy_to_temp(name: &str, reader: &mut dyn Read) -> Result<TempLogFile, String> {
+    let (temp, out) = decompress::create_temp(name)?;
+    let mut writer = BufWriter::new(out);
+    io::copy(reader, &mut writer).map_err(|e| format!("Failed to extract {}: {}", name, e))?;
+    writer.flush().map_err(|e| e.to_string())?;
+    Ok(temp)
+}
+
+// 将指定成员解出到临时文件，返回顺序与 names 一致
+pub fn extract_members(path: &Path, kind: ArchiveKind, names: &[String]) -> Result<Vec<(String, TempLogFile)>, String> {
+    let mut extracted: Vec<(String, TempLogFile)> = Vec::new();
+    match kind {
+        ArchiveKind::Zip => {
+            let file = fs::File::open(path).map_err(|e| e.to_string())?;
+            let mut zip = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for name in names {
+                let mut entry = zip.by_name(name).map_err(|e| format!("{}: {}", name, e))?;
+                extracted.push((name.clone(), copy_to_temp(name, &mut entry)?));
+            }
+        }
+        ArchiveKind::Tar(compression) => {
+            // tar 只能顺序读取，一次遍历取出所有需要的成员
+            let wanted: HashSet<&str> = names.iter().map(|s| s.as_str()).collect();
+            let mut archive = open_tar(path, compression)?;
+            for entry in archive.entries().map_err(|e| e.to_string())? {
+                let mut entry = entry.map_err(|e| e.to_string())?;
+                let name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().to_string();
+                if wanted.contains(name.as_str()) && !extracted.iter().any(|(n, _)| *n == name) {
+                    let temp = copy_to_temp(&name, &mut entry)?;
+                    extracted.push((name, temp));
+                }
+            }
+            if let Some(missing) = names.iter().find(|n| !extracted.iter().any(|(e, _)| e == *n)) {
+                return Err(format!("Member not found in archive: {}", missing));
+            }
+            extracted.sort_by_key(|(n, _)| names.iter().position(|x| x == n));
+        }
+    }
+    Ok(extracted)
+}
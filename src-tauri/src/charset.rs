@@ -0,0 +1,86 @@
+// 字符集识别：无 BOM 的文件按字节分布启发式判断（UTF-16 / UTF-8 / GB18030 / Big5 / Shift-JIS / Latin-1）
+use chardetng::EncodingDetector;
+
+use crate::FileEncoding;
+
+// 每个采样窗口的字节数；分别取文件头、中、尾，避免开头全是 ASCII 时误判为 UTF-8
+const SAMPLE_WINDOW: usize = 64 * 1024;
+
+// 取头、中、尾三个采样窗口；中尾窗口从下一个换行后开始，保证不会截断多字节字符
+fn sample_windows(bytes: &[u8]) -> Vec<&[u8]> {
+    if bytes.len() <= SAMPLE_WINDOW * 3 {
+        return vec![bytes];
+    }
+    let mut windows = vec![&bytes[..SAMPLE_WINDOW]];
+    for start in [bytes.len() / 2, bytes.len() - SAMPLE_WINDOW] {
+        let window = &bytes[start..(start + SAMPLE_WINDOW).min(bytes.len())];
+        if let Some(nl) = window.iter().position(|&b| b == b'\n') {
+            windows.push(&window[nl + 1..]);
+        }
+    }
+    windows
+}
+
+// 无 BOM 的 UTF-16：日志里 ASCII 占多数，每个字符的高字节为 0
+fn sniff_utf16(sample: &[u8]) -> Option<FileEncoding> {
+    let pairs = sample.len() / 2;
+    if pairs < 16 {
+        return None;
+    }
+    let (mut even_zero, mut odd_zero) = (0usize, 0usize);
+    for pair in sample.chunks_exact(2) {
+        if pair[0] == 0 {
+            even_zero += 1;
+        }
+        if pair[1] == 0 {
+            odd_zero += 1;
+        }
+    }
+    if odd_zero * 10 > pairs * 3 && even_zero * 20 < pairs {
+        Some(FileEncoding::Utf16Le)
+    } else if even_zero * 10 > pairs * 3 && odd_zero * 20 < pairs {
+        Some(FileEncoding::Utf16Be)
+    } else {
+        None
+    }
+}
+
+// 合法 UTF-8（允许窗口末尾截断半个字符）
+fn is_utf8(window: &[u8]) -> bool {
+    match std::str::from_utf8(window) {
+        Ok(_) => true,
+        Err(e) => e.error_len().is_none(),
+    }
+}
+
+/// 判断无 BOM 文件的编码；无法识别的单字节编码按 Latin-1 处理，保证每个字节都能还原
+pub fn sniff(bytes: &[u8]) -> FileEncoding {
+    let head = &bytes[..bytes.len().min(SAMPLE_WINDOW)];
+    if let Some(utf16) = sniff_utf16(head) {
+        return utf16;
+    }
+
+    let windows = sample_windows(bytes);
+    if windows.iter().all(|w| is_utf8(w)) {
+        return FileEncoding::Utf8;
+    }
+
+    let mut detector = EncodingDetector::new();
+    for window in &windows {
+        detector.feed(window, false);
+    }
+    detector.feed(&[], true);
+    let guess = detector.guess(None, true);
+
+    if guess == encoding_rs::GBK || guess == encoding_rs::GB18030 {
+        FileEncoding::Gb18030
+    } else if guess == encoding_rs::BIG5 {
+        FileEncoding::Big5
+    } else if guess == encoding_rs::SHIFT_JIS {
+        FileEncoding::ShiftJis
+    } else if guess == encoding_rs::UTF_8 {
+        FileEncoding::Utf8
+    } else {
+        FileEncoding::Latin1
+    }
+}
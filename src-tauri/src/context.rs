@@ -0,0 +1,119 @@
+// 命中上下文：搜索与过滤的命中行向前后扩展（类似 grep -B/-A），重叠或相邻的范围合并为一组
+// 除按行数外，还可以按时间窗口取上下文、只取同一线程/进程的行，并且不跨越会话边界
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use rayon::prelude::*;
+use serde::Deserialize;
+
+// 按线程或时间取上下文时，从命中行向一侧最多查看的行数，避免在线程极多或很密集的日志里扫到文件两端
+const SCAN_LIMIT: usize = 100_000;
+
+/// 上下文选项；before、after 分别为命中行之前、之后带出的行数
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct ContextOptions {
+    pub before: usize,
+    pub after: usize,
+    pub time_window_ms: Option<f64>, // 给出时按时间取上下文：命中行时间戳前后 N 毫秒内的行，取代 before/after
+    pub same_thread: bool,           // 只取与命中行同一线程/进程的行，before/after 也只计这些行
+    pub thread_regex: Option<String>, // 提取线程/进程号的正则（取第一个参与匹配的捕获组），缺省识别 tid=、pid=、[thread-N] 等写法
+    pub timestamp_regex: Option<String>, // 时间窗口使用的时间戳正则，缺省沿用解析文档时的时间戳正则
+    pub sessions: Vec<(usize, usize)>, // 会话范围（1-based 闭区间），上下文不跨越会话边界
+}
+
+/// 按线程或时间扩展时需要的逐行数据；没有线程号或时间戳的行（如堆栈续行）沿用上一行的值
+#[derive(Default)]
+pub struct LineKeys {
+    pub threads: Option<Vec<u64>>, // 线程键，0 表示未知
+    pub times: Option<Vec<f64>>,   // 毫秒时间戳，NaN 表示未知
+}
+
+impl ContextOptions {
+    pub fn symmetric(lines: usize) -> Self {
+        ContextOptions { before: lines, after: lines, ..Default::default() }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.before == 0 && self.after == 0 && self.time_window_ms.is_none()
+    }
+
+    /// 命中行连同上下文的逐行标记（只按行数，keys 为空时与 expand_with 相同）
+    pub fn expand(&self, hits: &[bool]) -> Vec<bool> {
+        self.expand_with(hits, &LineKeys::default())
+    }
+
+    pub fn expand_with(&self, hits: &[bool], keys: &LineKeys) -> Vec<bool> {
+        if keys.threads.is_none() && keys.times.is_none() && self.sessions.is_empty() {
+            return self.expand_lines(hits);
+        }
+        let mask: Vec<AtomicBool> = (0..hits.len()).map(|_| AtomicBool::new(false)).collect();
+        let mut sessions = self.sessions.clone();
+        sessions.sort_unstable();
+
+        hits.par_iter().enumerate().filter(|(_, &h)| h).for_each(|(hit, _)| {
+            let (lo, hi) = session_bounds(&sessions, hit, hits.len());
+            mask[hit].store(true, Ordering::Relaxed);
+            self.walk(hit, (lo..hit).rev(), self.before, keys, &mask);
+            self.walk(hit, hit + 1..=hi, self.after, keys, &mask);
+        });
+        mask.into_iter().map(AtomicBool::into_inner).collect()
+    }
+
+    // 从命中行向一侧逐行查看：按时间时出窗口即停，否则取满 count 行即停；其他线程的行跳过
+    fn walk(&self, hit: usize, lines: impl Iterator<Item = usize>, count: usize, keys: &LineKeys, mask: &[AtomicBool]) {
+        let window = self.time_window_ms.zip(keys.times.as_deref());
+        if window.is_none() && count == 0 {
+            return;
+        }
+        let thread = keys.threads.as_deref().map(|t| (t, t[hit]));
+        let mut taken = 0;
+        for line in lines.take(SCAN_LIMIT) {
+            // 时间戳未知（NaN）的行也算出了窗口
+            let within = window.is_none_or(|(window, times)| (times[line] - times[hit]).abs() <= window);
+            if !within {
+                break;
+            }
+            if thread.is_some_and(|(threads, own)| threads[line] != own) {
+                continue;
+            }
+            mask[line].store(true, Ordering::Relaxed);
+            taken += 1;
+            if window.is_none() && taken == count {
+                break;
+            }
+        }
+    }
+
+    // 只按行数：一次顺序扫描
+    fn expand_lines(&self, hits: &[bool]) -> Vec<bool> {
+        let mut mask = vec![false; hits.len()];
+        let mut covered = 0; // 已选中到的行（不含）
+        for (line, _) in hits.iter().enumerate().filter(|(_, &h)| h) {
+            let start = line.saturating_sub(self.before).max(covered);
+            let end = (line + self.after + 1).min(hits.len());
+            mask[start..end].fill(true);
+            covered = covered.max(end);
+        }
+        mask
+    }
+}
+
+// 命中行所在会话的首末行（0-based 闭区间）；不在任何会话内时为整个文件
+fn session_bounds(sessions: &[(usize, usize)], line: usize, line_count: usize) -> (usize, usize) {
+    let ln = line + 1;
+    let i = sessions.partition_point(|&(start, _)| start <= ln);
+    sessions[..i].iter().rev()
+        .find(|&&(_, end)| end >= ln)
+        .map_or((0, line_count - 1), |&(start, end)| (start.max(1) - 1, end.saturating_sub(1).min(line_count - 1)))
+}
+
+/// 向后填充：None 的行沿用上一行的值，开头没有值的行为 unknown
+pub fn carry_forward<T: Copy>(values: Vec<Option<T>>, unknown: T) -> Vec<T> {
+    let mut last = unknown;
+    values.into_iter().map(|v| {
+        if let Some(v) = v {
+            last = v;
+        }
+        last
+    }).collect()
+}
@@ -0,0 +1,193 @@
+// 压缩日志支持：识别 gz/zst/xz/bz2，解压到临时文件后按普通日志建立索引
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Xz,
+    Bzip2,
+}
+
+impl Compression {
+    // 去掉压缩扩展名后的显示名，例如 messages.gz -> messages
+    pub fn strip_extension(self, name: &str) -> String {
+        let exts: &[&str] = match self {
+            Compression::Gzip => &[".gz", ".gzip"],
+            Compression::Zstd => &[".zst", ".zstd"],
+            Compression::Xz => &[".xz"],
+            Compression::Bzip2 => &[".bz2"],
+        };
+        for ext in exts {
+            let cut = name.len().saturating_sub(ext.len());
+            if cut > 0 && name.get(cut..).is_some_and(|tail| tail.eq_ignore_ascii_case(ext)) {
+                return name[..cut].to_string();
+            }
+        }
+        name.to_string()
+    }
+}
+
+/// 解压进度事件 (`decompress-progress`)
+#[derive(Debug, Serialize, Clone)]
+pub struct DecompressProgress {
+    pub path: String,
+    pub bytes_read: u64,  // 已读取的压缩字节数
+    pub total_bytes: u64, // 压缩文件总大小
+    pub bytes_written: u64, // 已解压输出的字节数
+    pub done: bool,
+}
+
+// 通过魔数识别压缩格式（不依赖扩展名）
+pub fn detect_compression(path: &Path) -> io::Result<Option<Compression>> {
+    let mut magic = [0u8; 6];
+    let mut file = fs::File::open(path)?;
+    let n = file.read(&mut magic)?;
+    let magic = &magic[..n];
+
+    Ok(if magic.starts_with(&[0x1f, 0x8b]) {
+        Some(Compression::Gzip)
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Some(Compression::Zstd)
+    } else if magic.starts_with(&[0xfd, b'7', b'z', b'X', b'Z', 0x00]) {
+        Some(Compression::Xz)
+    } else if magic.starts_with(b"BZh") {
+        Some(Compression::Bzip2)
+    } else {
+        None
+    })
+}
+
+/// 解压得到的临时文件，随索引一起释放时自动删除
+pub struct TempLogFile {
+    path: PathBuf,
+}
+
+impl TempLogFile {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempLogFile {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// 统计已读取字节数的 Reader，用于计算解压进度
+struct CountingReader<R> {
+    inner: R,
+    count: Arc<AtomicU64>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.count.fetch_add(n as u64, Ordering::Relaxed);
+        Ok(n)
+    }
+}
+
+static TEMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+// 在系统临时目录创建输出文件；name_hint 只取最后一段，避免成员路径逃逸出临时目录
+pub fn create_temp(name_hint: &str) -> Result<(TempLogFile, fs::File), String> {
+    let stem = Path::new(name_hint).file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("log");
+    // 名称带随机后缀且以 create_new 创建：不跟随预先放置的符号链接，也不会覆盖已有文件
+    for _ in 0..16 {
+        let seq = TEMP_SEQ.fetch_add(1, Ordering::Relaxed);
+        let path = std::env::temp_dir().join(format!("logview-{}-{}-{:016x}-{}", std::process::id(), seq, random_suffix(seq), stem));
+        match fs::OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(file) => return Ok((TempLogFile { path }, file)),
+            Err(e) if e.kind() == io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e.to_string()),
+        }
+    }
+    Err("Failed to create a unique temporary file".to_string())
+}
+
+// 标准库的 RandomState 每个实例带随机种子，足以让临时文件名不可预测
+fn random_suffix(seq: u64) -> u64 {
+    use std::hash::{BuildHasher, Hasher};
+    let mut hasher = std::collections::hash_map::RandomState::new().build_hasher();
+    hasher.write_u64(seq);
+    if let Ok(elapsed) = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH) {
+        hasher.write_u128(elapsed.as_nanos());
+    }
+    hasher.finish()
+}
+
+// 按格式包装解压流
+pub fn open_decoder<'a, R: Read + 'a>(raw: R, kind: Compression) -> io::Result<Box<dyn Read + 'a>> {
+    let raw = BufReader::new(raw);
+    Ok(match kind {
+        Compression::Gzip => Box::new(flate2::read::MultiGzDecoder::new(raw)),
+        Compression::Zstd => Box::new(zstd::stream::read::Decoder::with_buffer(raw)?),
+        Compression::Xz => Box::new(xz2::read::XzDecoder::new_multi_decoder(raw)),
+        Compression::Bzip2 => Box::new(bzip2::read::MultiBzDecoder::new(raw)),
+    })
+}
+
+// 解压整个文件到临时目录；on_progress 大约每 4MB 输出回调一次
+pub fn decompress_to_temp(
+    source: &Path,
+    kind: Compression,
+    mut on_progress: impl FnMut(DecompressProgress),
+) -> Result<TempLogFile, String> {
+    let file = fs::File::open(source).map_err(|e| e.to_string())?;
+    let total_bytes = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let bytes_read = Arc::new(AtomicU64::new(0));
+
+    // 解压器自身也有缓冲，因此按压缩字节计算的进度是近似值
+    let raw = CountingReader { inner: file, count: bytes_read.clone() };
+    let mut decoder = open_decoder(raw, kind).map_err(|e| e.to_string())?;
+
+    let name_hint = source.file_name().and_then(|n| n.to_str()).unwrap_or("log");
+    let (temp, out) = create_temp(&kind.strip_extension(name_hint))?;
+    let mut writer = BufWriter::new(out);
+
+    let path_str = source.to_string_lossy().to_string();
+    let mut buf = vec![0u8; 256 * 1024];
+    let mut written: u64 = 0;
+    let mut last_report: u64 = 0;
+
+    loop {
+        let n = decoder.read(&mut buf).map_err(|e| format!("Decompression failed: {}", e))?;
+        if n == 0 {
+            break;
+        }
+        writer.write_all(&buf[..n]).map_err(|e| e.to_string())?;
+        written += n as u64;
+
+        if written - last_report >= 4 * 1024 * 1024 {
+            last_report = written;
+            on_progress(DecompressProgress {
+                path: path_str.clone(),
+                bytes_read: bytes_read.load(Ordering::Relaxed),
+                total_bytes,
+                bytes_written: written,
+                done: false,
+            });
+        }
+    }
+    writer.flush().map_err(|e| e.to_string())?;
+
+    on_progress(DecompressProgress {
+        path: path_str,
+        bytes_read: total_bytes,
+        total_bytes,
+        bytes_written: written,
+        done: true,
+    });
+
+    Ok(temp)
+}
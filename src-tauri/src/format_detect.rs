@@ -0,0 +1,290 @@
+// 日志格式识别：对文件开头取样，推荐时间戳、级别与启动标识正则，每项附带置信度 (0~1)
+// 时间戳在若干常见写法中取匹配且能解析的行最多的一种；级别取最常见的写法与实际出现的取值
+// 启动标识取时间戳回退（重启后从头计时）处反复出现的行
+use std::collections::HashMap;
+use std::time::SystemTime;
+
+use regex::Regex;
+use serde::Serialize;
+
+use crate::timestamp::{Format, TimestampOptions, TimestampParser};
+
+// 最多使用的样本行数
+const MAX_SAMPLE_LINES: usize = 50_000;
+
+// 时间戳比上一条早这么多秒以上才算回退，避免多线程日志的轻微乱序
+const RESET_TOLERANCE_SECS: f64 = 1.0;
+
+// 回退处前后各看几行（启动标识可能在时间戳回退之前或之后打印）
+const RESET_WINDOW: usize = 2;
+
+// 启动标识正则只取行首的若干个词
+const MARKER_TOKENS: usize = 6;
+
+const MAX_BOOT_SUGGESTIONS: usize = 5;
+
+// 常见时间戳写法（第一个捕获组为时间戳），越具体的越靠前；置信度相同时取靠前的
+const TIMESTAMP_CANDIDATES: &[&str] = &[
+    r"(\d{4}-\d{2}-\d{2}[T _]\d{2}:\d{2}:\d{2}(?:[.,]\d+)?(?:Z| ?[+-]\d{2}:?\d{2})?)",
+    r"(\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2}(?:[.,]\d+)?)",
+    r"(\d{2}/[A-Z][a-z]{2}/\d{4}:\d{2}:\d{2}:\d{2}(?: [+-]\d{4})?)",
+    r"([A-Z][a-z]{2} [ \d]\d \d{2}:\d{2}:\d{2}(?:\.\d+)?)",
+    r"\b(\d{2}-\d{2} \d{2}:\d{2}:\d{2}\.\d+)",
+    r"^\[\s*(\d+\.\d+)\]",
+    r"^\[?(\d{10}(?:\.\d{1,9})?|\d{13}|\d{16}|\d{19})\b",
+    r"\b(\d{2}:\d{2}:\d{2}(?:[.,]\d+)?)\b",
+];
+
+// 级别取值，按严重程度排列
+const LEVEL_WORDS: &[&str] = &[
+    "TRACE", "VERBOSE", "DEBUG", "FINE", "INFO", "NOTICE", "NORM", "SUCCESS", "WARN", "WARNING",
+    "ERROR", "ERR", "SEVERE", "CRITICAL", "CRIT", "FATAL", "ALERT", "EMERG",
+];
+
+// Android logcat 的单字母级别
+const LOGCAT_LEVELS: &str = "VDIWEFA";
+
+/// 级别的一种写法；{} 处为取值的可选列表
+struct LevelLayout {
+    template: &'static str,
+    logcat: bool, // 取值为单字母
+    weight: f64,  // 不带括号的裸词容易误中正文，置信度打折
+}
+
+const LEVEL_LAYOUTS: &[LevelLayout] = &[
+    LevelLayout { template: r"(?i)\[({})\]", logcat: false, weight: 1.0 },
+    LevelLayout { template: r"(?i)<({})>", logcat: false, weight: 1.0 },
+    LevelLayout { template: r#"(?i)\blevel[=:]\s*"?({})\b"#, logcat: false, weight: 1.0 },
+    LevelLayout { template: r"\s\d+\s+\d+ ([{}]) ", logcat: true, weight: 1.0 },
+    LevelLayout { template: r"(?:^|\s)([{}])/\S", logcat: true, weight: 1.0 },
+    LevelLayout { template: r"\b({})\b", logcat: false, weight: 0.8 },
+];
+
+#[derive(Debug, Serialize)]
+pub struct TimestampSuggestion {
+    regex: String,
+    format: Option<Format>,
+    column: usize,  // 时间戳在行内的位置（字符，取各行的中位数）
+    anchored: bool, // 时间戳总在行首（前面只有固定的前缀），推荐的正则已锚定到行首
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelCount {
+    level: String,
+    count: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LevelSuggestion {
+    regex: String,
+    vocabulary: Vec<LevelCount>, // 实际出现的取值，按出现次数排序
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BootSuggestion {
+    regex: String,
+    example: String,    // 第一次出现的行
+    resets: usize,      // 出现在多少次时间戳回退处
+    occurrences: usize, // 在样本中一共命中多少行
+    confidence: f64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct FormatReport {
+    lines_sampled: usize,
+    timestamp: Option<TimestampSuggestion>,
+    level: Option<LevelSuggestion>,
+    timestamp_resets: usize,
+    boot_markers: Vec<BootSuggestion>, // 按置信度排序
+}
+
+/// 识别一段样本文本的格式；modified 为文件修改时间，用于推断不带年份的时间戳
+pub fn detect(text: &str, options: &TimestampOptions, modified: Option<SystemTime>) -> Result<FormatReport, String> {
+    let lines: Vec<&str> = text.lines().filter(|l| !l.trim().is_empty()).take(MAX_SAMPLE_LINES).collect();
+    let base = TimestampParser::new(options, modified)?;
+
+    let timestamp = detect_timestamp(&lines, &base);
+    let times: Vec<Option<f64>> = match &timestamp {
+        Some((re, parser, _)) => lines.iter().map(|line| {
+            let caps = re.captures(line)?;
+            parser.parse(caps.get(1)?.as_str()).ok()
+        }).collect(),
+        None => vec![None; lines.len()],
+    };
+    let timestamp_re = timestamp.as_ref().map(|(re, _, _)| re);
+
+    // 级别按带时间戳的行计算覆盖率（堆栈续行通常两者都没有）
+    let stamped = times.iter().filter(|t| t.is_some()).count();
+    let level = detect_level(&lines, if stamped > 0 { stamped } else { lines.len() });
+
+    let resets = reset_points(&times);
+    let boot_markers = detect_boot_markers(&lines, &resets, timestamp_re);
+
+    Ok(FormatReport {
+        lines_sampled: lines.len(),
+        timestamp: timestamp.map(|(_, _, suggestion)| suggestion),
+        level,
+        timestamp_resets: resets.len(),
+        boot_markers,
+    })
+}
+
+// 各候选写法：置信度 = 匹配行占比 × 匹配到的时间戳中能解析的占比
+fn detect_timestamp(lines: &[&str], base: &TimestampParser) -> Option<(Regex, TimestampParser, TimestampSuggestion)> {
+    let mut best: Option<(Regex, TimestampParser, TimestampSuggestion)> = None;
+    for pattern in TIMESTAMP_CANDIDATES {
+        let re = Regex::new(pattern).expect("built-in timestamp pattern");
+        let matches: Vec<(&str, regex::Match)> = lines.iter()
+            .filter_map(|line| Some((*line, re.captures(line)?.get(1)?)))
+            .collect();
+        if matches.is_empty() {
+            continue;
+        }
+        let samples: Vec<&str> = matches.iter().map(|(_, m)| m.as_str()).collect();
+        let Ok(parser) = base.clone().detect(&samples) else { continue };
+        let parsed = samples.iter().filter(|s| parser.parse(s).is_ok()).count();
+        let confidence = matches.len() as f64 / lines.len() as f64 * parsed as f64 / samples.len() as f64;
+        if best.as_ref().is_some_and(|(_, _, b)| b.confidence >= confidence) {
+            continue;
+        }
+
+        let mut columns: Vec<usize> = matches.iter().map(|(line, m)| line[..m.start()].chars().count()).collect();
+        columns.sort_unstable();
+        let column = columns[columns.len() / 2];
+        // 各行时间戳前的文本都相同且很短（如 "[" 或空）时锚定到行首
+        let prefix = &matches[0].0[..matches[0].1.start()];
+        let fixed_prefix = prefix.chars().count() <= 3 && matches.iter().all(|(line, m)| &line[..m.start()] == prefix);
+        let anchored = pattern.starts_with('^') || fixed_prefix;
+        let regex = match (pattern.starts_with('^'), fixed_prefix) {
+            (false, true) => format!("^{}{}", regex::escape(prefix), pattern),
+            _ => pattern.to_string(),
+        };
+
+        let suggestion = TimestampSuggestion { regex, format: parser.format().cloned(), column, anchored, confidence: round(confidence) };
+        best = Some((re, parser, suggestion));
+    }
+    best
+}
+
+// 各写法：置信度 = 匹配行占 denominator 的比例 × 写法权重，推荐的正则只列出实际出现的取值
+fn detect_level(lines: &[&str], denominator: usize) -> Option<LevelSuggestion> {
+    let words = LEVEL_WORDS.join("|");
+    let mut best: Option<LevelSuggestion> = None;
+    for layout in LEVEL_LAYOUTS {
+        let alternatives = if layout.logcat { LOGCAT_LEVELS } else { words.as_str() };
+        let re = Regex::new(&layout.template.replace("{}", alternatives)).expect("built-in level pattern");
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        let mut matched = 0;
+        for line in lines {
+            if let Some(level) = re.captures(line).and_then(|c| c.get(1)) {
+                *counts.entry(level.as_str().to_uppercase()).or_default() += 1;
+                matched += 1;
+            }
+        }
+        if matched == 0 {
+            continue;
+        }
+        let confidence = (matched as f64 / denominator.max(1) as f64).min(1.0) * layout.weight;
+        if best.as_ref().is_some_and(|b| b.confidence >= confidence) {
+            continue;
+        }
+
+        let observed = |word: &str| counts.contains_key(word);
+        let alternatives = match layout.logcat {
+            true => LOGCAT_LEVELS.chars().map(String::from).filter(|c| observed(c)).collect::<String>(),
+            false => LEVEL_WORDS.iter().copied().filter(|w| observed(w)).collect::<Vec<_>>().join("|"),
+        };
+        let mut vocabulary: Vec<LevelCount> = counts.into_iter().map(|(level, count)| LevelCount { level, count }).collect();
+        vocabulary.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.level.cmp(&b.level)));
+        best = Some(LevelSuggestion {
+            regex: layout.template.replace("{}", &alternatives),
+            vocabulary,
+            confidence: round(confidence),
+        });
+    }
+    best
+}
+
+// 时间戳明显早于此前最新时间戳的行（重启后从头计时、时钟被重置）
+fn reset_points(times: &[Option<f64>]) -> Vec<usize> {
+    let mut latest = f64::NEG_INFINITY;
+    let mut resets = Vec::new();
+    for (idx, time) in times.iter().enumerate() {
+        let Some(time) = *time else { continue };
+        if time < latest - RESET_TOLERANCE_SECS {
+            resets.push(idx);
+            latest = time;
+        } else {
+            latest = latest.max(time);
+        }
+    }
+    resets
+}
+
+// 回退处附近各行去掉时间戳后取行首若干词作为模板（含数字的词视为可变部分），统计各模板出现在多少次回退处
+// 置信度 = 出现的回退占比 × 命中行中位于回退处或文件开头的占比（正文中到处出现的行不是好的标识）
+fn detect_boot_markers(lines: &[&str], resets: &[usize], timestamp_re: Option<&Regex>) -> Vec<BootSuggestion> {
+    if resets.is_empty() {
+        return Vec::new();
+    }
+    // 文件开头同样是一次启动，计为第 0 个边界，但不计入回退次数
+    let boundaries: Vec<usize> = std::iter::once(0).chain(resets.iter().copied()).collect();
+    // 模板 -> (出现在哪几个边界处, 示例行)
+    let mut templates: HashMap<String, (Vec<usize>, &str)> = HashMap::new();
+    for (n, &reset) in boundaries.iter().enumerate() {
+        let window = reset.saturating_sub(RESET_WINDOW)..(reset + RESET_WINDOW + 1).min(lines.len());
+        for line in &lines[window] {
+            let Some(template) = marker_template(line, timestamp_re) else { continue };
+            let entry = templates.entry(template).or_insert_with(|| (Vec::new(), line));
+            if entry.0.last() != Some(&n) {
+                entry.0.push(n);
+            }
+        }
+    }
+
+    // 只有一次回退时无法区分，所有模板都保留；否则至少出现在两次回退处
+    let min_resets = if resets.len() == 1 { 1 } else { 2 };
+    let mut suggestions: Vec<BootSuggestion> = templates.into_iter()
+        .filter_map(|(regex, (seen, example))| {
+            let at_resets = seen.iter().filter(|&&n| n > 0).count();
+            if at_resets < min_resets {
+                return None;
+            }
+            let re = Regex::new(&regex).ok()?;
+            let occurrences = lines.iter().filter(|line| re.is_match(line)).count().max(1);
+            let coverage = at_resets as f64 / resets.len() as f64;
+            let precision = (seen.len() as f64 / occurrences as f64).min(1.0);
+            Some(BootSuggestion {
+                regex,
+                example: example.trim().to_string(),
+                resets: at_resets,
+                occurrences,
+                confidence: round(coverage * precision),
+            })
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.confidence.total_cmp(&a.confidence).then_with(|| a.regex.cmp(&b.regex)));
+    suggestions.truncate(MAX_BOOT_SUGGESTIONS);
+    suggestions
+}
+
+fn marker_template(line: &str, timestamp_re: Option<&Regex>) -> Option<String> {
+    let rest = match timestamp_re.and_then(|re| re.find(line)) {
+        Some(m) => format!("{} {}", &line[..m.start()], &line[m.end()..]),
+        None => line.to_string(),
+    };
+    // 去掉时间戳后残留在行首的括号等符号
+    let tokens: Vec<String> = rest.split_whitespace()
+        .skip_while(|t| !t.chars().any(|c| c.is_alphanumeric()))
+        .take(MARKER_TOKENS)
+        .map(|t| if t.chars().any(|c| c.is_ascii_digit()) { r"\S+".to_string() } else { regex::escape(t) })
+        .collect();
+    // 全是可变部分的行（纯数字、地址）不适合作为标识
+    tokens.iter().any(|t| t != r"\S+").then(|| tokens.join(r"\s+"))
+}
+
+fn round(value: f64) -> f64 {
+    (value * 1000.0).round() / 1000.0
+}
@@ -0,0 +1,534 @@
+// 持久化索引缓存：行偏移、级别、切分正则命中、会话边界、时间戳与 trigram 索引写入缓存目录下的 sidecar 文件
+// 以路径、大小、修改时间以及首尾内容哈希识别文件；文件只被追加时复用已有部分，只扫描新增内容
+use std::fs;
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::OnceLock;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::line_index::{LevelCodes, LineOffsets, LineTimestamps};
+use crate::trigram::TrigramIndex;
+use crate::{FileEncoding, LogSession};
+
+// 首尾各取多少字节计算内容哈希
+const HASH_WINDOW: usize = 64 * 1024;
+const INDEX_MAGIC: &[u8; 8] = b"LVIDX002";
+const TIMESTAMP_MAGIC: &[u8; 8] = b"LVTS0001";
+const TRIGRAM_MAGIC: &[u8; 8] = b"LVTRI001";
+const HITS_MAGIC: &[u8; 8] = b"LVHIT001";
+// 每个文件最多保留的会话切分结果（不同的切分正则组合），切分正则命中同样按此上限保留
+const MAX_SESSION_ENTRIES: usize = 8;
+// 缓存目录总大小上限，超出后按修改时间从旧到新删除（时间戳每行 8 字节，大文件很快就会占满）
+const MAX_CACHE_BYTES: u64 = 1 << 30;
+
+static CACHE_DIR: OnceLock<PathBuf> = OnceLock::new();
+static TEMP_SEQ: AtomicU64 = AtomicU64::new(0);
+
+/// 设置缓存目录（应用启动时调用）；未设置时不读写缓存
+pub fn init(dir: PathBuf) {
+    let _ = fs::create_dir_all(&dir);
+    let _ = CACHE_DIR.set(dir);
+}
+
+// FNV-1a：跨版本稳定，足以判断内容是否变化
+fn fnv1a(bytes: &[u8]) -> u64 {
+    let mut hash: u64 = 0xcbf29ce484222325;
+    for &b in bytes {
+        hash ^= b as u64;
+        hash = hash.wrapping_mul(0x100000001b3);
+    }
+    hash
+}
+
+fn window_hashes(bytes: &[u8]) -> (u64, u64) {
+    let window = bytes.len().min(HASH_WINDOW);
+    (fnv1a(&bytes[..window]), fnv1a(&bytes[bytes.len() - window..]))
+}
+
+/// 文件身份：路径 + 大小 + 修改时间 + 首尾内容哈希
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileKey {
+    path: String,
+    size: u64,
+    mtime_ns: u64,
+    head_hash: u64,
+    tail_hash: u64,
+}
+
+impl FileKey {
+    pub fn compute(path: &Path, bytes: &[u8]) -> Option<Self> {
+        let modified = fs::metadata(path).ok()?.modified().ok()?;
+        let mtime_ns = modified.duration_since(UNIX_EPOCH).ok()?.as_nanos() as u64;
+        let (head_hash, tail_hash) = window_hashes(bytes);
+        Some(FileKey {
+            path: path.to_string_lossy().to_string(),
+            size: bytes.len() as u64,
+            mtime_ns,
+            head_hash,
+            tail_hash,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Validity {
+    Exact,    // 文件未变化
+    Appended, // 文件只在末尾追加了内容
+}
+
+// 对比缓存时的文件身份与当前文件；追加的判断依据是原有长度内的首尾哈希不变
+fn check(stored: &FileKey, current: &FileKey, bytes: &[u8]) -> Option<Validity> {
+    if stored.path != current.path || stored.size > current.size {
+        return None;
+    }
+    if stored == current {
+        return Some(Validity::Exact);
+    }
+    if stored.size == current.size {
+        return None; // 大小相同但内容或修改时间变了
+    }
+    let (head_hash, tail_hash) = window_hashes(&bytes[..stored.size as usize]);
+    if head_hash == stored.head_hash && tail_hash == stored.tail_hash {
+        Some(Validity::Appended)
+    } else {
+        None
+    }
+}
+
+fn cache_path(key: &FileKey, ext: &str) -> Option<PathBuf> {
+    let dir = CACHE_DIR.get()?;
+    Some(dir.join(format!("{:016x}.{}", fnv1a(key.path.as_bytes()), ext)))
+}
+
+// 先写临时文件再改名，避免并发读到写了一半的缓存；临时文件名按进程与序号区分，并发写入互不干扰
+// 缓存只是加速手段，写入失败时静默放弃
+fn write_atomic(path: &Path, write: impl FnOnce(&mut BufWriter<fs::File>) -> io::Result<()>) {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}-{}.tmp", std::process::id(), TEMP_SEQ.fetch_add(1, Ordering::Relaxed)));
+    let tmp = PathBuf::from(tmp);
+    let result = fs::File::create(&tmp).and_then(|file| {
+        let mut writer = BufWriter::new(file);
+        write(&mut writer)?;
+        writer.flush()
+    });
+    if result.and_then(|()| fs::rename(&tmp, path)).is_err() {
+        let _ = fs::remove_file(&tmp);
+        return;
+    }
+    if let Some(dir) = path.parent() {
+        prune(dir, path);
+    }
+}
+
+// 缓存目录超出上限时从最旧的文件开始删除，刚写入的文件保留
+fn prune(dir: &Path, keep: &Path) {
+    let Ok(entries) = fs::read_dir(dir) else { return };
+    let mut files: Vec<(SystemTime, u64, PathBuf)> = entries.filter_map(|entry| {
+        let entry = entry.ok()?;
+        let meta = entry.metadata().ok()?;
+        meta.is_file().then(|| (meta.modified().unwrap_or(UNIX_EPOCH), meta.len(), entry.path()))
+    }).collect();
+    let mut total: u64 = files.iter().map(|(_, len, _)| len).sum();
+    if total <= MAX_CACHE_BYTES {
+        return;
+    }
+    files.sort_by_key(|(modified, _, _)| *modified);
+    for (_, len, path) in files {
+        if total <= MAX_CACHE_BYTES {
+            break;
+        }
+        if path != keep && fs::remove_file(&path).is_ok() {
+            total -= len;
+        }
+    }
+}
+
+fn write_header<H: Serialize>(writer: &mut impl Write, magic: &[u8; 8], header: &H) -> io::Result<()> {
+    let json = serde_json::to_vec(header).map_err(io::Error::other)?;
+    writer.write_all(magic)?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(&json)
+}
+
+// 缓存文件的读取器：limit() 为剩余字节数，据此校验文件中记录的长度
+type CacheReader = io::Take<BufReader<fs::File>>;
+
+fn open_cache(path: &Path) -> Option<CacheReader> {
+    let file = fs::File::open(path).ok()?;
+    let len = file.metadata().ok()?.len();
+    Some(BufReader::new(file).take(len))
+}
+
+fn read_header<H: DeserializeOwned>(reader: &mut CacheReader, magic: &[u8; 8]) -> Option<H> {
+    let mut found = [0u8; 8];
+    reader.read_exact(&mut found).ok()?;
+    if &found != magic {
+        return None;
+    }
+    let mut len = [0u8; 4];
+    reader.read_exact(&mut len).ok()?;
+    let json = read_exact_vec(reader, u32::from_le_bytes(len) as usize)?;
+    serde_json::from_slice(&json).ok()
+}
+
+// 长度超出文件剩余部分说明缓存被截断或损坏，直接视为未命中，不按该长度分配内存
+fn read_exact_vec(reader: &mut CacheReader, len: usize) -> Option<Vec<u8>> {
+    if len as u64 > reader.limit() {
+        return None;
+    }
+    let mut buf = vec![0u8; len];
+    reader.read_exact(&mut buf).ok()?;
+    Some(buf)
+}
+
+// ---------------------------------------------------------------------------
+// 行偏移与级别
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct IndexHeader {
+    key: FileKey,
+    encoding: String,
+    encoding_override: Option<String>,
+    level_regex: Option<String>,
+    line_count: usize,
+    offsets_len: usize, // 偏移区的字节数：相邻行偏移之差的 LEB128 变长编码
+    level_table: Vec<String>, // 级别代码 n 对应 level_table[n - 1]，0 表示无级别
+}
+
+pub struct CachedIndex {
+    pub encoding: FileEncoding,
+    pub offsets: LineOffsets,
+    pub levels: LevelCodes,
+    pub validity: Validity,
+}
+
+// 读取缓存的行索引；编码覆盖或级别正则不同则视为未命中
+pub fn load_index(
+    key: &FileKey,
+    bytes: &[u8],
+    encoding_override: Option<FileEncoding>,
+    level_regex: Option<&str>,
+) -> Option<CachedIndex> {
+    let path = cache_path(key, "idx")?;
+    let mut reader = open_cache(&path)?;
+    let header: IndexHeader = read_header(&mut reader, INDEX_MAGIC)?;
+    if header.encoding_override.as_deref() != encoding_override.map(|e| e.label())
+        || header.level_regex.as_deref() != level_regex
+    {
+        return None;
+    }
+    let validity = check(&header.key, key, bytes)?;
+    if validity == Validity::Appended && header.line_count == 0 {
+        return None; // 原来是空文件，无法复用编码识别结果
+    }
+    let encoding = FileEncoding::from_label(&header.encoding)?;
+
+    let raw = read_exact_vec(&mut reader, header.offsets_len)?;
+    let mut offsets = LineOffsets::default();
+    let (mut pos, mut offset) = (0usize, 0usize);
+    for _ in 0..header.line_count {
+        let (delta, len) = read_varint(raw.get(pos..)?)?;
+        offset += delta as usize;
+        offsets.push(offset);
+        pos += len;
+    }
+    let codes = read_exact_vec(&mut reader, header.line_count)?;
+    let levels = LevelCodes::from_parts(codes, header.level_table);
+
+    Some(CachedIndex { encoding, offsets, levels, validity })
+}
+
+pub fn store_index(
+    key: &FileKey,
+    encoding: FileEncoding,
+    encoding_override: Option<FileEncoding>,
+    level_regex: Option<&str>,
+    offsets: &LineOffsets,
+    levels: &LevelCodes,
+) {
+    let Some(path) = cache_path(key, "idx") else { return };
+
+    let mut encoded = Vec::with_capacity(offsets.len() * 2);
+    let mut prev = 0usize;
+    for offset in offsets.iter() {
+        write_varint(&mut encoded, (offset - prev) as u64);
+        prev = offset;
+    }
+
+    let header = IndexHeader {
+        key: key.clone(),
+        encoding: encoding.label().to_string(),
+        encoding_override: encoding_override.map(|e| e.label().to_string()),
+        level_regex: level_regex.map(|s| s.to_string()),
+        line_count: offsets.len(),
+        offsets_len: encoded.len(),
+        level_table: levels.table().to_vec(),
+    };
+    write_atomic(&path, |w| {
+        write_header(w, INDEX_MAGIC, &header)?;
+        w.write_all(&encoded)?;
+        w.write_all(levels.codes())
+    });
+}
+
+fn write_varint(out: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        out.push((value as u8) | 0x80);
+        value >>= 7;
+    }
+    out.push(value as u8);
+}
+
+// 返回 (值, 占用字节数)
+fn read_varint(bytes: &[u8]) -> Option<(u64, usize)> {
+    let mut value = 0u64;
+    for (i, &b) in bytes.iter().enumerate().take(10) {
+        value |= ((b & 0x7f) as u64) << (7 * i);
+        if b & 0x80 == 0 {
+            return Some((value, i + 1));
+        }
+    }
+    None
+}
+
+// ---------------------------------------------------------------------------
+// 每行时间戳
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct TimestampHeader {
+    key: FileKey,
+    timestamp_regex: String,
+    line_count: usize,
+}
+
+// 返回可复用的前缀：文件未变化时为全部行，追加时去掉可能被续写的末行
+pub fn load_timestamps(key: &FileKey, bytes: &[u8], timestamp_regex: &str) -> Option<LineTimestamps> {
+    let path = cache_path(key, "ts")?;
+    let mut reader = open_cache(&path)?;
+    let header: TimestampHeader = read_header(&mut reader, TIMESTAMP_MAGIC)?;
+    if header.timestamp_regex != timestamp_regex {
+        return None;
+    }
+    let validity = check(&header.key, key, bytes)?;
+
+    let raw = read_exact_vec(&mut reader, header.line_count.checked_mul(8)?)?;
+    let mut values: Vec<f64> = raw.chunks_exact(8)
+        .map(|c| f64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    if validity == Validity::Appended {
+        values.pop();
+    }
+    Some(values.into())
+}
+
+pub fn store_timestamps(key: &FileKey, timestamp_regex: &str, values: &LineTimestamps) {
+    let Some(path) = cache_path(key, "ts") else { return };
+    let header = TimestampHeader {
+        key: key.clone(),
+        timestamp_regex: timestamp_regex.to_string(),
+        line_count: values.len(),
+    };
+    write_atomic(&path, |w| {
+        write_header(w, TIMESTAMP_MAGIC, &header)?;
+        for value in values.values() {
+            w.write_all(&value.to_le_bytes())?;
+        }
+        Ok(())
+    });
+}
+
+// ---------------------------------------------------------------------------
+// Trigram 索引
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct TrigramHeader {
+    key: FileKey,
+    encoding: String, // 索引基于解码后的文本，编码不同则不能复用
+    blocks: usize,
+    rows_len: usize,
+}
+
+// 已收录的块都不含末行，文件只被追加时仍然有效，新增的行由调用方补建
+pub fn load_trigrams(key: &FileKey, bytes: &[u8], encoding: FileEncoding) -> Option<TrigramIndex> {
+    let path = cache_path(key, "tri")?;
+    let mut reader = open_cache(&path)?;
+    let header: TrigramHeader = read_header(&mut reader, TRIGRAM_MAGIC)?;
+    if header.encoding != encoding.label() {
+        return None;
+    }
+    check(&header.key, key, bytes)?;
+
+    let raw = read_exact_vec(&mut reader, header.rows_len.checked_mul(8)?)?;
+    let rows = raw.chunks_exact(8)
+        .map(|c| u64::from_le_bytes(c.try_into().unwrap()))
+        .collect();
+    TrigramIndex::from_parts(header.blocks, rows)
+}
+
+pub fn store_trigrams(key: &FileKey, encoding: FileEncoding, index: &TrigramIndex) {
+    let Some(path) = cache_path(key, "tri") else { return };
+    let header = TrigramHeader {
+        key: key.clone(),
+        encoding: encoding.label().to_string(),
+        blocks: index.blocks(),
+        rows_len: index.rows().len(),
+    };
+    write_atomic(&path, |w| {
+        write_header(w, TRIGRAM_MAGIC, &header)?;
+        for row in index.rows() {
+            w.write_all(&row.to_le_bytes())?;
+        }
+        Ok(())
+    });
+}
+
+// ---------------------------------------------------------------------------
+// 切分正则命中
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct HitsEntry {
+    regex: String,
+    hits_len: usize, // 命中区的字节数：相邻命中行号之差的 LEB128 变长编码
+}
+
+#[derive(Serialize, Deserialize)]
+struct HitsHeader {
+    key: FileKey,
+    line_count: usize,
+    entries: Vec<HitsEntry>,
+}
+
+// 读取全部条目：(文件头, 各正则的命中行)
+fn read_hits_file(key: &FileKey) -> Option<(HitsHeader, Vec<Vec<usize>>)> {
+    let path = cache_path(key, "hits")?;
+    let mut reader = open_cache(&path)?;
+    let header: HitsHeader = read_header(&mut reader, HITS_MAGIC)?;
+    let mut all = Vec::with_capacity(header.entries.len());
+    for entry in &header.entries {
+        let raw = read_exact_vec(&mut reader, entry.hits_len)?;
+        let mut hits = Vec::new();
+        let (mut pos, mut line) = (0usize, 0usize);
+        while pos < raw.len() {
+            let (delta, len) = read_varint(&raw[pos..])?;
+            line += delta as usize;
+            hits.push(line);
+            pos += len;
+        }
+        all.push(hits);
+    }
+    Some((header, all))
+}
+
+// 返回前 lines 行内的命中；缓存覆盖的行数不足（文件追加时末行可能被续写，不计入覆盖范围）则未命中
+pub fn load_hits(key: &FileKey, bytes: &[u8], regex: &str, lines: usize) -> Option<Vec<usize>> {
+    let (header, all) = read_hits_file(key)?;
+    let covered = match check(&header.key, key, bytes)? {
+        Validity::Exact => header.line_count,
+        Validity::Appended => header.line_count.saturating_sub(1),
+    };
+    if covered < lines {
+        return None;
+    }
+    let i = header.entries.iter().position(|e| e.regex == regex)?;
+    let mut hits = all.into_iter().nth(i)?;
+    hits.truncate(hits.partition_point(|&line| line < lines));
+    Some(hits)
+}
+
+// 保存各切分正则在全部 line_count 行内的命中；文件未变化时保留其他正则的已有条目
+pub fn store_hits(key: &FileKey, line_count: usize, found: &[(&str, &[usize])]) {
+    let Some(path) = cache_path(key, "hits") else { return };
+    let mut entries: Vec<(String, Vec<u8>)> = Vec::new();
+    if let Some((header, all)) = read_hits_file(key).filter(|(h, _)| h.key == *key && h.line_count == line_count) {
+        for (entry, hits) in header.entries.into_iter().zip(all) {
+            if !found.iter().any(|(regex, _)| *regex == entry.regex) {
+                entries.push((entry.regex, encode_lines(&hits)));
+            }
+        }
+    }
+    for (regex, hits) in found {
+        entries.push((regex.to_string(), encode_lines(hits)));
+    }
+    if entries.len() > MAX_SESSION_ENTRIES {
+        entries.drain(..entries.len() - MAX_SESSION_ENTRIES);
+    }
+
+    let header = HitsHeader {
+        key: key.clone(),
+        line_count,
+        entries: entries.iter().map(|(regex, raw)| HitsEntry { regex: regex.clone(), hits_len: raw.len() }).collect(),
+    };
+    write_atomic(&path, |w| {
+        write_header(w, HITS_MAGIC, &header)?;
+        for (_, raw) in &entries {
+            w.write_all(raw)?;
+        }
+        Ok(())
+    });
+}
+
+// 升序行号按相邻之差做变长编码
+fn encode_lines(lines: &[usize]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(lines.len() * 2);
+    let mut prev = 0usize;
+    for &line in lines {
+        write_varint(&mut out, (line - prev) as u64);
+        prev = line;
+    }
+    out
+}
+
+// ---------------------------------------------------------------------------
+// 会话边界
+// ---------------------------------------------------------------------------
+
+#[derive(Serialize, Deserialize)]
+struct SessionEntry {
+    boot_regex: String,
+    timestamp_regex: String,
+    time_gap_threshold: f64,
+    sessions: Vec<LogSession>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SessionFile {
+    key: FileKey,
+    entries: Vec<SessionEntry>,
+}
+
+fn read_session_file(key: &FileKey) -> Option<SessionFile> {
+    let path = cache_path(key, "sessions.json")?;
+    let file: SessionFile = serde_json::from_reader(BufReader::new(fs::File::open(path).ok()?)).ok()?;
+    // 会话边界依赖全文，文件变化后不再复用
+    (file.key == *key).then_some(file)
+}
+
+pub fn load_sessions(key: &FileKey, boot_regex: &str, timestamp_regex: &str, time_gap_threshold: f64) -> Option<Vec<LogSession>> {
+    read_session_file(key)?.entries.into_iter()
+        .find(|e| e.boot_regex == boot_regex && e.timestamp_regex == timestamp_regex && e.time_gap_threshold == time_gap_threshold)
+        .map(|e| e.sessions)
+}
+
+pub fn store_sessions(key: &FileKey, boot_regex: &str, timestamp_regex: &str, time_gap_threshold: f64, sessions: &[LogSession]) {
+    let Some(path) = cache_path(key, "sessions.json") else { return };
+    let mut file = read_session_file(key).unwrap_or(SessionFile { key: key.clone(), entries: Vec::new() });
+    file.entries.retain(|e| !(e.boot_regex == boot_regex && e.timestamp_regex == timestamp_regex && e.time_gap_threshold == time_gap_threshold));
+    file.entries.push(SessionEntry {
+        boot_regex: boot_regex.to_string(),
+        timestamp_regex: timestamp_regex.to_string(),
+        time_gap_threshold,
+        sessions: sessions.to_vec(),
+    });
+    if file.entries.len() > MAX_SESSION_ENTRIES {
+        file.entries.remove(0);
+    }
+    write_atomic(&path, |w| serde_json::to_writer(w, &file).map_err(io::Error::other));
+}
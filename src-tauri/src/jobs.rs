@@ -0,0 +1,158 @@
+// 后台任务：耗时命令登记为任务，通过 `job-progress` 事件汇报进度，前端可随时取消
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+pub type JobId = u64;
+
+// 进度事件的最小间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
+// 逐行处理时每隔多少行累计一次进度并检查取消标志
+const TICK_STRIDE: usize = 4096;
+
+/// 任务进度事件 (`job-progress`)；finished 为 true 时任务已结束（完成、失败或取消）
+#[derive(Debug, Serialize, Clone)]
+pub struct JobProgress {
+    job_id: JobId,
+    kind: &'static str,
+    phase: &'static str,
+    done: u64,
+    total: u64,
+    finished: bool,
+    cancelled: bool,
+}
+
+#[derive(Default)]
+pub struct JobManager {
+    next_id: AtomicU64,
+    flags: Mutex<HashMap<JobId, Arc<AtomicBool>>>, // 进行中（或已分配尚未开始）的任务及其取消标志
+}
+
+impl JobManager {
+    // 预先分配任务号：前端拿到后随命令一起传入，命令返回前即可取消
+    pub fn create(&self) -> JobId {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        self.flags.lock().unwrap().insert(id, Arc::new(AtomicBool::new(false)));
+        id
+    }
+
+    pub fn cancel(&self, id: JobId) -> bool {
+        match self.flags.lock().unwrap().get(&id) {
+            Some(flag) => {
+                flag.store(true, Ordering::Relaxed);
+                true
+            }
+            None => false,
+        }
+    }
+
+    // 开始执行任务；未传入任务号时自动分配（此时无法从前端取消）
+    pub fn start<'a>(
+        &'a self,
+        id: Option<JobId>,
+        kind: &'static str,
+        on_progress: impl Fn(JobProgress) + Send + Sync + 'a,
+    ) -> Job<'a> {
+        let id = id.unwrap_or_else(|| self.create());
+        let cancelled = self.flags.lock().unwrap()
+            .entry(id)
+            .or_insert_with(|| Arc::new(AtomicBool::new(false)))
+            .clone();
+        Job {
+            id,
+            kind,
+            manager: self,
+            cancelled,
+            phase: Mutex::new(""),
+            done: AtomicU64::new(0),
+            total: AtomicU64::new(0),
+            last_emit: Mutex::new(Instant::now()),
+            on_progress: Box::new(on_progress),
+        }
+    }
+}
+
+/// 正在执行的任务；析构时发送结束事件并从管理器中移除
+pub struct Job<'a> {
+    id: JobId,
+    kind: &'static str,
+    manager: &'a JobManager,
+    cancelled: Arc<AtomicBool>,
+    phase: Mutex<&'static str>,
+    done: AtomicU64,
+    total: AtomicU64,
+    last_emit: Mutex<Instant>,
+    on_progress: Box<dyn Fn(JobProgress) + Send + Sync + 'a>,
+}
+
+impl Job<'_> {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    // 已取消时返回错误，用 `?` 提前结束命令
+    pub fn check(&self) -> Result<(), String> {
+        if self.is_cancelled() {
+            Err(format!("Job {} cancelled", self.id))
+        } else {
+            Ok(())
+        }
+    }
+
+    // 进入新阶段（如 "index"、"sessions"），进度从 0 开始计
+    pub fn set_phase(&self, phase: &'static str, total: u64) {
+        *self.phase.lock().unwrap() = phase;
+        self.done.store(0, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.emit(false);
+    }
+
+    // 直接设置当前阶段的进度（如解压的字节数）
+    pub fn report(&self, done: u64, total: u64) {
+        self.done.store(done, Ordering::Relaxed);
+        self.total.store(total, Ordering::Relaxed);
+        self.maybe_emit();
+    }
+
+    // 在逐行的并行循环中调用：每 TICK_STRIDE 行累计一次进度；返回 true 表示任务已被取消
+    pub fn tick(&self, idx: usize) -> bool {
+        if idx.is_multiple_of(TICK_STRIDE) {
+            self.done.fetch_add(TICK_STRIDE as u64, Ordering::Relaxed);
+            self.maybe_emit();
+        }
+        self.is_cancelled()
+    }
+
+    fn maybe_emit(&self) {
+        // 其他线程正在发送时直接跳过
+        let Ok(mut last) = self.last_emit.try_lock() else { return };
+        if last.elapsed() >= PROGRESS_INTERVAL {
+            *last = Instant::now();
+            drop(last);
+            self.emit(false);
+        }
+    }
+
+    fn emit(&self, finished: bool) {
+        let total = self.total.load(Ordering::Relaxed);
+        (self.on_progress)(JobProgress {
+            job_id: self.id,
+            kind: self.kind,
+            phase: *self.phase.lock().unwrap(),
+            done: self.done.load(Ordering::Relaxed).min(total),
+            total,
+            finished,
+            cancelled: self.is_cancelled(),
+        });
+    }
+}
+
+impl Drop for Job<'_> {
+    fn drop(&mut self) {
+        self.manager.flags.lock().unwrap().remove(&self.id);
+        self.emit(true);
+    }
+}
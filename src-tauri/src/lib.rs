@@ -1,18 +1,65 @@
+use std::borrow::Cow;
 use std::fs;
-use std::path::Path;
-use regex::{Regex, RegexBuilder};
+use std::path::{Path, PathBuf};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use tauri::{Manager, Emitter, State};
 use memmap2::Mmap;
 use rayon::prelude::*;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+mod archive;
+mod charset;
+mod context;
+mod decompress;
+mod format_detect;
+mod index_cache;
+mod jobs;
+mod line_index;
+mod literal;
+mod query;
+mod results;
+mod rotation;
+mod scope;
+mod search_options;
+mod session_split;
+mod spans;
+mod tail;
+mod time_range;
+mod timestamp;
+mod trigram;
+mod validate;
+
+/// 文档句柄：由 `parse_log_file` 返回，后续命令通过它定位已打开的日志
+pub type DocHandle = u64;
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct FileInfo {
+    handle: DocHandle,
     name: String,
     size: u64,
     lines: usize,
     sessions: usize,
+    encoding: String, // 实际使用的编码（自动识别或手动指定）
+}
+
+/// 轮转日志组中的一个源文件
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LogSource {
+    name: String,
+    first_line: usize, // 1-based，在拼接后日志中的起始行号
+    line_count: usize,
+}
+
+/// 某一行的出处
+#[derive(Debug, Serialize, Deserialize)]
+pub struct LineSource {
+    source: String,
+    line_number: usize, // 1-based，源文件内的行号
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -28,13 +75,42 @@ pub struct LogLine {
     line_number: usize,
     content: String,
     level: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    spans: Option<Vec<spans::MatchSpan>>, // 请求了命中规则时，各规则在 content（已去掉行尾换行，与搜索匹配的文本一致）上的命中区间
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    hit: Option<bool>, // 带上下文的结果中：命中行为 true，上下文行为 false
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    group_start: bool, // 带上下文的结果中开始新的一组，前端在其上方画分隔线
+}
+
+impl LogLine {
+    fn new(index: &LogIndex, idx: usize, content: String, span_matcher: Option<&spans::SpanMatcher>) -> Self {
+        LogLine {
+            line_number: idx + 1,
+            spans: span_matcher.map(|m| m.spans(&content)),
+            content,
+            level: index.line_level(idx),
+            hit: None,
+            group_start: false,
+        }
+    }
+
+    // 结果集中的一行：带上下文时标出命中与分组
+    fn in_result(mut self, result: &results::ResultSet) -> Self {
+        if result.has_context() {
+            let idx = self.line_number - 1;
+            self.hit = Some(result.is_hit(idx));
+            self.group_start = result.starts_group(idx);
+        }
+        self
+    }
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ParsedLog {
     sessions: Vec<LogSession>,
     line_count: usize,
-    levels: Vec<Option<String>>,
+    levels: line_index::LevelRuns, // 游程编码的级别，避免逐行传输字符串
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -42,11 +118,55 @@ pub enum FileEncoding {
     Utf8,
     Utf16Le,
     Utf16Be,
+    Gb18030, // 兼容 GBK / GB2312
+    Big5,
+    ShiftJis,
+    Latin1,
+}
+
+impl FileEncoding {
+    // 手动指定编码时接受的名称（不区分大小写）
+    fn from_label(label: &str) -> Option<Self> {
+        match label.trim().to_ascii_lowercase().as_str() {
+            "utf-8" | "utf8" => Some(FileEncoding::Utf8),
+            "utf-16le" | "utf16le" => Some(FileEncoding::Utf16Le),
+            "utf-16be" | "utf16be" => Some(FileEncoding::Utf16Be),
+            "gb18030" | "gbk" | "gb2312" | "cp936" => Some(FileEncoding::Gb18030),
+            "big5" => Some(FileEncoding::Big5),
+            "shift_jis" | "shift-jis" | "sjis" | "cp932" => Some(FileEncoding::ShiftJis),
+            "latin1" | "latin-1" | "iso-8859-1" => Some(FileEncoding::Latin1),
+            _ => None,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            FileEncoding::Utf8 => "utf-8",
+            FileEncoding::Utf16Le => "utf-16le",
+            FileEncoding::Utf16Be => "utf-16be",
+            FileEncoding::Gb18030 => "gb18030",
+            FileEncoding::Big5 => "big5",
+            FileEncoding::ShiftJis => "shift_jis",
+            FileEncoding::Latin1 => "latin1",
+        }
+    }
+}
+
+// 解析命令参数中的编码覆盖；空字符串表示自动识别
+fn parse_encoding_override(label: Option<&str>) -> Result<Option<FileEncoding>, String> {
+    match label.filter(|l| !l.trim().is_empty()) {
+        Some(l) => FileEncoding::from_label(l).map(Some).ok_or_else(|| format!("Unknown encoding: {}", l)),
+        None => Ok(None),
+    }
 }
 
 fn bytes_to_string_with_encoding(bytes: &[u8], encoding: FileEncoding) -> String {
     match encoding {
         FileEncoding::Utf8 => String::from_utf8_lossy(bytes).to_string(),
+        FileEncoding::Gb18030 => encoding_rs::GB18030.decode_without_bom_handling(bytes).0.into_owned(),
+        FileEncoding::Big5 => encoding_rs::BIG5.decode_without_bom_handling(bytes).0.into_owned(),
+        FileEncoding::ShiftJis => encoding_rs::SHIFT_JIS.decode_without_bom_handling(bytes).0.into_owned(),
+        FileEncoding::Latin1 => bytes.iter().map(|&b| b as char).collect(),
         FileEncoding::Utf16Le => {
             let u16_data: Vec<u16> = bytes.chunks_exact(2)
                 .map(|c| u16::from_le_bytes([c[0], c[1]]))
@@ -62,17 +182,220 @@ fn bytes_to_string_with_encoding(bytes: &[u8], encoding: FileEncoding) -> String
     }
 }
 
+// 被映射的源文件；轮转日志拼接时，一个索引由多个分段首尾相连组成
+#[derive(Clone)]
+pub struct Segment {
+    mmap: Arc<Mmap>, // 跟随模式复制索引时与旧索引共享映射
+    first_line: usize, // 本分段首行的全局行号 (0-based)
+    encoding: FileEncoding,
+    path: PathBuf, // 实际映射的文件（跟随模式重新映射时使用）
+    name: String,  // 原始来源路径，用于展示行的出处
+    temp_file: Option<Arc<decompress::TempLogFile>>, // 压缩日志解压出的临时文件，需在 mmap 之后释放
+    cache_key: Option<index_cache::FileKey>, // 持久化缓存的文件身份；临时文件不缓存
+}
+
 // 核心索引结构
 pub struct LogIndex {
-    mmap: Mmap,
-    offsets: Vec<usize>, // 每行起始位置的字节偏移
-    levels: Vec<Option<String>>, // 每行的日志级别（预处理）
-    encoding: FileEncoding,
+    segments: Vec<Segment>,
+    offsets: line_index::LineOffsets, // 每行起始位置在所属分段内的字节偏移（块编码）
+    levels: line_index::LevelCodes, // 每行的日志级别代码（预处理）
+    level_re: Option<Regex>, // 级别正则（跟随模式增量提取时复用）
+    encoding_override: Option<FileEncoding>, // 手动指定的编码，重建索引时沿用
+    timestamp_options: timestamp::TimestampOptions, // 时间戳的时区、年份与格式设置，重建索引时沿用
+    line_cache: line_index::LineCache, // 按正则缓存的切分命中与时间戳
+    trigrams: Mutex<Option<Arc<trigram::TrigramIndex>>>, // 后台建立的 trigram 索引，建好前搜索全量扫描
 }
 
+impl LogIndex {
+    fn line_count(&self) -> usize {
+        self.offsets.len()
+    }
+
+    // 首个分段的编码，用于向前端展示
+    fn encoding(&self) -> FileEncoding {
+        self.segments.first().map(|s| s.encoding).unwrap_or(FileEncoding::Utf8)
+    }
+
+    // 单文件文档的缓存身份；会话与时间戳依赖全文，只对单个普通文件缓存
+    fn document_cache_key(&self) -> Option<&index_cache::FileKey> {
+        match self.segments.as_slice() {
+            [only] => only.cache_key.as_ref(),
+            _ => None,
+        }
+    }
+
+    // 各源文件中最晚的修改时间，作为推断时间戳年份的参考
+    fn modified(&self) -> Option<std::time::SystemTime> {
+        // 压缩日志映射的是解压出的临时文件，优先取原文件的修改时间
+        self.segments.iter()
+            .filter_map(|s| fs::metadata(&s.name).or_else(|_| fs::metadata(&s.path)).and_then(|m| m.modified()).ok())
+            .max()
+    }
+
+    fn total_bytes(&self) -> u64 {
+        self.segments.iter().map(|s| s.mmap.len() as u64).sum()
+    }
+
+    // 行号 (0-based) 所在的分段；空文件分段不含任何行，会被自然跳过
+    fn segment_index(&self, idx: usize) -> usize {
+        if self.segments.len() == 1 {
+            0
+        } else {
+            self.segments.partition_point(|s| s.first_line <= idx).saturating_sub(1)
+        }
+    }
+
+    fn segment_end_line(&self, seg: usize) -> usize {
+        self.segments.get(seg + 1).map(|s| s.first_line).unwrap_or(self.offsets.len())
+    }
+
+    // 行的原始字节（包含换行符）
+    fn line_bytes(&self, idx: usize) -> &[u8] {
+        let seg_idx = self.segment_index(idx);
+        let seg = &self.segments[seg_idx];
+        let start = self.offsets.get(idx);
+        let end = if idx + 1 < self.segment_end_line(seg_idx) { self.offsets.get(idx + 1) } else { seg.mmap.len() };
+        &seg.mmap[start..end]
+    }
+
+    fn line_encoding(&self, idx: usize) -> FileEncoding {
+        self.segments[self.segment_index(idx)].encoding
+    }
+
+    // 解码后的整行（包含换行符）
+    fn line_str(&self, idx: usize) -> String {
+        bytes_to_string_with_encoding(self.line_bytes(idx), self.line_encoding(idx))
+    }
+
+    // 解码后的行内容，去掉末尾的 \r\n
+    fn line_text(&self, idx: usize) -> String {
+        let encoding = self.line_encoding(idx);
+        bytes_to_string_with_encoding(trim_line_ending(self.line_bytes(idx), encoding), encoding)
+    }
+
+    // 查询匹配用的行视图：去掉末尾的 \r\n，使 $ 锚定在行尾而不是换行符之后
+    fn line_view(&self, idx: usize) -> query::LineView<'_> {
+        let encoding = self.line_encoding(idx);
+        query::LineView::new(trim_line_ending(self.line_bytes(idx), encoding), encoding, self.levels.get(idx))
+    }
+
+    // 行的 UTF-8 文本（包含换行符）：UTF-8 文件直接借用映射，其他编码解码
+    fn line_utf8(&self, idx: usize) -> Cow<'_, [u8]> {
+        match self.line_encoding(idx) {
+            FileEncoding::Utf8 => Cow::Borrowed(self.line_bytes(idx)),
+            encoding => Cow::Owned(bytes_to_string_with_encoding(self.line_bytes(idx), encoding).into_bytes()),
+        }
+    }
+
+    // trigram 索引给出的候选块；索引尚未建立或查询提取不出片段时返回 None
+    fn search_candidates(&self, query: &trigram::TrigramQuery) -> Option<trigram::Candidates> {
+        self.trigrams.lock().unwrap().as_ref()?.candidates(query)
+    }
+
+    fn line_level(&self, idx: usize) -> Option<String> {
+        self.levels.get(idx).map(str::to_string)
+    }
+
+    // 跟随模式：最后一个分段只被追加，从原末行起（末行可能只写了一半）一次扫描出新行的偏移、级别以及已缓存的切分命中与时间戳
+    // 返回首个重新扫描的行
+    fn extend_lines(&mut self) -> usize {
+        let Some(resume_at) = self.offsets.pop() else { return 0 };
+        let Some(last) = self.segments.last() else { return 0 };
+        let (mmap, encoding) = (last.mmap.clone(), last.encoding);
+        let from = self.offsets.len();
+        let (splitters, timestamp) = self.line_cache.patterns();
+        let splitters: Vec<&Regex> = splitters.iter().take(64).collect();
+        let mut levels = std::mem::take(&mut self.levels);
+        levels.truncate(from);
+
+        let scan = LineScan {
+            level_re: self.level_re.as_ref(),
+            level_from: from,
+            splitters: &splitters,
+            hits_from: from,
+            timestamp: timestamp.as_ref(),
+            timestamp_from: from,
+        };
+        let scanned = scan_new_lines(&mmap, resume_at, encoding, &scan, &mut self.offsets, &mut levels, None);
+
+        for (re, found) in splitters.iter().zip(scanned.hits) {
+            let mut hits = self.line_cache.hits(re).map(|h| h.to_vec()).unwrap_or_default();
+            hits.truncate(hits.partition_point(|&idx| idx < from));
+            hits.extend(found);
+            self.line_cache.store_hits(re, Arc::new(hits));
+        }
+        if let Some(extractor) = &timestamp {
+            let mut values = self.line_cache.timestamps(extractor).map(|ts| (*ts).clone()).unwrap_or_default();
+            values.truncate(from);
+            values.extend(scanned.timestamps);
+            self.line_cache.store_timestamps(extractor, Arc::new(values));
+        }
+        self.levels = levels;
+        from
+    }
+
+    // 行的出处：(来源文件, 文件内行号 1-based)
+    fn line_source(&self, idx: usize) -> (&str, usize) {
+        let seg = &self.segments[self.segment_index(idx)];
+        (&seg.name, idx - seg.first_line + 1)
+    }
+}
+
+// 掐掉换行符 (编码相关的)
+fn trim_line_ending(bytes: &[u8], encoding: FileEncoding) -> &[u8] {
+    let mut end_pos = bytes.len();
+    match encoding {
+        FileEncoding::Utf16Le | FileEncoding::Utf16Be => {
+            while end_pos >= 2 {
+                let b1 = bytes[end_pos - 2];
+                let b2 = bytes[end_pos - 1];
+                if (encoding == FileEncoding::Utf16Le && (b1 == 0x0A || b1 == 0x0D) && b2 == 0x00) ||
+                   (encoding == FileEncoding::Utf16Be && b1 == 0x00 && (b2 == 0x0A || b2 == 0x0D)) {
+                    end_pos -= 2;
+                } else {
+                    break;
+                }
+            }
+        }
+        _ => {
+            while end_pos > 0 && (bytes[end_pos-1] == b'\n' || bytes[end_pos-1] == b'\r') {
+                end_pos -= 1;
+            }
+        }
+    }
+    &bytes[..end_pos]
+}
+
+// 文档注册表：支持同时打开多个日志（例如设备日志与服务端日志并排）
 #[derive(Default)]
 pub struct AppState {
-    pub current_index: Mutex<Option<Arc<LogIndex>>>,
+    pub documents: Mutex<HashMap<DocHandle, Arc<LogIndex>>>,
+    next_handle: AtomicU64,
+    tails: Mutex<HashMap<DocHandle, Arc<AtomicBool>>>, // 跟随模式的停止标志
+    jobs: jobs::JobManager, // 后台任务（进度与取消）
+    results: results::ResultRegistry, // 过滤/搜索结果集
+}
+
+impl AppState {
+    fn get_index(&self, handle: DocHandle) -> Result<Arc<LogIndex>, String> {
+        self.documents.lock().unwrap().get(&handle).cloned()
+            .ok_or_else(|| format!("No file opened for handle {}. Call parse_log_file first.", handle))
+    }
+
+    // 注册索引；传入已有句柄时原地替换（重新加载同一文件），否则分配新句柄
+    // 调用方给出的句柄必须已经打开，不能借此占用尚未分配的句柄
+    fn register(&self, index: LogIndex, handle: Option<DocHandle>) -> Result<DocHandle, String> {
+        let mut docs = self.documents.lock().unwrap();
+        let handle = match handle {
+            Some(handle) if !docs.contains_key(&handle) => {
+                return Err(format!("No file opened for handle {}", handle));
+            }
+            Some(handle) => handle,
+            None => self.next_handle.fetch_add(1, Ordering::Relaxed) + 1,
+        };
+        docs.insert(handle, Arc::new(index));
+        Ok(handle)
+    }
 }
 
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
@@ -86,244 +409,842 @@ fn greet(name: &str) -> String {
 // -----------------------------------------------------------------------------
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn parse_log_file(
     path: String, 
     boot_regex: String, 
     level_regex: String,
+    timestamp_regex: Option<String>, // 可选：按时间间隙切分会话时的时间戳正则，建索引时一并解析
+    handle: Option<DocHandle>, // 可选：在已有句柄上重新加载，而不是新开一个文档
+    encoding: Option<String>, // 可选：手动指定编码（如 "gbk"），为空时自动识别
+    timestamp_options: Option<timestamp::TimestampOptions>, // 可选：时间戳的时区、年份与格式，缺省自动识别
+    job_id: Option<jobs::JobId>, // 可选：create_job 分配的任务号，用于进度与取消
+    app: tauri::AppHandle,
     state: State<'_, AppState>
 ) -> Result<FileInfo, String> {
-    let file = fs::File::open(&path).map_err(|e| e.to_string())?;
-    let mmap = unsafe { Mmap::map(&file).map_err(|e| e.to_string())? };
-    let bytes = &mmap[..];
+    // 先登记任务：参数校验失败提前返回时，任务随 job 析构一并移除
+    let job_app = app.clone();
+    let job = state.jobs.start(job_id, "parse_log_file", move |progress| {
+        let _ = job_app.emit("job-progress", progress);
+    });
+    let encoding = parse_encoding_override(encoding.as_deref())?;
+    let timestamp_options = timestamp_options.unwrap_or_default();
+    timestamp::TimestampParser::new(&timestamp_options, None)?;
+    let (index, sessions_count) = build_log_index(&path, &boot_regex, &level_regex, timestamp_regex.as_deref().unwrap_or(""), &timestamp_options, encoding, Some(&job), &mut |progress| {
+        job.report(progress.bytes_read, progress.total_bytes);
+        let _ = app.emit("decompress-progress", progress);
+    })?;
+    let encoding = index.encoding().label().to_string();
+    let total_bytes = index.total_bytes();
+    let line_count = index.line_count();
+
+    // 保存到文档注册表
+    let handle = state.register(index, handle)?;
 
-    // 0. 编码检测 (BOM)
-    let (encoding, start_offset) = if bytes.starts_with(&[0xff, 0xfe]) {
-        (FileEncoding::Utf16Le, 2)
-    } else if bytes.starts_with(&[0xfe, 0xff]) {
-        (FileEncoding::Utf16Be, 2)
-    } else if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
-        (FileEncoding::Utf8, 3)
-    } else {
-        (FileEncoding::Utf8, 0)
-    };
-    
-    // 1. 并行寻找换行符，记录每行起始偏移
-    let mut offsets = vec![start_offset]; 
-    
-    let mut newline_offsets: Vec<usize> = match encoding {
-        FileEncoding::Utf16Le => {
-            // UTF-16 LE: 寻找 0x0A 并且确认它是偶数索引（相对于 start_offset）
-            (start_offset..bytes.len()).into_par_iter()
-                .filter(|&idx| bytes[idx] == 0x0A && (idx - start_offset) % 2 == 0)
-                .map(|idx| idx + 2) // \n\0 后面的位置
-                .collect()
-        }
-        FileEncoding::Utf16Be => {
-            // UTF-16 BE: 寻找 0x0A 并且确认它是奇数索引
-            (start_offset..bytes.len()).into_par_iter()
-                .filter(|&idx| bytes[idx] == 0x0A && (idx - start_offset) % 2 == 1)
-                .map(|idx| idx + 1)
-                .collect()
-        }
-        _ => {
-            // UTF-8
-            (start_offset..bytes.len()).into_par_iter()
-                .filter(|&idx| bytes[idx] == b'\n')
-                .map(|idx| idx + 1)
-                .collect()
+    let file_name = Path::new(&path).file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown")
+        .to_string();
+
+    Ok(FileInfo {
+        handle,
+        name: file_name,
+        size: total_bytes,
+        lines: line_count,
+        sessions: sessions_count,
+        encoding,
+    })
+}
+
+#[tauri::command]
+async fn close_log_file(handle: DocHandle, state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(stop) = state.tails.lock().unwrap().remove(&handle) {
+        stop.store(true, Ordering::Relaxed);
+    }
+    state.documents.lock().unwrap().remove(&handle);
+    state.results.release_document(handle);
+    Ok(())
+}
+
+// 识别格式时最多读取的字节数
+const FORMAT_SAMPLE_LEN: u64 = 4 * 1024 * 1024;
+
+// 识别日志格式：对文件开头取样，推荐时间戳、级别与启动标识正则及各自的置信度，供打开文件前填写
+#[tauri::command]
+async fn detect_log_format(
+    path: String,
+    timestamp_options: Option<timestamp::TimestampOptions>, // 可选：解释时间戳所用的时区、年份
+) -> Result<format_detect::FormatReport, String> {
+    let source = Path::new(&path);
+    if archive::detect_archive(source).map_err(|e| e.to_string())?.is_some() {
+        return Err("This file is an archive. Detect the format of its members instead.".to_string());
+    }
+    let text = rotation::head_text(source, FORMAT_SAMPLE_LEN).ok_or_else(|| format!("Cannot read {}", path))?;
+    let modified = fs::metadata(source).and_then(|m| m.modified()).ok();
+    format_detect::detect(&text, &timestamp_options.unwrap_or_default(), modified)
+}
+
+#[tauri::command]
+async fn list_archive_members(path: String) -> Result<Vec<archive::ArchiveMember>, String> {
+    let source = Path::new(&path);
+    let kind = archive::detect_archive(source).map_err(|e| e.to_string())?
+        .ok_or("Not a zip or tar archive")?;
+    archive::list_members(source, kind)
+}
+
+// 直接打开压缩包内的成员：逐个解出到临时文件并建立索引，每个成员对应一个文档句柄
+#[tauri::command]
+async fn open_archive_members(
+    path: String,
+    members: Vec<String>,
+    boot_regex: String,
+    level_regex: String,
+    encoding: Option<String>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<Vec<FileInfo>, String> {
+    let source = Path::new(&path);
+    let kind = archive::detect_archive(source).map_err(|e| e.to_string())?
+        .ok_or("Not a zip or tar archive")?;
+    let archive_name = source.file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("archive")
+        .to_string();
+
+    let level_re = compile_level_regex(&level_regex);
+    let boot_re = compile_boot_regex(&boot_regex);
+    let splitters: Vec<&Regex> = boot_re.iter().collect();
+    let encoding = parse_encoding_override(encoding.as_deref())?;
+
+    let mut infos = Vec::new();
+    for (member, temp) in archive::extract_members(source, kind, &members)? {
+        // 成员本身可能还是压缩日志（如 tar 包里的 messages.1.gz）
+        let mut source = prepare_source(temp.path(), &mut |progress| {
+            let _ = app.emit("decompress-progress", progress);
+        })?;
+        if source.temp_file.is_none() {
+            source.temp_file = Some(Arc::new(temp));
         }
-    };
-    
-    newline_offsets.sort_unstable();
-    offsets.extend(newline_offsets);
+        let index = index_sources(vec![source], level_re.clone(), &splitters, None, encoding, None)?;
+
+        let sessions_count = boot_session_count(&index, boot_re.as_ref(), None)?;
+        let size = index.total_bytes();
+        let lines = index.line_count();
+        let index_encoding = index.encoding().label().to_string();
+        let handle = state.register(index, None)?;
+
+        infos.push(FileInfo {
+            handle,
+            name: format!("{}!{}", archive_name, member),
+            size,
+            lines,
+            sessions: sessions_count,
+            encoding: index_encoding,
+        });
+    }
+
+    Ok(infos)
+}
 
-    if offsets.last() == Some(&bytes.len()) {
-        offsets.pop(); 
+// 打开轮转日志组：目录或通配符匹配到的文件按时间先后首尾相连，作为一个文档，会话可以跨文件
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn open_rotated_logs(
+    spec: String, // 目录，或 /var/log/app.log* 这样的通配符
+    boot_regex: String,
+    level_regex: String,
+    timestamp_regex: String, // 无法按轮转后缀排序时，用于读取各文件首条时间戳
+    handle: Option<DocHandle>,
+    encoding: Option<String>,
+    timestamp_options: Option<timestamp::TimestampOptions>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<FileInfo, String> {
+    let encoding = parse_encoding_override(encoding.as_deref())?;
+    let timestamp_options = timestamp_options.unwrap_or_default();
+    timestamp::TimestampParser::new(&timestamp_options, None)?;
+    let paths = rotation::order_sources(rotation::expand_spec(&spec)?, &timestamp_regex, &timestamp_options)?;
+
+    let mut sources = Vec::with_capacity(paths.len());
+    for path in &paths {
+        sources.push(prepare_source(path, &mut |progress| {
+            let _ = app.emit("decompress-progress", progress);
+        })?);
     }
+    let boot_re = compile_boot_regex(&boot_regex);
+    let splitters: Vec<&Regex> = boot_re.iter().collect();
+    // 时间戳格式按最新的文件识别，年份也以它的修改时间为准
+    let timestamp = sources.last().and_then(|source| source_extractor(compile_timestamp_regex(&timestamp_regex), &source.path, &timestamp_options));
+    let mut index = index_sources(sources, compile_level_regex(&level_regex), &splitters, timestamp.as_ref(), encoding, None)?;
+    index.timestamp_options = timestamp_options;
+    let sessions_count = boot_session_count(&index, boot_re.as_ref(), None)?;
+    let encoding = index.encoding().label().to_string();
+    let total_bytes = index.total_bytes();
+    let line_count = index.line_count();
+    let handle = state.register(index, handle)?;
+
+    let base_name = Path::new(&spec).file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("logs");
 
-    let line_count = offsets.len();
-    
-    // Helper to convert bytes to string based on detected encoding
-    let bytes_to_str = |b: &[u8]| -> String {
-        match encoding {
-            FileEncoding::Utf8 => String::from_utf8_lossy(b).to_string(),
-            FileEncoding::Utf16Le => {
-                let u16_data: Vec<u16> = b.chunks_exact(2)
-                    .map(|c| u16::from_le_bytes([c[0], c[1]]))
-                    .collect();
-                String::from_utf16_lossy(&u16_data)
-            },
-            FileEncoding::Utf16Be => {
-                let u16_data: Vec<u16> = b.chunks_exact(2)
-                    .map(|c| u16::from_be_bytes([c[0], c[1]]))
-                    .collect();
-                String::from_utf16_lossy(&u16_data)
-            }
+    Ok(FileInfo {
+        handle,
+        name: format!("{} ({} files)", base_name, paths.len()),
+        size: total_bytes,
+        lines: line_count,
+        sessions: sessions_count,
+        encoding,
+    })
+}
+
+#[tauri::command]
+async fn list_log_sources(handle: DocHandle, state: State<'_, AppState>) -> Result<Vec<LogSource>, String> {
+    let index = state.get_index(handle)?;
+    Ok((0..index.segments.len()).map(|seg| {
+        let segment = &index.segments[seg];
+        LogSource {
+            name: segment.name.clone(),
+            first_line: segment.first_line + 1,
+            line_count: index.segment_end_line(seg) - segment.first_line,
         }
-    };
+    }).collect())
+}
 
-    // 2. 预分析：并行提取日志级别
-    let level_re = if !level_regex.is_empty() {
-        Regex::new(&level_regex).ok()
+#[tauri::command]
+async fn get_line_source(
+    handle: DocHandle,
+    line_number: usize, // 1-based
+    state: State<'_, AppState>
+) -> Result<LineSource, String> {
+    let index = state.get_index(handle)?;
+    if line_number == 0 || line_number > index.line_count() {
+        return Err(format!("Line {} out of range", line_number));
+    }
+    let (source, local_line) = index.line_source(line_number - 1);
+    Ok(LineSource {
+        source: source.to_string(),
+        line_number: local_line,
+    })
+}
+
+// 分配任务号：随 parse_log_file / search_log 等耗时命令传入，进度通过 `job-progress` 事件推送
+// 接收任务号的命令第一步就调用 jobs.start，此后任何提前返回都会随 Job 析构移除任务
+#[tauri::command]
+async fn create_job(state: State<'_, AppState>) -> Result<jobs::JobId, String> {
+    Ok(state.jobs.create())
+}
+
+// 取消任务：对应命令会尽快以 "Job N cancelled" 错误返回
+#[tauri::command]
+async fn cancel_job(job_id: jobs::JobId, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.jobs.cancel(job_id))
+}
+
+// 跟随模式：监视文件追加/截断/轮转，通过 `log-appended` 事件推送新增行范围
+#[tauri::command]
+async fn start_tail(
+    handle: DocHandle,
+    interval_ms: Option<u64>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<(), String> {
+    state.get_index(handle)?;
+
+    let mut tails = state.tails.lock().unwrap();
+    if tails.contains_key(&handle) {
+        return Ok(());
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    tails.insert(handle, stop.clone());
+
+    let interval = Duration::from_millis(interval_ms.unwrap_or(500).max(50));
+    tail::spawn_follower(app, handle, interval, stop);
+    Ok(())
+}
+
+#[tauri::command]
+async fn stop_tail(handle: DocHandle, state: State<'_, AppState>) -> Result<(), String> {
+    if let Some(stop) = state.tails.lock().unwrap().remove(&handle) {
+        stop.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+// 建立索引：mmap 文件、扫描换行，一次扫描预提取级别、启动标识与时间戳，并统计会话数
+#[allow(clippy::too_many_arguments)]
+fn build_log_index(
+    path: &str,
+    boot_regex: &str,
+    level_regex: &str,
+    timestamp_regex: &str,
+    timestamp_options: &timestamp::TimestampOptions,
+    encoding: Option<FileEncoding>,
+    job: Option<&jobs::Job>,
+    on_progress: &mut dyn FnMut(decompress::DecompressProgress),
+) -> Result<(LogIndex, usize), String> {
+    // 预分析：日志级别、启动标识与时间戳
+    let level_re = compile_level_regex(level_regex);
+    let boot_re = compile_boot_regex(boot_regex);
+
+    let source = Path::new(path);
+    if archive::detect_archive(source).map_err(|e| e.to_string())?.is_some() {
+        return Err("This file is an archive. Use list_archive_members / open_archive_members to open its members.".to_string());
+    }
+
+    if let Some(job) = job {
+        job.set_phase("decompress", 0);
+    }
+    let source = prepare_source(source, on_progress)?;
+    if let Some(job) = job {
+        job.check()?;
+        job.set_phase("index", 0);
+    }
+    let splitters: Vec<&Regex> = boot_re.iter().collect();
+    let timestamp = source_extractor(compile_timestamp_regex(timestamp_regex), &source.path, timestamp_options);
+    let mut index = index_sources(vec![source], level_re, &splitters, timestamp.as_ref(), encoding, job)?;
+    index.timestamp_options = timestamp_options.clone();
+    let sessions_count = boot_session_count(&index, boot_re.as_ref(), job)?;
+
+    Ok((index, sessions_count))
+}
+
+fn compile_level_regex(level_regex: &str) -> Option<Regex> {
+    if !level_regex.is_empty() {
+        Regex::new(level_regex).ok()
     } else {
         Regex::new(r"(?i)\[(DEBUG|INFO|WARN|ERROR|FATAL|NORM|TRACE|SUCCESS)\]").ok()
-    };
+    }
+}
 
-    let boot_re = if !boot_regex.is_empty() {
-        Regex::new(&boot_regex).ok()
+fn compile_boot_regex(boot_regex: &str) -> Option<Regex> {
+    if !boot_regex.is_empty() {
+        Regex::new(boot_regex).ok()
     } else {
         Regex::new(r"(?i)(system|boot|start)(ed|ing|up)").ok()
-    };
+    }
+}
 
-    // 预提取所有级别的 Logic
-    let levels: Vec<Option<String>> = (0..line_count).into_par_iter().map(|idx| {
-        let start = offsets[idx];
-        let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-        let line_bytes = &bytes[start..end];
-        let line_str = bytes_to_str(line_bytes);
-        
-        level_re.as_ref().and_then(|re| {
-            re.captures(&line_str).and_then(|cap| {
-                if cap.len() > 1 {
-                    cap.get(1).map(|m| m.as_str().to_uppercase())
-                } else {
-                    cap.get(0).map(|m| m.as_str().to_uppercase())
-                }
+// 时间戳正则为空表示不按时间切分
+fn compile_timestamp_regex(timestamp_regex: &str) -> Option<Regex> {
+    if !timestamp_regex.is_empty() {
+        Regex::new(timestamp_regex).ok()
+    } else {
+        None
+    }
+}
+
+// 建索引时预提取时间戳用的提取器，格式按文件开头的样本识别
+// 识别失败时不预提取，留到切分会话或分析时按全文样本识别并报告错误
+fn source_extractor(re: Option<Regex>, path: &Path, options: &timestamp::TimestampOptions) -> Option<timestamp::Extractor> {
+    let text = rotation::head_text(path, rotation::HEAD_SAMPLE_LEN)?;
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    timestamp::Extractor::detect_in(re?, &text, options, modified).ok()
+}
+
+// 识别格式时最多查看的行数
+const TIMESTAMP_SAMPLE_LINES: usize = 20_000;
+
+// 文档的时间戳提取器：正则与已缓存的相同时直接复用，否则从文档开头取样识别格式
+fn timestamp_extractor(index: &LogIndex, re: Regex) -> Result<timestamp::Extractor, String> {
+    if let Some(cached) = index.line_cache.extractor().filter(|ex| ex.regex().as_str() == re.as_str()) {
+        return Ok(cached);
+    }
+    let samples: Vec<String> = (0..index.line_count().min(TIMESTAMP_SAMPLE_LINES))
+        .filter_map(|idx| timestamp::capture(&re, &index.line_str(idx)).map(str::to_string))
+        .take(timestamp::MAX_SAMPLES)
+        .collect();
+    let parser = timestamp::TimestampParser::new(&index.timestamp_options, index.modified())?.detect(&samples)?;
+    Ok(timestamp::Extractor::new(re, parser))
+}
+
+// 待映射的源文件
+pub struct SourceFile {
+    path: PathBuf, // 实际映射的文件
+    name: String,  // 原始来源路径
+    temp_file: Option<Arc<decompress::TempLogFile>>,
+}
+
+impl SourceFile {
+    fn plain(path: &Path) -> Self {
+        SourceFile { path: path.to_path_buf(), name: path.to_string_lossy().to_string(), temp_file: None }
+    }
+}
+
+// 准备日志源：压缩日志先解压到临时文件，之后的索引、搜索与导出与普通文件完全一致
+fn prepare_source(
+    source: &Path,
+    on_progress: &mut dyn FnMut(decompress::DecompressProgress),
+) -> Result<SourceFile, String> {
+    match decompress::detect_compression(source).map_err(|e| e.to_string())? {
+        Some(kind) => {
+            let temp = decompress::decompress_to_temp(source, kind, &mut *on_progress)?;
+            Ok(SourceFile {
+                path: temp.path().to_path_buf(),
+                name: source.to_string_lossy().to_string(),
+                temp_file: Some(Arc::new(temp)),
             })
-        })
-    }).collect();
+        }
+        None => Ok(SourceFile::plain(source)),
+    }
+}
 
-    // 3. 计算会话数
-    let sessions_count = if let Some(ref re) = boot_re {
-        (0..line_count).into_par_iter().filter(|&idx| {
-            let start = offsets[idx];
-            let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-            let line_bytes = &bytes[start..end];
-            let line_str = bytes_to_str(line_bytes);
-            re.is_match(&line_str)
-        }).count() + 1
-    } else {
-        1
-    };
+// 启动标识的命中行数 + 1 即会话数；建索引时已一并扫描，这里通常直接命中缓存
+fn boot_session_count(index: &LogIndex, boot_re: Option<&Regex>, job: Option<&jobs::Job>) -> Result<usize, String> {
+    let Some(re) = boot_re else { return Ok(1) };
+    let (hits, _) = line_data(index, std::slice::from_ref(re), None, job)?;
+    Ok(hits[0].len() + 1)
+}
+
+// 依次映射各源文件并首尾相连地建立索引（轮转日志拼接时按时间先后传入）
+// 偏移之外的逐行数据（级别、切分正则命中、时间戳）由每个分段的一次并行扫描共同产出
+// encoding_override 为空时每个文件各自识别编码
+fn index_sources(
+    sources: Vec<SourceFile>,
+    level_re: Option<Regex>,
+    splitters: &[&Regex],
+    timestamp: Option<&timestamp::Extractor>,
+    encoding_override: Option<FileEncoding>,
+    job: Option<&jobs::Job>,
+) -> Result<LogIndex, String> {
+    let single = sources.len() == 1;
+    let mut segments = Vec::with_capacity(sources.len());
+    let mut offsets = line_index::LineOffsets::default();
+    let mut levels = line_index::LevelCodes::default();
+    let mut hits: Vec<Vec<usize>> = vec![Vec::new(); splitters.len()];
+    let mut timestamps = line_index::LineTimestamps::default();
+
+    for source in sources {
+        let file = fs::File::open(&source.path).map_err(|e| format!("{}: {}", source.name, e))?;
+        let mmap = unsafe { Mmap::map(&file).map_err(|e| e.to_string())? };
+        let bytes = &mmap[..];
+
+        // 优先使用持久化缓存；文件只被追加时只扫描新增部分
+        let cache_key = if source.temp_file.is_none() { index_cache::FileKey::compute(&source.path, bytes) } else { None };
+        let level_pattern = level_re.as_ref().map(|re| re.as_str());
+        let cached = cache_key.as_ref()
+            .and_then(|key| index_cache::load_index(key, bytes, encoding_override, level_pattern));
+
+        // 缓存中已有的行直接复用偏移；resume_at 为需要从字节中重新分行的起点（缓存的末行可能被续写，从它重新开始）
+        let cache_hit = cached.as_ref().is_some_and(|hit| hit.validity == index_cache::Validity::Exact);
+        let (encoding, mut seg_offsets, mut seg_levels, resume_at) = match cached {
+            Some(hit) if cache_hit => (hit.encoding, hit.offsets, hit.levels, None),
+            Some(mut hit) => {
+                let resume_at = hit.offsets.pop();
+                hit.levels.truncate(hit.offsets.len());
+                (hit.encoding, hit.offsets, hit.levels, resume_at)
+            }
+            None => {
+                // 编码检测 (BOM / 内容推断 / 手动指定)
+                let (encoding, start_offset) = resolve_encoding(bytes, encoding_override);
+                (encoding, line_index::LineOffsets::default(), line_index::LevelCodes::default(), Some(start_offset))
+            }
+        };
+        let known_lines = seg_offsets.len();
 
-    let mmap_len = bytes.len();
+        // 单个普通文件的时间戳同样可以从持久化缓存续用
+        let mut seg_timestamps = match (timestamp, cache_key.as_ref()) {
+            (Some(ex), Some(key)) if single => index_cache::load_timestamps(key, bytes, &ex.key()).unwrap_or_default(),
+            _ => line_index::LineTimestamps::default(),
+        };
+        seg_timestamps.truncate(known_lines);
+        let cached_timestamps = seg_timestamps.len();
+
+        // 切分正则（启动标识）的命中也从缓存续用；全部切分正则都有缓存时，已知的行不必重新解码
+        let cached_hits: Option<Vec<Vec<usize>>> = cache_key.as_ref()
+            .filter(|_| !splitters.is_empty() && known_lines > 0)
+            .and_then(|key| splitters.iter().map(|re| index_cache::load_hits(key, bytes, re.as_str(), known_lines)).collect());
+        let hits_from = if cached_hits.is_some() { known_lines } else { 0 };
+
+        // 1. 缓存提供了偏移的行：补齐缓存中缺少的级别、切分正则命中与时间戳
+        if let Some(job) = job {
+            job.set_phase("index", known_lines as u64);
+        }
+        let known_scan = LineScan {
+            level_re: level_re.as_ref(),
+            level_from: seg_levels.len(),
+            splitters,
+            hits_from,
+            timestamp,
+            timestamp_from: cached_timestamps,
+        };
+        let line_of = |idx: usize| {
+            let start = seg_offsets.get(idx);
+            let end = if idx + 1 < known_lines { seg_offsets.get(idx + 1) } else { resume_at.unwrap_or(bytes.len()) };
+            bytes_to_string_with_encoding(&bytes[start..end], encoding)
+        };
+        let mut scanned = scan_lines(known_lines, &line_of, &known_scan, &mut seg_levels, job);
+
+        // 2. 其余部分一次并行扫描：分行的同时提取级别、切分正则命中与时间戳
+        if let Some(resume_at) = resume_at {
+            if let Some(job) = job {
+                job.set_phase("index", (bytes.len() - resume_at) as u64);
+            }
+            let new_scan = LineScan {
+                level_re: level_re.as_ref(),
+                level_from: known_lines,
+                splitters,
+                hits_from: known_lines,
+                timestamp,
+                timestamp_from: known_lines,
+            };
+            let fresh = scan_new_lines(bytes, resume_at, encoding, &new_scan, &mut seg_offsets, &mut seg_levels, job);
+            for (all, found) in scanned.hits.iter_mut().zip(fresh.hits) {
+                all.extend(found);
+            }
+            scanned.timestamps.extend(fresh.timestamps);
+        }
+        if let Some(job) = job {
+            job.check()?;
+        }
+        let seg_lines = seg_offsets.len();
+
+        if let Some(key) = cache_key.as_ref().filter(|_| !cache_hit) {
+            index_cache::store_index(key, encoding, encoding_override, level_pattern, &seg_offsets, &seg_levels);
+        }
+        let seg_hits = match cached_hits {
+            Some(mut cached) => {
+                for (all, found) in cached.iter_mut().zip(scanned.hits) {
+                    all.extend(found);
+                }
+                cached
+            }
+            None => scanned.hits,
+        };
+        if let Some(key) = cache_key.as_ref().filter(|_| !splitters.is_empty() && (hits_from == 0 || known_lines < seg_lines)) {
+            let found: Vec<(&str, &[usize])> = splitters.iter().map(|re| re.as_str()).zip(seg_hits.iter().map(Vec::as_slice)).collect();
+            index_cache::store_hits(key, seg_lines, &found);
+        }
+        seg_timestamps.extend(scanned.timestamps);
+        if let (Some(ex), Some(key)) = (timestamp, cache_key.as_ref()) {
+            if single && cached_timestamps < seg_lines {
+                index_cache::store_timestamps(key, &ex.key(), &seg_timestamps);
+            }
+        }
+
+        let first_line = offsets.len();
+        for (all, seg_hits) in hits.iter_mut().zip(seg_hits) {
+            all.extend(seg_hits.into_iter().map(|idx| idx + first_line));
+        }
+        timestamps.extend(seg_timestamps.values().iter().copied());
+
+        segments.push(Segment {
+            first_line,
+            encoding,
+            path: source.path,
+            name: source.name,
+            temp_file: source.temp_file,
+            cache_key,
+            mmap: Arc::new(mmap),
+        });
+        if segments.len() == 1 {
+            (offsets, levels) = (seg_offsets, seg_levels);
+        } else {
+            offsets.extend(seg_offsets.iter());
+            levels.append(&seg_levels);
+        }
+    }
+
+    let line_cache = line_index::LineCache::default();
+    for (re, hits) in splitters.iter().zip(hits) {
+        line_cache.store_hits(re, Arc::new(hits));
+    }
+    if let Some(ex) = timestamp {
+        line_cache.store_timestamps(ex, Arc::new(timestamps));
+    }
 
-    // 保存到全局状态
-    let index = Arc::new(LogIndex {
-        mmap,
+    Ok(LogIndex {
+        segments,
         offsets,
         levels,
-        encoding,
+        level_re,
+        encoding_override,
+        timestamp_options: timestamp::TimestampOptions::default(),
+        line_cache,
+        trigrams: Mutex::default(),
+    })
+}
+
+fn bom_encoding(bytes: &[u8]) -> Option<(FileEncoding, usize)> {
+    if bytes.starts_with(&[0xff, 0xfe]) {
+        Some((FileEncoding::Utf16Le, 2))
+    } else if bytes.starts_with(&[0xfe, 0xff]) {
+        Some((FileEncoding::Utf16Be, 2))
+    } else if bytes.starts_with(&[0xef, 0xbb, 0xbf]) {
+        Some((FileEncoding::Utf8, 3))
+    } else {
+        None
+    }
+}
+
+// 判断编码，返回 (编码, 正文起始偏移)；优先看 BOM，没有 BOM 时按内容推断
+fn detect_encoding(bytes: &[u8]) -> (FileEncoding, usize) {
+    bom_encoding(bytes).unwrap_or_else(|| (charset::sniff(bytes), 0))
+}
+
+// 手动指定编码时只跳过与之相符的 BOM
+fn resolve_encoding(bytes: &[u8], forced: Option<FileEncoding>) -> (FileEncoding, usize) {
+    match forced {
+        Some(encoding) => {
+            let start = bom_encoding(bytes).filter(|(e, _)| *e == encoding).map(|(_, len)| len).unwrap_or(0);
+            (encoding, start)
+        }
+        None => detect_encoding(bytes),
+    }
+}
+
+// 并行扫描换行符时的分块大小（偶数，保证 UTF-16 的码元对齐），每轮处理 SCAN_ROUND_CHUNKS 块
+const SCAN_CHUNK: usize = 1 << 20;
+const SCAN_ROUND_CHUNKS: usize = 64;
+
+// 一次逐行扫描要产出的数据；各项 *_from 之前的行已由缓存提供，不再重复计算
+struct LineScan<'a> {
+    level_re: Option<&'a Regex>,
+    level_from: usize, // 从这一行起向 levels 追加级别代码
+    splitters: &'a [&'a Regex], // 需要记录命中行的切分正则（最多 64 个）
+    hits_from: usize,
+    timestamp: Option<&'a timestamp::Extractor>,
+    timestamp_from: usize,
+}
+
+struct LineScanOutput {
+    hits: Vec<Vec<usize>>, // 与 splitters 一一对应、hits_from 起的命中行 (0-based，升序)
+    timestamps: Vec<f64>,  // timestamp_from 起各行的时间戳，NaN 表示无
+}
+
+// 逐行扫描时每批并行处理的行数，限制中间结果的内存占用
+const SCAN_BATCH_LINES: usize = 256 * 1024;
+
+// 单行的扫描结果：(级别代码，未登记的新级别以 Err 带回), 切分命中位掩码, 时间戳
+type ScannedLine = (Option<Result<u8, String>>, u64, f64);
+
+// 每行只解码一次，同时提取级别、切分正则命中与时间戳；批内并行，批间按行号顺序汇总
+// 任务被取消时提前返回，结果不可用
+fn scan_lines(
+    line_count: usize,
+    line_of: &(dyn Fn(usize) -> String + Sync),
+    scan: &LineScan,
+    levels: &mut line_index::LevelCodes,
+    job: Option<&jobs::Job>,
+) -> LineScanOutput {
+    debug_assert!(scan.splitters.len() <= 64);
+    let want_hits = !scan.splitters.is_empty() && scan.hits_from < line_count;
+    let want_timestamps = scan.timestamp.is_some() && scan.timestamp_from < line_count;
+    let start = [
+        want_hits.then_some(scan.hits_from),
+        (scan.level_from < line_count).then_some(scan.level_from),
+        want_timestamps.then_some(scan.timestamp_from),
+    ].into_iter().flatten().min().unwrap_or(line_count);
+
+    let mut out = LineScanOutput { hits: vec![Vec::new(); scan.splitters.len()], timestamps: Vec::new() };
+    let mut batch_start = start;
+    while batch_start < line_count {
+        let batch_end = (batch_start + SCAN_BATCH_LINES).min(line_count);
+        let known = &*levels;
+        let batch: Vec<ScannedLine> = (batch_start..batch_end).into_par_iter().map(|idx| {
+            let need_level = idx >= scan.level_from;
+            let need_hits = want_hits && idx >= scan.hits_from;
+            let need_timestamp = want_timestamps && idx >= scan.timestamp_from;
+            if job.is_some_and(|j| j.tick(idx)) {
+                return (need_level.then_some(Ok(0)), 0, f64::NAN);
+            }
+            let decode = (need_level && scan.level_re.is_some()) || need_hits || need_timestamp;
+            let line = if decode { line_of(idx) } else { String::new() };
+            scan_line(&line, scan, known, need_level, need_hits, need_timestamp)
+        }).collect();
+
+        for (idx, (level, mut mask, timestamp)) in (batch_start..batch_end).zip(batch) {
+            if let Some(level) = level {
+                let code = level.unwrap_or_else(|new| levels.intern(&new));
+                levels.push_code(code);
+            }
+            while mask != 0 {
+                out.hits[mask.trailing_zeros() as usize].push(idx);
+                mask &= mask - 1;
+            }
+            if want_timestamps && idx >= scan.timestamp_from {
+                out.timestamps.push(timestamp);
+            }
+        }
+        if job.is_some_and(|j| j.is_cancelled()) {
+            break;
+        }
+        batch_start = batch_end;
+    }
+    out
+}
+
+// 单行的级别、切分命中位掩码与时间戳；不需要的项分别为 None、0、NaN
+fn scan_line(
+    line: &str,
+    scan: &LineScan,
+    known: &line_index::LevelCodes,
+    need_level: bool,
+    need_hits: bool,
+    need_timestamp: bool,
+) -> ScannedLine {
+    let level = need_level.then(|| match scan.level_re.and_then(|re| capture_level(re, line)) {
+        Some(level) => known.lookup(&level).ok_or(level),
+        None => Ok(0),
     });
-    
-    let mut current = state.current_index.lock().unwrap();
-    *current = Some(index);
+    let mask = scan.splitters.iter().enumerate()
+        .filter(|(_, re)| need_hits && re.is_match(line))
+        .fold(0u64, |mask, (bit, _)| mask | (1 << bit));
+    // 无法解析的时间戳同样记为 NaN，具体原因由 inspect_timestamps 报告
+    let timestamp = scan.timestamp.filter(|_| need_timestamp)
+        .and_then(|ex| ex.extract(line)?.ok())
+        .unwrap_or(f64::NAN);
+    (level, mask, timestamp)
+}
 
-    let file_name = Path::new(&path).file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown")
-        .to_string();
+// 从 start_offset 起一次扫描完成分行与逐行提取：按字节分块并行，每块找出块内的换行，
+// 就地解码起始于本块的行并提取级别、切分命中与时间戳（块内最后一行跨块时向后读到行尾）
+// 新行的起始偏移与级别按块顺序追加到 offsets / levels，返回的命中与时间戳从 offsets 原有的行数起
+// 任务被取消时提前返回，结果不可用
+fn scan_new_lines(
+    bytes: &[u8],
+    start_offset: usize,
+    encoding: FileEncoding,
+    scan: &LineScan,
+    offsets: &mut line_index::LineOffsets,
+    levels: &mut line_index::LevelCodes,
+    job: Option<&jobs::Job>,
+) -> LineScanOutput {
+    debug_assert!(scan.splitters.len() <= 64);
+    debug_assert_eq!(levels.len(), offsets.len());
+    let want_hits = !scan.splitters.is_empty();
+    let want_timestamps = scan.timestamp.is_some();
+    let decode = scan.level_re.is_some() || want_hits || want_timestamps;
+
+    // 换行符在码元内的位置：UTF-16 LE 为 \n\0 的第 0 字节，BE 为 \0\n 的第 1 字节
+    let newline_at = match encoding {
+        FileEncoding::Utf16Le => Some(0),
+        FileEncoding::Utf16Be => Some(1),
+        _ => None,
+    };
+    // [from, to) 内每个换行之后的位置
+    let line_ends = |from: usize, to: usize| -> Box<dyn Iterator<Item = usize> + '_> {
+        match newline_at {
+            Some(at) => Box::new((from..to).step_by(2)
+                .filter(move |&unit| unit + at < to && bytes[unit + at] == 0x0A)
+                .map(|unit| (unit + 2).min(bytes.len()))),
+            None => Box::new(memchr::memchr_iter(b'\n', &bytes[from..to]).map(move |i| from + i + 1)),
+        }
+    };
 
-    Ok(FileInfo {
-        name: file_name,
-        size: mmap_len as u64,
-        lines: line_count,
-        sessions: sessions_count,
-    })
+    let mut out = LineScanOutput { hits: vec![Vec::new(); scan.splitters.len()], timestamps: Vec::new() };
+    let mut round_start = start_offset;
+    while round_start < bytes.len() {
+        let round_end = (round_start + SCAN_CHUNK * SCAN_ROUND_CHUNKS).min(bytes.len());
+        let known = &*levels;
+        let chunks: Vec<(Vec<usize>, Vec<ScannedLine>)> = (round_start..round_end).step_by(SCAN_CHUNK)
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .map(|chunk_start| {
+                let chunk_end = (chunk_start + SCAN_CHUNK).min(round_end);
+                // 起始于本块的行：扫描起点，以及块内每个换行之后的位置；末尾换行之后没有内容时不算作新的一行
+                let mut starts: Vec<usize> = (chunk_start == start_offset).then_some(start_offset).into_iter()
+                    .chain(line_ends(chunk_start, chunk_end))
+                    .collect();
+                if starts.last() == Some(&bytes.len()) {
+                    starts.pop();
+                }
+                if job.is_some_and(|j| j.is_cancelled()) {
+                    return (Vec::new(), Vec::new());
+                }
+                let scanned = starts.iter().enumerate().map(|(i, &start)| {
+                    if !decode {
+                        return (Some(Ok(0)), 0, f64::NAN);
+                    }
+                    let end = match starts.get(i + 1) {
+                        Some(&next) => next,
+                        None => line_ends(start, bytes.len()).next().unwrap_or(bytes.len()),
+                    };
+                    let line = bytes_to_string_with_encoding(&bytes[start..end], encoding);
+                    scan_line(&line, scan, known, true, want_hits, want_timestamps)
+                }).collect();
+                (starts, scanned)
+            })
+            .collect();
+
+        for (starts, scanned) in chunks {
+            for (start, (level, mut mask, timestamp)) in starts.into_iter().zip(scanned) {
+                let idx = offsets.len();
+                offsets.push(start);
+                if let Some(level) = level {
+                    let code = level.unwrap_or_else(|new| levels.intern(&new));
+                    levels.push_code(code);
+                }
+                while mask != 0 {
+                    out.hits[mask.trailing_zeros() as usize].push(idx);
+                    mask &= mask - 1;
+                }
+                if want_timestamps && idx >= scan.timestamp_from {
+                    out.timestamps.push(timestamp);
+                }
+            }
+        }
+        if let Some(job) = job {
+            job.report((round_end - start_offset) as u64, (bytes.len() - start_offset) as u64);
+            if job.is_cancelled() {
+                break;
+            }
+        }
+        round_start = round_end;
+    }
+    out
+}
+
+// 级别正则的第一个捕获组（没有捕获组时取整个匹配），统一为大写
+fn capture_level(re: &Regex, line: &str) -> Option<String> {
+    let cap = re.captures(line)?;
+    let level = if cap.len() > 1 { cap.get(1) } else { cap.get(0) }?;
+    Some(level.as_str().to_uppercase())
 }
 
 #[tauri::command]
 async fn parse_log_content(
-    path: String,
+    handle: DocHandle,
     boot_regex: String,
     level_regex: String,
     timestamp_regex: String,
     time_gap_threshold: f64,
     state: State<'_, AppState>
 ) -> Result<ParsedLog, String> {
-    let index_opt = {
-        let current = state.current_index.lock().unwrap();
-        current.clone()
-    };
+    let index_opt = state.get_index(handle).ok();
 
     if let Some(index) = index_opt {
-        let bytes = &index.mmap[..];
-        let line_count = index.offsets.len();
-        
-        let mut sessions = Vec::new();
-        let mut current_session_start = 1;
-        let mut session_id = 0;
+        let line_count = index.line_count();
 
-        let boot_re = if !boot_regex.is_empty() {
-            Regex::new(&boot_regex).ok()
-        } else {
-            Regex::new(r"(?i)(system|boot|start)(ed|ing|up)").ok()
+        // 按时间切分时先识别时间戳格式，无法识别时报告错误，而不是静默地不按时间切分
+        let timestamp = match compile_timestamp_regex(&timestamp_regex).filter(|_| time_gap_threshold > 0.0) {
+            Some(re) => Some(timestamp_extractor(&index, re)?),
+            None => None,
         };
+        let timestamp_key = timestamp.as_ref().map(|ex| ex.key()).unwrap_or_default();
+
+        // 同一文件、同样的切分参数：直接使用缓存的会话边界
+        let cache_key = index.document_cache_key();
+        if let Some(sessions) = cache_key.and_then(|key| index_cache::load_sessions(key, &boot_regex, &timestamp_key, time_gap_threshold)) {
+            return Ok(ParsedLog {
+                sessions,
+                line_count,
+                levels: index.levels.runs(0..line_count),
+            });
+        }
+        
+        let boot_re = compile_boot_regex(&boot_regex);
 
-        let ts_re = if !timestamp_regex.is_empty() {
-            Regex::new(&timestamp_regex).ok()
-        } else {
-            None
-        };
-
-        let mut last_time: Option<f64> = None;
-
-        for idx in 0..line_count {
-            let start = index.offsets[idx];
-            let end = if idx + 1 < line_count { index.offsets[idx+1] } else { bytes.len() };
-            let line_bytes = &bytes[start..end];
-            let line_str = bytes_to_string_with_encoding(line_bytes, index.encoding);
-
-            let mut split_session = false;
-            let mut boot_marker = String::new();
-
-            // 1. 基于 Boot 标识符切分
-            if let Some(ref re) = boot_re {
-                if re.is_match(&line_str) && idx > 0 {
-                    split_session = true;
-                    boot_marker = line_str.trim().to_string();
-                }
-            }
-
-            // 2. 基于时间间隙切分
-            if !split_session && time_gap_threshold > 0.0 {
-                if let Some(ref re) = ts_re {
-                    if let Some(caps) = re.captures(&line_str) {
-                        let ts_str = caps.get(1).map(|m| m.as_str()).unwrap_or(caps.get(0).unwrap().as_str());
-                        if let Some(current_time) = try_parse_timestamp(ts_str) {
-                            if let Some(last) = last_time {
-                                if (current_time - last).abs() > time_gap_threshold {
-                                    split_session = true;
-                                    boot_marker = format!("Time Gap Detected: {:.2}s", current_time - last);
-                                }
-                            }
-                            last_time = Some(current_time);
-                        }
-                    }
-                }
-            }
+        // 启动标识命中行与每行时间戳取自索引的逐行缓存，切分点分块并行检测
+        let (hits, timestamps) = line_data(&index, boot_re.as_slice(), timestamp.as_ref(), None)?;
+        let boot_hits = hits.first().map(|h| h.as_slice()).unwrap_or_default();
+        let splits = session_split::split_points(line_count, boot_hits, timestamps.as_deref(), time_gap_threshold);
+        let sessions = build_sessions(&index, &splits);
 
-            if split_session && idx > 0 {
-                sessions.push(LogSession {
-                    id: session_id,
-                    start_line: current_session_start,
-                    end_line: idx,
-                    boot_marker: if boot_marker.is_empty() { line_str.trim().to_string() } else { boot_marker },
-                });
-                session_id += 1;
-                current_session_start = idx + 1;
-            }
+        if let Some(key) = cache_key {
+            index_cache::store_sessions(key, &boot_regex, &timestamp_key, time_gap_threshold, &sessions);
         }
 
-        // 扫尾末尾会话
-        sessions.push(LogSession {
-            id: session_id,
-            start_line: current_session_start,
-            end_line: line_count,
-            boot_marker: if line_count > 0 { "End of File".to_string() } else { "Full Log".to_string() },
-        });
-
-        let levels = index.levels.clone();
+        let levels = index.levels.runs(0..line_count);
 
         Ok(ParsedLog {
             sessions,
@@ -335,115 +1256,139 @@ async fn parse_log_content(
     }
 }
 
-// 辅助函数：尝试解析时间戳
-fn try_parse_timestamp(s: &str) -> Option<f64> {
-    let s = s.trim();
-    // 1. 尝试解析为纯数字（如内核秒数 [123.456]）
-    if let Ok(val) = s.parse::<f64>() {
-        return Some(val);
+// 切分正则的命中行与每行时间戳：优先取索引中的缓存，缺少的部分补一次扫描并写回缓存
+// 时间戳在单个普通文件上还会读写持久化缓存，文件被追加时只解析新增的行
+type LineData = (Vec<Arc<Vec<usize>>>, Option<Arc<line_index::LineTimestamps>>);
+
+fn line_data(
+    index: &LogIndex,
+    splitters: &[Regex],
+    timestamp: Option<&timestamp::Extractor>,
+    job: Option<&jobs::Job>,
+) -> Result<LineData, String> {
+    let cache = &index.line_cache;
+    let line_count = index.line_count();
+    let mut hits: Vec<Option<Arc<Vec<usize>>>> = splitters.iter().map(|re| cache.hits(re)).collect();
+    let mut timestamps = timestamp.and_then(|ex| cache.timestamps(ex));
+
+    // 内存中没有时先看持久化缓存，不完整的部分留给下面的扫描
+    let mut pending = None;
+    if let (Some(ex), None) = (timestamp, &timestamps) {
+        let mut values = index.document_cache_key()
+            .and_then(|key| index_cache::load_timestamps(key, &index.segments[0].mmap, &ex.key()))
+            .unwrap_or_default();
+        values.truncate(line_count);
+        if values.len() == line_count {
+            let values = Arc::new(values);
+            cache.store_timestamps(ex, values.clone());
+            timestamps = Some(values);
+        } else {
+            pending = Some(values);
+        }
     }
 
-    // 2. 尝试解析常见日期时间格式
-    // 这里我们可以尝试几种常见格式，或者使用更强大的解析库
-    // 简便起见，我们尝试几种模式
-    let formats = [
-        "%Y-%m-%d %H:%M:%S%.3f",
-        "%Y-%m-%d %H:%M:%S",
-        "%H:%M:%S%.3f",
-        "%H:%M:%S",
-    ];
+    let missing: Vec<usize> = (0..splitters.len()).filter(|&i| hits[i].is_none()).collect();
+    let mut chunks = missing.chunks(64);
+    loop {
+        let chunk = chunks.next().unwrap_or(&[]);
+        if chunk.is_empty() && pending.is_none() {
+            break;
+        }
+        let chunk_res: Vec<&Regex> = chunk.iter().map(|&i| &splitters[i]).collect();
+        let scan = LineScan {
+            level_re: None,
+            level_from: line_count,
+            splitters: &chunk_res,
+            hits_from: 0,
+            timestamp: timestamp.filter(|_| pending.is_some()),
+            timestamp_from: pending.as_ref().map_or(line_count, |values| values.len()),
+        };
+        let scanned = scan_lines(line_count, &|idx| index.line_str(idx), &scan, &mut line_index::LevelCodes::default(), job);
+        if let Some(job) = job {
+            job.check()?;
+        }
 
-    for fmt in formats {
-        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(s, fmt) {
-            return Some(dt.and_utc().timestamp() as f64 + dt.and_utc().timestamp_subsec_nanos() as f64 / 1_000_000_000.0);
+        for (&i, found) in chunk.iter().zip(scanned.hits) {
+            let found = Arc::new(found);
+            cache.store_hits(&splitters[i], found.clone());
+            hits[i] = Some(found);
+        }
+        if let (Some(mut values), Some(ex)) = (pending.take(), timestamp) {
+            values.extend(scanned.timestamps);
+            if let Some(key) = index.document_cache_key() {
+                index_cache::store_timestamps(key, &ex.key(), &values);
+            }
+            let values = Arc::new(values);
+            cache.store_timestamps(ex, values.clone());
+            timestamps = Some(values);
         }
     }
 
-    // 如果包含日期和时间，但不是标准格式，尝试部分匹配
-    // 或者针对 [2026-01-22_21:18:34.723] 这种带下划线的
-    let s_clean = s.replace('_', " ").replace('T', " ");
-    for fmt in formats {
-        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(&s_clean, fmt) {
-            return Some(dt.and_utc().timestamp() as f64 + dt.and_utc().timestamp_subsec_nanos() as f64 / 1_000_000_000.0);
-        }
+    Ok((hits.into_iter().map(Option::unwrap_or_default).collect(), timestamps))
+}
+
+// 由切分点构建会话列表（行号 1-based）；启动标识切分的标记取命中行的内容
+fn build_sessions(index: &LogIndex, splits: &[(usize, session_split::SplitReason)]) -> Vec<LogSession> {
+    let markers: Vec<String> = splits.par_iter().map(|&(idx, reason)| match reason {
+        session_split::SplitReason::Boot => index.line_str(idx).trim().to_string(),
+        session_split::SplitReason::TimeGap(gap) => format!("Time Gap Detected: {:.2}s", gap),
+    }).collect();
+
+    let mut sessions = Vec::with_capacity(splits.len() + 1);
+    let mut current_session_start = 1;
+    for (id, (&(idx, _), boot_marker)) in splits.iter().zip(markers).enumerate() {
+        sessions.push(LogSession {
+            id,
+            start_line: current_session_start,
+            end_line: idx,
+            boot_marker,
+        });
+        current_session_start = idx + 1;
     }
 
-    None
+    // 扫尾末尾会话
+    let line_count = index.line_count();
+    sessions.push(LogSession {
+        id: splits.len(),
+        start_line: current_session_start,
+        end_line: line_count,
+        boot_marker: if line_count > 0 { "End of File".to_string() } else { "Full Log".to_string() },
+    });
+    sessions
 }
 
 #[tauri::command]
 async fn parse_log_with_custom_splitters(
-    path: String,
+    handle: DocHandle,
     splitter_regexes: Vec<String>,
     _level_regex: String,
     state: State<'_, AppState>
-) -> Result<ParsedLog, String> {
-    let index_opt = {
-        let current = state.current_index.lock().unwrap();
-        current.clone()
-    };
-
-    let index = if let Some(idx) = index_opt {
-        idx
-    } else {
-        // 如果索引丢了，尝试重新解析文件 (虽然不如直接报错优雅，但更鲁棒)
-        parse_log_file(path.clone(), String::new(), String::new(), state.clone()).await?;
-        state.current_index.lock().unwrap().clone().ok_or("Failed to re-index file")?
-    };
+) -> Result<ParsedLog, validate::CommandError> {
+    // 句柄未打开时直接报错，由前端重新打开文件
+    let index = state.get_index(handle)?;
 
-    let bytes = &index.mmap[..];
-    let line_count = index.offsets.len();
-    
-    let mut sessions = Vec::new();
-    let mut current_session_start = 1;
-    let mut session_id = 0;
+    let line_count = index.line_count();
 
-    // 编译所有激活的分割器
+    // 编译所有激活的分割器；任一无效时报告是第几个、出错位置，而不是悄悄少切几段
     let mut compiled_splitters = Vec::new();
-    for regex_str in &splitter_regexes {
+    for (i, regex_str) in splitter_regexes.iter().enumerate() {
         if !regex_str.is_empty() {
-            if let Ok(re) = Regex::new(regex_str) {
-                compiled_splitters.push(re);
-            }
-        }
-    }
-
-    // 遍历所有行，检查是否匹配任意一个分割器
-    for idx in 0..line_count {
-        let start = index.offsets[idx];
-        let end = if idx + 1 < line_count { index.offsets[idx+1] } else { bytes.len() };
-        let line_str = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
-        
-        let mut is_match = false;
-        for re in &compiled_splitters {
-            if re.is_match(&line_str) {
-                is_match = true;
-                break;
-            }
-        }
-
-        if is_match && idx > 0 {
-            sessions.push(LogSession {
-                id: session_id,
-                start_line: current_session_start,
-                end_line: idx,
-                boot_marker: line_str.trim().to_string(),
-            });
-            session_id += 1;
-            current_session_start = idx + 1;
+            let re = validate::regex(regex_str, false)
+                .map_err(|e| e.in_rule(format!("splitter #{}", i + 1), regex_str))?;
+            compiled_splitters.push(re);
         }
     }
 
-    // 扫尾末尾会话
-    sessions.push(LogSession {
-        id: session_id,
-        start_line: current_session_start,
-        end_line: line_count,
-        boot_marker: if line_count > 0 { "End of File".to_string() } else { "Full Log".to_string() },
-    });
+    // 任意一个分割器命中的行（命中行取自逐行缓存，只有新的分割器需要扫描）
+    let (hits, _) = line_data(&index, &compiled_splitters, None, None)?;
+    let mut split_lines: Vec<usize> = hits.iter().flat_map(|h| h.iter().copied()).collect();
+    split_lines.par_sort_unstable();
+    split_lines.dedup();
+    let splits = session_split::split_points(line_count, &split_lines, None, 0.0);
+    let sessions = build_sessions(&index, &splits);
 
     // 兼容性返回：只带元数据
-    let levels = index.levels.clone();
+    let levels = index.levels.runs(0..line_count);
 
     Ok(ParsedLog {
         sessions,
@@ -454,33 +1399,24 @@ async fn parse_log_with_custom_splitters(
 
 #[tauri::command]
 async fn get_log_range(
+    handle: DocHandle,
     start_line: usize, // 1-based
     end_line: usize,   // 1-based
+    span_rules: Option<Vec<spans::SpanRule>>,
     state: State<'_, AppState>
 ) -> Result<Vec<LogLine>, String> {
-    let index_opt = {
-        let current = state.current_index.lock().unwrap();
-        current.clone()
-    };
+    let index_opt = state.get_index(handle).ok();
+    let span_matcher = span_rules.as_deref().map(spans::SpanMatcher::new);
 
     if let Some(index) = index_opt {
-        let line_count = index.offsets.len();
+        let line_count = index.line_count();
         let start_idx = (start_line.max(1) - 1).min(line_count);
         let end_idx = end_line.min(line_count);
         
         if start_idx >= end_idx { return Ok(vec![]); }
 
-        let bytes = &index.mmap[..];
         let result: Vec<LogLine> = (start_idx..end_idx).into_par_iter().map(|idx| {
-            let start = index.offsets[idx];
-            let end = if idx + 1 < line_count { index.offsets[idx+1] } else { bytes.len() };
-            let line_content = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
-            
-            LogLine {
-                line_number: idx + 1,
-                content: line_content,
-                level: index.levels[idx].clone(),
-            }
+            LogLine::new(&index, idx, index.line_text(idx), span_matcher.as_ref())
         }).collect();
 
         Ok(result)
@@ -491,26 +1427,20 @@ async fn get_log_range(
 
 #[tauri::command]
 async fn get_log_lines_by_indices(
+    handle: DocHandle,
     indices: Vec<usize>, // 0-based
+    span_rules: Option<Vec<spans::SpanRule>>,
     state: State<'_, AppState>
 ) -> Result<Vec<LogLine>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
+    let index_opt = state.get_index(handle).ok();
+    let span_matcher = span_rules.as_deref().map(spans::SpanMatcher::new);
     
     if let Some(index) = index_opt {
-        let line_count = index.offsets.len();
-        let bytes = &index.mmap[..];
+        let line_count = index.line_count();
         
         let result: Vec<LogLine> = indices.into_par_iter().filter_map(|idx| {
             if idx >= line_count { return None; }
-            let start = index.offsets[idx];
-            let end = if idx + 1 < line_count { index.offsets[idx+1] } else { bytes.len() };
-            let line_content = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
-            
-            Some(LogLine {
-                line_number: idx + 1,
-                content: line_content,
-                level: index.levels[idx].clone(),
-            })
+            Some(LogLine::new(&index, idx, index.line_text(idx), span_matcher.as_ref()))
         }).collect();
 
         Ok(result)
@@ -527,12 +1457,18 @@ pub struct PatternStat {
 }
 
 #[tauri::command]
-async fn analyze_log_patterns(state: State<'_, AppState>) -> Result<Vec<PatternStat>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
+async fn analyze_log_patterns(
+    handle: DocHandle,
+    job_id: Option<jobs::JobId>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<Vec<PatternStat>, String> {
+    let job = state.jobs.start(job_id, "analyze_log_patterns", move |progress| {
+        let _ = app.emit("job-progress", progress);
+    });
+    let index = state.get_index(handle)?;
+    job.set_phase("patterns", index.line_count() as u64);
     
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
     
     // 预编译正则，放在循环外
     let ts_re = Regex::new(r"\d{2}:\d{2}:\d{2}").unwrap();
@@ -542,12 +1478,11 @@ async fn analyze_log_patterns(state: State<'_, AppState>) -> Result<Vec<PatternS
     // 使用 parallel fold 和 reduce 来加速聚合并减少中间内存占用
     use std::collections::HashMap;
     
-    let pattern_counts: HashMap<String, (usize, Option<String>)> = (0..offsets.len()).into_par_iter().fold(
+    let pattern_counts: HashMap<String, (usize, Option<String>)> = (0..index.line_count()).into_par_iter().fold(
         || HashMap::<String, (usize, Option<String>)>::new(),
         |mut acc, idx| {
-            let start = offsets[idx];
-            let end = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-            let line = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
+            if job.tick(idx) { return acc; }
+            let line = index.line_str(idx);
             if line.trim().is_empty() { return acc; }
 
             let mut fingerprint = line;
@@ -558,7 +1493,7 @@ async fn analyze_log_patterns(state: State<'_, AppState>) -> Result<Vec<PatternS
             let entry = acc.entry(fingerprint).or_insert((0, None));
             entry.0 += 1;
             if entry.1.is_none() {
-                entry.1 = index.levels[idx].clone();
+                entry.1 = index.line_level(idx);
             }
             acc
         }
@@ -575,6 +1510,7 @@ async fn analyze_log_patterns(state: State<'_, AppState>) -> Result<Vec<PatternS
             m1
         }
     );
+    job.check()?;
 
     let mut stats: Vec<PatternStat> = pattern_counts.into_iter()
         .map(|(content, (count, level))| PatternStat { content, count, level })
@@ -591,18 +1527,13 @@ pub struct MetricDataPoint {
 }
 
 #[tauri::command]
-async fn extract_metrics(regex: String, state: State<'_, AppState>) -> Result<Vec<MetricDataPoint>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
+async fn extract_metrics(handle: DocHandle, regex: String, state: State<'_, AppState>) -> Result<Vec<MetricDataPoint>, String> {
+    let index = state.get_index(handle)?;
     
     let re = Regex::new(&regex).map_err(|e| format!("Invalid regex: {}", e))?;
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
 
-    let data: Vec<MetricDataPoint> = (0..offsets.len()).into_par_iter().filter_map(|idx| {
-        let start = offsets[idx];
-        let end = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-        let line = String::from_utf8_lossy(&bytes[start..end]);
+    let data: Vec<MetricDataPoint> = (0..index.line_count()).into_par_iter().filter_map(|idx| {
+        let line = index.line_str(idx);
         
         if let Some(caps) = re.captures(&line) {
             let val_str = if caps.len() > 1 {
@@ -628,22 +1559,21 @@ async fn extract_metrics(regex: String, state: State<'_, AppState>) -> Result<Ve
 
 #[tauri::command]
 async fn save_sessions(
-    source_path: String,
+    handle: DocHandle,
     target_path: String,
-    ranges: Vec<(usize, usize)>
+    ranges: Vec<(usize, usize)>,
+    state: State<'_, AppState>
 ) -> Result<(), String> {
-    let bytes = fs::read(&source_path)
-        .map_err(|e| format!("Failed to read source file: {}", e))?;
-    let content = String::from_utf8_lossy(&bytes);
-    let lines: Vec<&str> = content.lines().collect();
+    let index = state.get_index(handle)?;
+    let line_count = index.line_count();
 
     let mut output = String::new();
     for (start, end) in ranges {
         // start and end are 1-based line numbers
-        if start > 0 && start <= lines.len() && end >= start {
-            let actual_end = if end > lines.len() { lines.len() } else { end };
+        if start > 0 && start <= line_count && end >= start {
+            let actual_end = if end > line_count { line_count } else { end };
             for i in (start - 1)..(actual_end) {
-                output.push_str(lines[i]);
+                output.push_str(&index.line_text(i));
                 output.push('\n');
             }
         }
@@ -655,133 +1585,395 @@ async fn save_sessions(
     Ok(())
 }
 
+// 搜索时随结果一起返回的默认首页行数
+const SEARCH_PAGE_LINES: usize = 200;
+
+/// 搜索结果：命中行保存为结果集，首页内容随结果返回，其余页通过 get_result_lines 按需获取
+#[derive(Debug, Serialize)]
+pub struct SearchPage {
+    result: results::ResultInfo, // 保留的命中（超过 max_results 时只保留前 max_results 条）
+    total: usize,                // 全部命中数，不受 max_results 限制
+    lines: Vec<LogLine>,         // 前 page_size 条命中行
+}
+
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn search_log(
+    handle: DocHandle,
     query: String,
     is_regex: bool,
     line_ranges: Option<Vec<(usize, usize)>>, // 新增：可选的行号范围限制 (start, end) 1-based
+    scope: Option<scope::MarkerScope>, // 只在起止标记之间搜索，与 line_ranges 同时给出时取交集
+    time_range: Option<time_range::TimeRange>, // 只搜索时间戳（没有时沿用上一行）落在范围内的行
+    options: Option<search_options::SearchOptions>, // 大小写、整词、反选与每会话命中上限，缺省为不区分大小写的子串匹配
+    context: Option<context::ContextOptions>, // 命中前后带出的上下文行，结果按组合并
+    page_size: Option<usize>,
+    max_results: Option<usize>, // 结果集最多保留的命中数，None 表示不限
+    span_rules: Option<Vec<spans::SpanRule>>, // 给出时首页各行附带这些规则的命中区间
+    job_id: Option<jobs::JobId>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>
-) -> Result<Vec<LogLine>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
-    
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
+) -> Result<SearchPage, String> {
+    let job = state.jobs.start(job_id, "search_log", move |progress| {
+        let _ = app.emit("job-progress", progress);
+    });
+    let index = state.get_index(handle)?;
+
+    let mask = search_mask(&index, &query, is_regex, &options.unwrap_or_default(), line_ranges.as_deref(), scope.as_ref(), time_range.as_ref(), &job)?;
+    let found = results::ResultSet::from_mask(handle, &mask);
+    let total = found.len();
+    let kept = match max_results {
+        Some(limit) => found.truncate(limit),
+        None => found,
+    };
+    // 上限只计命中行，上下文在截断后再展开
+    let kept = match context.filter(|c| !c.is_empty()) {
+        Some(context) => {
+            let hits = kept.to_mask();
+            results::ResultSet::from_mask(handle, &context_mask(&index, &context, &hits)?).with_hits(&hits)
+        }
+        None => kept,
+    };
 
-    let trimmed_query = query.trim_matches(|c: char| c == '\r' || c == '\n');
+    let span_matcher = span_rules.as_deref().map(spans::SpanMatcher::new);
+    let lines: Vec<LogLine> = kept.page(0, page_size.unwrap_or(SEARCH_PAGE_LINES)).into_par_iter().map(|idx| {
+        LogLine::new(&index, idx, index.line_text(idx), span_matcher.as_ref()).in_result(&kept)
+    }).collect();
 
-    if trimmed_query.is_empty() {
-        return Ok(vec![]);
+    Ok(SearchPage { result: state.results.insert(kept), total, lines })
+}
+
+// 正则预览默认返回的示例行数
+const REGEX_PREVIEW_LINES: usize = 20;
+// 正则预览每批扫描的行数，取满示例后不再继续
+const REGEX_PREVIEW_CHUNK: usize = 1 << 16;
+
+/// 正则中的一条示例命中
+#[derive(Debug, Serialize)]
+pub struct RegexSample {
+    line: LogLine,                 // 附带该正则的命中区间
+    captures: Vec<Option<String>>, // 行内首个命中的各捕获组（不含整体匹配）
+}
+
+/// 正则校验结果：无效时给出错误位置；有效且给出文档时附带当前文件中的前几条命中
+#[derive(Debug, Serialize)]
+pub struct RegexPreview {
+    error: Option<validate::SyntaxError>,
+    samples: Vec<RegexSample>,
+    scanned_lines: usize, // 已扫描的行数；示例取满后提前结束时小于总行数
+    line_count: usize,
+}
+
+// 校验正则并预览它在当前文件上的命中，供分割器、级别、时间戳等正则输入框即时提示
+#[tauri::command]
+async fn validate_regex(
+    handle: Option<DocHandle>,
+    pattern: String,
+    case_insensitive: Option<bool>,
+    limit: Option<usize>,
+    state: State<'_, AppState>
+) -> Result<RegexPreview, String> {
+    let re = match validate::regex(&pattern, case_insensitive.unwrap_or(false)) {
+        Ok(re) => re,
+        Err(e) => return Ok(RegexPreview { error: Some(e), samples: Vec::new(), scanned_lines: 0, line_count: 0 }),
+    };
+    let Some(index) = handle.map(|h| state.get_index(h)).transpose()? else {
+        return Ok(RegexPreview { error: None, samples: Vec::new(), scanned_lines: 0, line_count: 0 });
+    };
+
+    let line_count = index.line_count();
+    let limit = limit.unwrap_or(REGEX_PREVIEW_LINES);
+    let candidates = index.search_candidates(&trigram::TrigramQuery::regex(&pattern, case_insensitive.unwrap_or(false)));
+    let mut hits = Vec::new();
+    let mut scanned_lines = 0;
+    while scanned_lines < line_count && hits.len() < limit {
+        let end = (scanned_lines + REGEX_PREVIEW_CHUNK).min(line_count);
+        hits.extend((scanned_lines..end).into_par_iter()
+            .filter(|&idx| candidates.as_ref().is_none_or(|c| c.contains(idx)) && re.is_match(&index.line_text(idx)))
+            .collect::<Vec<usize>>());
+        scanned_lines = end;
     }
+    hits.truncate(limit);
+
+    let matcher = spans::SpanMatcher::regex(re.clone());
+    let samples = hits.into_iter().map(|idx| {
+        let text = index.line_text(idx);
+        let captures = re.captures(&text)
+            .map(|caps| caps.iter().skip(1).map(|m| m.map(|m| m.as_str().to_string())).collect())
+            .unwrap_or_default();
+        RegexSample { line: LogLine::new(&index, idx, text, Some(&matcher)), captures }
+    }).collect();
 
-    let search_fn: Box<dyn Fn(&str) -> bool + Send + Sync> = if is_regex {
-        let re = RegexBuilder::new(trimmed_query)
-            .case_insensitive(true)
-            .build()
-            .map_err(|e| e.to_string())?;
-        Box::new(move |s| re.is_match(s))
-    } else {
-        let q = trimmed_query.to_lowercase();
-        Box::new(move |s| s.to_lowercase().contains(&q))
+    Ok(RegexPreview { error: None, samples, scanned_lines, line_count })
+}
+
+// 起止标记界定的范围；标记命中行取自逐行缓存，与会话切分共用
+fn marker_ranges(index: &LogIndex, scope: &scope::MarkerScope, job: Option<&jobs::Job>) -> Result<Vec<(usize, usize)>, String> {
+    let start = validate::regex(&scope.start, false).map_err(|e| e.in_rule("scope start marker", &scope.start))?;
+    let end = validate::regex(&scope.end, false).map_err(|e| e.in_rule("scope end marker", &scope.end))?;
+    let (hits, _) = line_data(index, &[start, end], None, job)?;
+    Ok(scope.ranges(&hits[0], &hits[1], index.line_count()))
+}
+
+// 给出标记范围时与 line_ranges 取交集
+fn scoped_ranges(
+    index: &LogIndex,
+    line_ranges: Option<Vec<(usize, usize)>>,
+    scope: Option<&scope::MarkerScope>,
+    job: Option<&jobs::Job>,
+) -> Result<Option<Vec<(usize, usize)>>, String> {
+    let Some(scope) = scope else { return Ok(line_ranges) };
+    let blocks = marker_ranges(index, scope, job)?;
+    Ok(Some(match line_ranges {
+        Some(ranges) => scope::intersect(&ranges, &blocks),
+        None => blocks,
+    }))
+}
+
+// 计算标记范围（1-based 闭区间），供前端展示或作为其他分析命令的 line_ranges
+#[tauri::command]
+async fn get_scope_ranges(
+    handle: DocHandle,
+    scope: scope::MarkerScope,
+    state: State<'_, AppState>
+) -> Result<Vec<(usize, usize)>, String> {
+    let index = state.get_index(handle)?;
+    marker_ranges(&index, &scope, None)
+}
+
+// 缺省的线程/进程号正则：tid=123、pid: 45、thread-id 7、[pool-1-thread-3]、[main]
+const DEFAULT_THREAD_REGEX: &str = r"(?i)\b(?:tid|pid|thread(?:[ _-]?id)?)\s*[=:#]?\s*(\w[\w.-]*)|\[((?:[\w.-]+-)?thread-\d+|main)\]";
+
+// 命中行连同上下文的逐行标记；按线程或时间取上下文时先准备逐行的线程键与时间戳
+fn context_mask(index: &LogIndex, context: &context::ContextOptions, hits: &[bool]) -> Result<Vec<bool>, String> {
+    let mut keys = context::LineKeys::default();
+    if context.same_thread {
+        let pattern = context.thread_regex.as_deref().filter(|p| !p.is_empty()).unwrap_or(DEFAULT_THREAD_REGEX);
+        let re = validate::regex(pattern, false).map_err(|e| e.in_rule("thread regex", pattern))?;
+        let threads: Vec<Option<u64>> = (0..index.line_count()).into_par_iter().map(|idx| {
+            let line = index.line_str(idx);
+            let caps = re.captures(&line)?;
+            let id = caps.iter().skip(1).flatten().next()?;
+            let mut hasher = std::collections::hash_map::DefaultHasher::new();
+            id.as_str().hash(&mut hasher);
+            Some(hasher.finish().max(1))
+        }).collect();
+        keys.threads = Some(context::carry_forward(threads, 0));
+    }
+    if context.time_window_ms.is_some() {
+        let (_, timestamps) = line_times(index, context.timestamp_regex.as_deref(), None)?
+            .ok_or("Time-window context needs a timestamp regex")?;
+        keys.times = Some(timestamps.carried().iter().map(|s| s * 1000.0).collect());
+    }
+    Ok(context.expand_with(hits, &keys))
+}
+
+// 每行时间戳及其提取器：给出的时间戳正则优先，缺省沿用解析文档时的时间戳正则；两者都没有时为 None
+// 样本中的时间戳都无法解析时返回错误
+fn line_times(
+    index: &LogIndex,
+    timestamp_regex: Option<&str>,
+    job: Option<&jobs::Job>,
+) -> Result<Option<(timestamp::Extractor, Arc<line_index::LineTimestamps>)>, String> {
+    let extractor = match timestamp_regex.filter(|p| !p.is_empty()) {
+        Some(pattern) => {
+            let re = validate::regex(pattern, false).map_err(|e| e.in_rule("timestamp regex", pattern))?;
+            timestamp_extractor(index, re)?
+        }
+        None => match index.line_cache.extractor() {
+            Some(ex) => ex,
+            None => return Ok(None),
+        },
     };
+    let (_, timestamps) = line_data(index, &[], Some(&extractor), job)?;
+    Ok(timestamps.map(|ts| (extractor, ts)))
+}
+
+// 时间范围筛选需要的逐行时间与解析后的范围；只写时分秒的时间补上文档首个时间戳的日期
+fn time_bounds(
+    index: &LogIndex,
+    range: Option<&time_range::TimeRange>,
+    job: Option<&jobs::Job>,
+) -> Result<Option<(Arc<line_index::LineTimestamps>, time_range::Bounds)>, String> {
+    let Some(range) = range else { return Ok(None) };
+    let (extractor, timestamps) = line_times(index, range.timestamp_regex.as_deref(), job)?
+        .ok_or("Time-range filter needs a timestamp regex")?;
+    let bounds = range.bounds(extractor.parser(), timestamps.first())?;
+    Ok(Some((timestamps, bounds)))
+}
 
-    let result: Vec<LogLine> = if let Some(ranges) = line_ranges {
-        if ranges.is_empty() {
-            return Ok(vec![]);
+// 逐行标记搜索命中；line_ranges 为 1-based 闭区间，给出时只在这些范围内搜索，并作为每会话命中上限的会话边界
+// scope、time_range 给出时再限制在起止标记之间、时间范围之内，不改变会话边界
+#[allow(clippy::too_many_arguments)]
+fn search_mask(
+    index: &LogIndex,
+    query: &str,
+    is_regex: bool,
+    options: &search_options::SearchOptions,
+    line_ranges: Option<&[(usize, usize)]>,
+    scope: Option<&scope::MarkerScope>,
+    time_range: Option<&time_range::TimeRange>,
+    job: &jobs::Job,
+) -> Result<Vec<bool>, String> {
+    let line_count = index.line_count();
+    let trimmed_query = query.trim_matches(|c: char| c == '\r' || c == '\n');
+    if trimmed_query.is_empty() {
+        return Ok(vec![false; line_count]);
+    }
+    let node = options.compile(&[trimmed_query], is_regex, "search query")?;
+    let candidates = node.candidates(&|q| index.search_candidates(q));
+    // 区间排序合并后逐行二分查找，避免每行遍历所有区间
+    let searched = scoped_ranges(index, line_ranges.map(<[_]>::to_vec), scope, Some(job))?.map(|r| scope::normalize(&r));
+    let times = time_bounds(index, time_range, Some(job))?;
+
+    job.set_phase("search", line_count as u64);
+    let mut mask: Vec<bool> = (0..line_count).into_par_iter().map(|idx| {
+        if job.tick(idx) || candidates.as_ref().is_some_and(|c| !c.contains(idx)) {
+            return false;
         }
-        // 将范围转换为索引
-        ranges.into_par_iter().flat_map(|(start, end)| {
-            let start_idx = (start.max(1) - 1).min(offsets.len());
-            let end_idx = end.min(offsets.len());
-            
-            if start_idx >= end_idx {
-                return vec![];
-            }
+        if searched.as_ref().is_some_and(|ranges| !scope::contains(ranges, idx + 1)) {
+            return false;
+        }
+        if times.as_ref().is_some_and(|(t, bounds)| !bounds.contains(t.carried()[idx])) {
+            return false;
+        }
+        // 字面量直接匹配原始字节，只解码正则需要的行
+        node.matches(&index.line_view(idx))
+    }).collect();
+    job.check()?;
+    options.limit_per_session(&mut mask, line_ranges);
+    Ok(mask)
+}
 
-            (start_idx..end_idx).into_iter().filter_map(|idx| {
-                let start_pos = offsets[idx];
-                let next_start = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-                
-                // 掐掉换行符 (编码相关的)
-                let mut end_pos = next_start;
-                match index.encoding {
-                    FileEncoding::Utf16Le | FileEncoding::Utf16Be => {
-                        while end_pos >= start_pos + 2 {
-                            let b1 = bytes[end_pos - 2];
-                            let b2 = bytes[end_pos - 1];
-                            if (index.encoding == FileEncoding::Utf16Le && (b1 == 0x0A || b1 == 0x0D) && b2 == 0x00) ||
-                               (index.encoding == FileEncoding::Utf16Be && b1 == 0x00 && (b2 == 0x0A || b2 == 0x0D)) {
-                                end_pos -= 2;
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                    _ => {
-                        while end_pos > start_pos && (bytes[end_pos-1] == b'\n' || bytes[end_pos-1] == b'\r') {
-                            end_pos -= 1;
-                        }
-                    }
-                }
-                
-                let line_str = bytes_to_string_with_encoding(&bytes[start_pos..end_pos], index.encoding);
-                
-                if search_fn(&line_str) {
-                    Some(LogLine {
-                        line_number: idx + 1,
-                        content: line_str,
-                        level: index.levels[idx].clone(),
-                    })
-                } else {
-                    None
-                }
-            }).collect::<Vec<_>>()
-        }).collect()
-    } else {
-        // 全文搜索
-        (0..offsets.len())
-            .into_par_iter()
-            .filter_map(|idx| {
-                let start_pos = offsets[idx];
-                let next_start = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-                
-                let mut end_pos = next_start;
-                match index.encoding {
-                    FileEncoding::Utf16Le | FileEncoding::Utf16Be => {
-                        while end_pos >= start_pos + 2 {
-                            let b1 = bytes[end_pos - 2];
-                            let b2 = bytes[end_pos - 1];
-                            if (index.encoding == FileEncoding::Utf16Le && (b1 == 0x0A || b1 == 0x0D) && b2 == 0x00) ||
-                               (index.encoding == FileEncoding::Utf16Be && b1 == 0x00 && (b2 == 0x0A || b2 == 0x0D)) {
-                                end_pos -= 2;
-                            } else {
-                                break;
-                            }
-                        }
-                    }
-                    _ => {
-                        while end_pos > start_pos && (bytes[end_pos-1] == b'\n' || bytes[end_pos-1] == b'\r') {
-                            end_pos -= 1;
-                        }
-                    }
-                }
+/// trigram 索引摘要
+#[derive(Debug, Serialize, Clone)]
+pub struct SearchIndexInfo {
+    indexed_lines: usize, // 已收录的行数（整块），之后的行搜索时总是全量匹配
+    bytes: usize,
+}
 
-                let line_str = bytes_to_string_with_encoding(&bytes[start_pos..end_pos], index.encoding);
-                
-                if search_fn(&line_str) {
-                    Some(LogLine {
-                        line_number: idx + 1,
-                        content: line_str,
-                        level: index.levels[idx].clone(),
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect()
+// 建立 trigram 索引，供之后的搜索与过滤排除不可能命中的行块；打开文件后由前端在后台调用
+// 单个普通文件的索引会持久化，文件只被追加时只补建新增的块
+#[tauri::command]
+async fn build_search_index(
+    handle: DocHandle,
+    job_id: Option<jobs::JobId>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<SearchIndexInfo, String> {
+    let job = state.jobs.start(job_id, "build_search_index", move |progress| {
+        let _ = app.emit("job-progress", progress);
+    });
+    let index = state.get_index(handle)?;
+
+    let line_count = index.line_count();
+    let encoding = index.encoding();
+    let cache_key = index.document_cache_key();
+    let cached = cache_key.and_then(|key| index_cache::load_trigrams(key, &index.segments[0].mmap, encoding));
+    let current = index.trigrams.lock().unwrap().clone();
+    let base = current.as_deref().or(cached.as_ref());
+
+    job.set_phase("trigram", line_count as u64);
+    let built = trigram::TrigramIndex::build(base, line_count, &|idx| index.line_utf8(idx), Some(&job));
+    job.check()?;
+
+    if let Some(key) = cache_key.filter(|_| cached.as_ref().is_none_or(|c| c.blocks() != built.blocks())) {
+        index_cache::store_trigrams(key, encoding, &built);
+    }
+    let info = SearchIndexInfo {
+        indexed_lines: built.blocks() * trigram::BLOCK_LINES,
+        bytes: built.rows().len() * 8,
     };
+    *index.trigrams.lock().unwrap() = Some(Arc::new(built));
+    Ok(info)
+}
+
+// 与 search_log 相同的匹配规则，但只把命中行保存为结果集，不传回内容
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+async fn search_log_result(
+    handle: DocHandle,
+    query: String,
+    is_regex: bool,
+    line_ranges: Option<Vec<(usize, usize)>>,
+    scope: Option<scope::MarkerScope>,
+    time_range: Option<time_range::TimeRange>,
+    options: Option<search_options::SearchOptions>,
+    job_id: Option<jobs::JobId>,
+    app: tauri::AppHandle,
+    state: State<'_, AppState>
+) -> Result<results::ResultInfo, String> {
+    let job = state.jobs.start(job_id, "search_log_result", move |progress| {
+        let _ = app.emit("job-progress", progress);
+    });
+    let index = state.get_index(handle)?;
+
+    let mask = search_mask(&index, &query, is_regex, &options.unwrap_or_default(), line_ranges.as_deref(), scope.as_ref(), time_range.as_ref(), &job)?;
+    Ok(state.results.insert(results::ResultSet::from_mask(handle, &mask)))
+}
+
+#[tauri::command]
+async fn get_result_info(result_id: results::ResultId, state: State<'_, AppState>) -> Result<results::ResultInfo, String> {
+    state.results.info(result_id)
+}
+
+// 结果集分页：返回第 offset 个命中起的 limit 个行号（0-based）
+#[tauri::command]
+async fn get_result_page(
+    result_id: results::ResultId,
+    offset: usize,
+    limit: usize,
+    state: State<'_, AppState>
+) -> Result<Vec<usize>, String> {
+    Ok(state.results.get(result_id)?.page(offset, limit))
+}
+
+// 结果集分页并直接带回行内容，供虚拟列表滚动时使用
+#[tauri::command]
+async fn get_result_lines(
+    result_id: results::ResultId,
+    offset: usize,
+    limit: usize,
+    span_rules: Option<Vec<spans::SpanRule>>,
+    state: State<'_, AppState>
+) -> Result<Vec<LogLine>, String> {
+    let result = state.results.get(result_id)?;
+    let index = state.get_index(result.doc())?;
+    let line_count = index.line_count();
+    let span_matcher = span_rules.as_deref().map(spans::SpanMatcher::new);
+
+    let lines: Vec<LogLine> = result.page(offset, limit).into_par_iter().filter_map(|idx| {
+        if idx >= line_count { return None; }
+        Some(LogLine::new(&index, idx, index.line_text(idx), span_matcher.as_ref()).in_result(&result))
+    }).collect();
+
+    Ok(lines)
+}
+
+// 某行（0-based）在结果集中的位置，用于跳转定位；不在结果中时返回 None
+#[tauri::command]
+async fn get_result_rank(
+    result_id: results::ResultId,
+    line_index: usize,
+    state: State<'_, AppState>
+) -> Result<Option<usize>, String> {
+    Ok(state.results.get(result_id)?.rank(line_index))
+}
+
+#[tauri::command]
+async fn intersect_results(
+    a: results::ResultId,
+    b: results::ResultId,
+    state: State<'_, AppState>
+) -> Result<results::ResultInfo, String> {
+    let (a, b) = (state.results.get(a)?, state.results.get(b)?);
+    let set = a.intersect(&b)?;
+    Ok(state.results.insert(set))
+}
 
-    Ok(result)
+#[tauri::command]
+async fn release_result(result_id: results::ResultId, state: State<'_, AppState>) -> Result<bool, String> {
+    Ok(state.results.release(result_id))
 }
 
 #[tauri::command]
@@ -795,37 +1987,6 @@ async fn read_config_file(path: String) -> Result<String, String> {
     Ok(String::from_utf8_lossy(&bytes).to_string())
 }
 
-use chrono::NaiveDateTime;
-
-fn parse_timestamp_to_ms(ts_str: &str) -> f64 {
-    if let Ok(val) = ts_str.parse::<f64>() {
-        // 情况 1: Uptime 或 Unix Timestamp (1234.567)
-        // 如果数字小于 10^10，我们假设它是秒，转为毫秒
-        if val < 10_000_000_000.0 { val * 1000.0 } else { val }
-    } else {
-        // 情况 2: 日期字符串
-        // 尝试解析常见格式 [2026-01-16 20:30:44.295]
-        let formats = [
-            "%Y-%m-%d %H:%M:%S%.3f",
-            "%Y-%m-%d %H:%M:%S",
-            "%Y-%m-%d_%H:%M:%S",
-            "%Y-%m-%dT%H:%M:%S%.3f",
-            "%Y/%m/%d %H:%M:%S%.3f",
-            "%H:%M:%S%.3f",
-            "%H:%M:%S",
-        ];
-        
-        let mut parsed_ms = None;
-        for fmt in formats {
-            if let Ok(dt) = NaiveDateTime::parse_from_str(ts_str, fmt) {
-                parsed_ms = Some(dt.and_utc().timestamp_millis() as f64);
-                break;
-            }
-        }
-        parsed_ms.unwrap_or(0.0)
-    }
-}
-
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TimeGap {
     line_number: usize,
@@ -833,36 +1994,20 @@ pub struct TimeGap {
 }
 
 #[tauri::command]
-async fn analyze_time_gaps(timestamp_regex: String, state: State<'_, AppState>) -> Result<Vec<TimeGap>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
-    
-    let re = Regex::new(&timestamp_regex).map_err(|e| e.to_string())?;
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
+async fn analyze_time_gaps(handle: DocHandle, timestamp_regex: String, state: State<'_, AppState>) -> Result<Vec<TimeGap>, String> {
+    let index = state.get_index(handle)?;
 
-    // 1. 并行提取所有行的时间戳
-    let timestamps: Vec<Option<f64>> = (0..offsets.len()).into_par_iter().map(|idx| {
-        let start = offsets[idx];
-        let end = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-        let line = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
-
-        if let Some(caps) = re.captures(&line) {
-            let ts_str = caps.get(1).map(|m| m.as_str()).unwrap_or("");
-            let current_ms = parse_timestamp_to_ms(ts_str);
-            if current_ms > 0.0 {
-                return Some(current_ms);
-            }
-        }
-        None
-    }).collect();
+    // 1. 每行时间戳取自索引的逐行缓存（秒，NaN 表示无）
+    let (_, timestamps) = line_times(&index, Some(&timestamp_regex), None)?
+        .ok_or("Time-gap analysis needs a timestamp regex")?;
 
     // 2. 串行计算差值
     let mut last_time: Option<f64> = None;
     let mut gaps = Vec::new();
 
-    for (idx, ts_opt) in timestamps.into_iter().enumerate() {
-        if let Some(current_ms) = ts_opt {
+    for (idx, &seconds) in timestamps.values().iter().enumerate() {
+        if !seconds.is_nan() {
+            let current_ms = seconds * 1000.0;
             if let Some(last) = last_time {
                 let diff = current_ms - last;
                 if diff > 10.0 {
@@ -890,19 +2035,27 @@ pub struct WorkflowSegment {
 }
 
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn analyze_workflow_duration(
+    handle: DocHandle,
     start_regex: String,
     end_regex: String,
     timestamp_regex: String,
     id_regex: Option<String>,
+    job_id: Option<jobs::JobId>,
+    app: tauri::AppHandle,
     state: State<'_, AppState>
 ) -> Result<Vec<WorkflowSegment>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
+    let job = state.jobs.start(job_id, "analyze_workflow_duration", move |progress| {
+        let _ = app.emit("job-progress", progress);
+    });
+    let index = state.get_index(handle)?;
     
     let start_re = Regex::new(&start_regex).map_err(|e| format!("Start Regex Error: {}", e))?;
     let end_re = Regex::new(&end_regex).map_err(|e| format!("End Regex Error: {}", e))?;
-    let ts_re = Regex::new(&timestamp_regex).map_err(|e| format!("Timestamp Regex Error: {}", e))?;
+    let (_, timestamps) = line_times(&index, Some(&timestamp_regex), Some(&job))?
+        .ok_or("Workflow analysis needs a timestamp regex")?;
+    let timestamps = timestamps.values();
     let id_re = if let Some(ref r) = id_regex {
         if r.is_empty() { None } else {
             Some(Regex::new(r).map_err(|e| format!("ID Regex Error: {}", e))?)
@@ -911,10 +2064,8 @@ async fn analyze_workflow_duration(
         None
     };
 
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
 
-    // 1. 并行预处理：提取时间戳、ID和匹配标记
+    // 1. 并行预处理：取时间戳（毫秒，NaN 表示无）、ID和匹配标记
     // 为了减少内存分配，我们只记录必要信息
     #[derive(Clone)]
     struct LineMeta {
@@ -925,15 +2076,14 @@ async fn analyze_workflow_duration(
         is_end: bool,
     }
 
-    let metas: Vec<LineMeta> = (0..offsets.len()).into_par_iter().map(|idx| {
-        let start = offsets[idx];
-        let end = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-        let line = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
+    job.set_phase("scan", index.line_count() as u64);
+    let metas: Vec<LineMeta> = (0..index.line_count()).into_par_iter().map(|idx| {
+        if job.tick(idx) {
+            return LineMeta { line_num: idx + 1, ts: f64::NAN, id: None, is_start: false, is_end: false };
+        }
+        let line = index.line_str(idx);
         
-        let ts = ts_re.captures(&line)
-            .and_then(|c| c.get(1))
-            .map(|m| parse_timestamp_to_ms(m.as_str()))
-            .unwrap_or(0.0);
+        let ts = timestamps.get(idx).map_or(f64::NAN, |s| s * 1000.0);
             
         let id = id_re.as_ref()
             .and_then(|re| re.captures(&line))
@@ -953,16 +2103,17 @@ async fn analyze_workflow_duration(
             is_end: end_re.is_match(&line),
         }
     }).collect();
+    job.check()?;
 
     // 2. 串行匹配逻辑 (因为这涉及到状态机)
     let mut segments = Vec::new();
     use std::collections::HashMap;
     let mut active_starts_with_id: HashMap<String, (usize, f64)> = HashMap::new();
     let mut active_starts_no_id: Vec<(usize, f64)> = Vec::new();
-    let mut last_valid_ts = 0.0;
+    let mut last_valid_ts = f64::NAN;
 
     for meta in metas {
-        if meta.ts > 0.0 {
+        if !meta.ts.is_nan() {
             last_valid_ts = meta.ts;
         }
         
@@ -977,7 +2128,7 @@ async fn analyze_workflow_duration(
         if meta.is_end {
             if let Some(ref flow_id) = meta.id {
                 if let Some((s_line, s_ts)) = active_starts_with_id.remove(flow_id) {
-                    if last_valid_ts > 0.0 && s_ts > 0.0 {
+                    if !last_valid_ts.is_nan() && !s_ts.is_nan() {
                         segments.push(WorkflowSegment {
                             start_line: s_line,
                             end_line: meta.line_num,
@@ -990,7 +2141,7 @@ async fn analyze_workflow_duration(
                 }
             } else {
                 if let Some((s_line, s_ts)) = active_starts_no_id.pop() {
-                    if last_valid_ts > 0.0 && s_ts > 0.0 {
+                    if !last_valid_ts.is_nan() && !s_ts.is_nan() {
                         segments.push(WorkflowSegment {
                             start_line: s_line,
                             end_line: meta.line_num,
@@ -1010,18 +2161,18 @@ async fn analyze_workflow_duration(
 
 #[tauri::command]
 async fn analyze_recurrent_intervals(
+    handle: DocHandle,
     regex: String,
     timestamp_regex: String,
     state: State<'_, AppState>
 ) -> Result<Vec<WorkflowSegment>, String> {
-    let index_opt = state.current_index.lock().unwrap().clone();
-    let index = index_opt.ok_or("No file opened")?;
+    let index = state.get_index(handle)?;
     
     let re = Regex::new(&regex).map_err(|e| format!("Regex Error: {}", e))?;
-    let ts_re = Regex::new(&timestamp_regex).map_err(|e| format!("Timestamp Regex Error: {}", e))?;
+    let (_, timestamps) = line_times(&index, Some(&timestamp_regex), None)?
+        .ok_or("Interval analysis needs a timestamp regex")?;
+    let timestamps = timestamps.values();
 
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
 
     // 1. 并行预处理
     struct Hit {
@@ -1030,15 +2181,10 @@ async fn analyze_recurrent_intervals(
         is_hit: bool,
     }
 
-    let hits: Vec<Hit> = (0..offsets.len()).into_par_iter().map(|idx| {
-        let start = offsets[idx];
-        let end = if idx + 1 < offsets.len() { offsets[idx+1] } else { bytes.len() };
-        let line = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
+    let hits: Vec<Hit> = (0..index.line_count()).into_par_iter().map(|idx| {
+        let line = index.line_str(idx);
         
-        let ts = ts_re.captures(&line)
-            .and_then(|c| c.get(1))
-            .map(|m| parse_timestamp_to_ms(m.as_str()))
-            .unwrap_or(0.0);
+        let ts = timestamps.get(idx).map_or(f64::NAN, |s| s * 1000.0);
 
         Hit {
             line_num: idx + 1,
@@ -1050,15 +2196,15 @@ async fn analyze_recurrent_intervals(
     // 2. 串行计算间隔
     let mut segments = Vec::new();
     let mut last_hit: Option<(usize, f64)> = None;
-    let mut last_valid_ts = 0.0;
+    let mut last_valid_ts = f64::NAN;
 
     for hit in hits {
-        if hit.ts > 0.0 {
+        if !hit.ts.is_nan() {
             last_valid_ts = hit.ts;
         }
 
         if hit.is_hit {
-            if last_valid_ts > 0.0 {
+            if !last_valid_ts.is_nan() {
                 if let Some((prev_line, prev_ts)) = last_hit {
                     segments.push(WorkflowSegment {
                         start_line: prev_line,
@@ -1077,165 +2223,160 @@ async fn analyze_recurrent_intervals(
     Ok(segments)
 }
 
+// 单个精简条件：! 排除、/ 正则、= 区分大小写、? 模糊（暂按包含处理）、& 查询语言，其余为包含
+fn refinement_node(s: &str) -> Result<query::Node, validate::SyntaxError> {
+    if let Some(text) = s.strip_prefix('!') {
+        Ok(query::Node::Not(Box::new(query::Node::literal(text, true).map_err(validate::SyntaxError::new)?)))
+    } else if let Some(pattern) = s.strip_prefix('/') {
+        validate::regex(pattern, true).map(query::Node::Regex).map_err(|e| e.shifted(1))
+    } else if let Some(exact) = s.strip_prefix('=') {
+        query::Node::literal(exact, false).map_err(validate::SyntaxError::new)
+    } else if let Some(text) = s.strip_prefix('?') {
+        // AI 模式暂时作为普通包含匹配处理，直到接入模型
+        query::Node::literal(text, true).map_err(validate::SyntaxError::new)
+    } else if let Some(expr) = s.strip_prefix('&') {
+        query::parse(expr).map_err(|e| validate::SyntaxError::from(e).shifted(1))
+    } else {
+        query::Node::literal(s, true).map_err(validate::SyntaxError::new)
+    }
+}
+
+// 过滤结果保存为后端结果集，前端通过句柄分页读取
 #[tauri::command]
+#[allow(clippy::too_many_arguments)]
 async fn get_filtered_indices(
+    handle: DocHandle,
     log_levels: Vec<String>,
     line_ranges: Option<Vec<(usize, usize)>>,
+    scope: Option<scope::MarkerScope>, // 只保留起止标记之间的行，与 line_ranges 同时给出时取交集
+    time_range: Option<time_range::TimeRange>, // 只保留时间戳（没有时沿用上一行）落在范围内的行
     highlights: Vec<String>,
     context_lines: usize,
     refinements: Vec<String>,
+    options: Option<search_options::SearchOptions>, // 追踪关键字的匹配选项，与 search_log 相同
+    context: Option<context::ContextOptions>, // 前后分别指定的上下文行数，给出时取代对称的 context_lines
     state: State<'_, AppState>
-) -> Result<Vec<usize>, String> {
-    let index = state.current_index.lock().unwrap().clone()
-        .ok_or("No file opened")?;
+) -> Result<results::ResultInfo, validate::CommandError> {
+    let index = state.get_index(handle)?;
+    let sessions = line_ranges.clone();
+    let line_ranges = scoped_ranges(&index, line_ranges, scope.as_ref(), None)?.map(|r| scope::normalize(&r));
+    let times = time_bounds(&index, time_range.as_ref(), None)?;
     
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
-    let line_count = offsets.len();
+    let line_count = index.line_count();
 
     let levels_set: std::collections::HashSet<String> = log_levels.iter()
         .map(|s| s.to_uppercase()).collect();
     
-    let keywords: Vec<String> = highlights.iter()
-        .map(|s| s.trim().to_lowercase())
+    let keywords: Vec<&str> = highlights.iter()
+        .map(|s| s.trim())
         .filter(|s| !s.is_empty())
         .collect();
+    // 所有追踪关键字合成一个匹配器（子串匹配时为 Aho-Corasick 自动机），每行只扫描一遍
+    let options = options.unwrap_or_default();
+    let keyword_node = if keywords.is_empty() {
+        None
+    } else {
+        Some(options.compile(&keywords, false, "keyword")?)
+    };
+    let keyword_candidates = keyword_node.as_ref().and_then(|n| n.candidates(&|q| index.search_candidates(q)));
 
-    // 预处理多级过滤器
-    enum RefinementMode {
-        Include(String),
-        Exclude(String),
-        Regex(regex::Regex),
-        Exact(String),
-    }
-
-    let parsed_refinements: Vec<RefinementMode> = refinements.iter()
-        .filter(|s| !s.trim().is_empty())
-        .map(|s| {
-            let s = s.trim();
-            if s.starts_with('!') {
-                RefinementMode::Exclude(s[1..].to_lowercase())
-            } else if s.starts_with('/') {
-                let pattern = if s.len() > 1 { &s[1..] } else { "" };
-                let re = regex::RegexBuilder::new(pattern)
-                    .case_insensitive(true)
-                    .build()
-                    .unwrap_or_else(|_| regex::Regex::new("").unwrap());
-                RefinementMode::Regex(re)
-            } else if s.starts_with('=') {
-                RefinementMode::Exact(s[1..].to_string())
-            } else if s.starts_with('?') {
-                // AI 模式暂时作为普通包含匹配处理，直到接入模型
-                RefinementMode::Include(s[1..].to_lowercase())
-            } else {
-                RefinementMode::Include(s.to_lowercase())
-            }
-        })
+    // 按级别代码预先判断是否保留（无级别的行按 INFO 处理）
+    let level_allowed: Vec<bool> = std::iter::once("INFO".to_string())
+        .chain(index.levels.table().iter().map(|l| l.to_uppercase()))
+        .map(|l| levels_set.contains(&l))
         .collect();
 
+    // 精简条件之间为“且”，合成一棵匹配树；无效的正则或查询报告是第几条、出错位置，不再退化为匹配所有行
+    let refinement = query::Node::And(refinements.iter()
+        .enumerate()
+        .map(|(i, s)| (i, s.trim()))
+        .filter(|(_, s)| !s.is_empty())
+        .map(|(i, s)| refinement_node(s).map_err(|e| e.in_rule(format!("refinement #{}", i + 1), s)))
+        .collect::<Result<_, _>>()?);
+    let has_refinements = !matches!(&refinement, query::Node::And(nodes) if nodes.is_empty());
+    // 每个条件的 trigram 候选都可以用来排除行块（NOT 与字段谓词除外）
+    let refinement_candidates = refinement.candidates(&|q| index.search_candidates(q));
+
     // 第一阶段：确定“种子”行（Trace Keywords 或基础过滤条件）
-    let is_seed: Vec<bool> = (0..line_count).into_par_iter().map(|idx| {
+    let mut is_seed: Vec<bool> = (0..line_count).into_par_iter().map(|idx| {
         // 范围和级别是全局基础过滤，不参与上下文扩展
-        if let Some(ref ranges) = line_ranges {
-            let ln = idx + 1;
-            if !ranges.iter().any(|(s, e)| ln >= *s && ln <= *e) { return false; }
-        }
-        if !levels_set.is_empty() {
-            let cur_lv = index.levels[idx].as_ref().map(|s| s.to_uppercase()).unwrap_or_else(|| "INFO".to_string());
-            if !levels_set.contains(&cur_lv) { return false; }
-        }
+        if line_ranges.as_ref().is_some_and(|ranges| !scope::contains(ranges, idx + 1)) { return false; }
+        if times.as_ref().is_some_and(|(t, bounds)| !bounds.contains(t.carried()[idx])) { return false; }
+        if !levels_set.is_empty() && !level_allowed[index.levels.code(idx) as usize] { return false; }
 
         // 如果没有关键字，所有符合范围和级别的行都是种子
-        if keywords.is_empty() { return true; }
+        let Some(keyword_node) = &keyword_node else { return true; };
+        if keyword_candidates.as_ref().is_some_and(|c| !c.contains(idx)) { return false; }
 
-        let start = offsets[idx];
-        let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-        let line_bytes = &bytes[start..end];
-        let line_str_original = bytes_to_string_with_encoding(line_bytes, index.encoding);
-        let line_str_lower = line_str_original.to_lowercase();
-
-        keywords.iter().any(|k| line_str_lower.contains(k))
+        keyword_node.matches(&index.line_view(idx))
     }).collect();
+    if keyword_node.is_some() {
+        options.limit_per_session(&mut is_seed, sessions.as_deref());
+    }
 
-    // 第二阶段：上下文扩展（仅当有关键字且 context_lines > 0 时有效）
-    let mut in_trace = vec![false; line_count];
-    if !keywords.is_empty() && context_lines > 0 {
-        // 串行扩展 mask（虽然增加了主线程压力，但逻辑简单可靠，对于数百万行也是毫秒级）
-        for i in 0..line_count {
-            if is_seed[i] {
-                let start = i.saturating_sub(context_lines);
-                let end = (i + context_lines).min(line_count - 1);
-                for j in start..=end { in_trace[j] = true; }
-            }
-        }
+    // 第二阶段：上下文扩展（仅当有关键字且上下文行数不为 0 时有效）
+    let context = context.unwrap_or(context::ContextOptions::symmetric(context_lines));
+    let with_context = !keywords.is_empty() && !context.is_empty();
+    let expanded;
+    let in_trace: &[bool] = if with_context {
+        expanded = context_mask(&index, &context, &is_seed)?;
+        &expanded
     } else {
-        in_trace = is_seed;
-    }
+        &is_seed
+    };
 
     // 第三阶段：在最终的 trace 范围内应用“精简”过滤器 (Refinements)
-    let result: Vec<usize> = (0..line_count).into_par_iter().filter_map(|idx| {
-        if !in_trace[idx] { return None; }
+    let mask: Vec<bool> = (0..line_count).into_par_iter().map(|idx| {
+        if !in_trace[idx] { return false; }
         
         // 如果没有精简过滤器，直接返回
-        if parsed_refinements.is_empty() { return Some(idx); }
-
-        // 获取行内容以进行精简检查
-        let start = offsets[idx];
-        let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-        let line_bytes = &bytes[start..end];
-        let line_str_original = bytes_to_string_with_encoding(line_bytes, index.encoding);
-        let line_str_lower = line_str_original.to_lowercase();
-
-        for ref_mode in &parsed_refinements {
-            match ref_mode {
-                RefinementMode::Include(k) => {
-                    if !line_str_lower.contains(k) { return None; }
-                }
-                RefinementMode::Exclude(k) => {
-                    if line_str_lower.contains(k) { return None; }
-                }
-                RefinementMode::Regex(re) => {
-                    if !re.is_match(&line_str_original) { return None; }
-                }
-                RefinementMode::Exact(k) => {
-                    if !line_str_original.contains(k) { return None; }
-                }
-            }
-        }
+        if !has_refinements { return true; }
+        if refinement_candidates.as_ref().is_some_and(|c| !c.contains(idx)) { return false; }
 
-        Some(idx)
+        // 字面量条件直接匹配原始字节，只有正则与字段条件才解码行内容
+        refinement.matches(&index.line_view(idx))
     }).collect();
 
-    Ok(result)
+    let found = results::ResultSet::from_mask(handle, &mask);
+    if !with_context {
+        return Ok(state.results.insert(found));
+    }
+    // 命中行：种子行中同样通过精简条件的行，其余为上下文
+    let hits: Vec<bool> = is_seed.par_iter().zip(&mask).map(|(&seed, &kept)| seed && kept).collect();
+    Ok(state.results.insert(found.with_hits(&hits)))
 }
 
 #[tauri::command]
 async fn save_filtered_logs(
+    result_id: results::ResultId,
     path: String,
-    indices: Vec<usize>,
     state: State<'_, AppState>
 ) -> Result<(), String> {
     use std::fs::File;
     use std::io::{Write, BufWriter};
 
-    let index = state.current_index.lock().unwrap().clone()
-        .ok_or("No file opened")?;
+    let result = state.results.get(result_id)?;
+    let index = state.get_index(result.doc())?;
     
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
-    let line_count = offsets.len();
+    let line_count = index.line_count();
 
     let file = File::create(path).map_err(|e| e.to_string())?;
     let mut writer = BufWriter::new(file);
 
-    for idx in indices {
+    // 带上下文的结果按 grep 的格式导出：命中行 `N:`、上下文行 `N-`，不相邻的组之间写 `--`
+    let mut previous: Option<usize> = None;
+    for idx in result.iter() {
         if idx >= line_count { continue; }
-        let start = offsets[idx];
-        let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-        let line_str = bytes_to_string_with_encoding(&bytes[start..end], index.encoding);
+        let line_str = index.line_str(idx);
+
+        if result.has_context() && previous.is_some_and(|p| idx != p + 1) {
+            writeln!(writer, "--").map_err(|e| e.to_string())?;
+        }
+        previous = Some(idx);
         
         // 写入 1-based 行号前缀
-        writeln!(writer, "{}: {}", idx + 1, line_str.trim_end()).map_err(|e| e.to_string())?;
+        let mark = if result.is_hit(idx) { ':' } else { '-' };
+        writeln!(writer, "{}{} {}", idx + 1, mark, line_str.trim_end()).map_err(|e| e.to_string())?;
     }
 
     writer.flush().map_err(|e| e.to_string())?;
@@ -1244,35 +2385,133 @@ async fn save_filtered_logs(
 
 #[tauri::command]
 async fn find_first_occurrence(
+    handle: DocHandle,
     query: String,
     line_ranges: Option<Vec<(usize, usize)>>,
+    options: Option<search_options::SearchOptions>, // 与 search_log 相同的匹配选项
     state: State<'_, AppState>
 ) -> Result<Option<usize>, String> {
-    let index = state.current_index.lock().unwrap().clone()
-        .ok_or("No file opened")?;
+    let index = state.get_index(handle)?;
     
-    let bytes = &index.mmap[..];
-    let offsets = &index.offsets;
-    let line_count = offsets.len();
-    let query_lower = query.to_lowercase();
+    let line_count = index.line_count();
+    let options = options.unwrap_or_default();
+    // 上限为 0 时不会有任何命中，不必扫描
+    if options.limit_first(Some(0)).is_none() {
+        return Ok(None);
+    }
+    let node = options.compile(&[&query], false, "search query")?;
+    let candidates = node.candidates(&|q| index.search_candidates(q));
+    let line_ranges = line_ranges.map(|r| scope::normalize(&r));
 
     // 并行查找第一个匹配项
     let first_match = (0..line_count).into_par_iter().find_first(|&idx| {
-        // 范围检查
-        if let Some(ref ranges) = line_ranges {
-            let ln = idx + 1; // 1-based line number for comparison with session ranges
-            if !ranges.iter().any(|(s, e)| ln >= *s && ln <= *e) { return false; }
-        }
+        if candidates.as_ref().is_some_and(|c| !c.contains(idx)) { return false; }
+        // 范围检查（1-based 行号，与会话范围比较）
+        if line_ranges.as_ref().is_some_and(|ranges| !scope::contains(ranges, idx + 1)) { return false; }
 
-        let start = offsets[idx];
-        let end = if idx + 1 < line_count { offsets[idx+1] } else { bytes.len() };
-        let line_bytes = &bytes[start..end];
-        let line_str = bytes_to_string_with_encoding(line_bytes, index.encoding).to_lowercase();
-        
-        line_str.contains(&query_lower)
+        node.matches(&index.line_view(idx))
     });
 
-    Ok(first_match)
+    // 与 search_log 一样按会话截断（line_ranges 即会话范围）
+    Ok(options.limit_first(first_match))
+}
+
+// 首个时间不早于 time 的行（0-based）：在各范围内依次二分查找，只写时分秒时补上该范围首个时间戳的日期
+// 未给出范围时按行顺序线性查找
+fn line_at_time(
+    index: &LogIndex,
+    time: &str,
+    timestamp_regex: Option<&str>,
+    line_ranges: Option<Vec<(usize, usize)>>,
+) -> Result<Option<usize>, String> {
+    let (extractor, timestamps) = line_times(index, timestamp_regex, None)?
+        .ok_or("Jumping to a time needs a timestamp regex")?;
+    let times = timestamps.carried();
+    let line_count = times.len().min(index.line_count());
+
+    // 未给出范围时整个文件的时间不一定单调（每次启动都会重新计时），不能二分，按行顺序找第一个不早于目标的行
+    let Some(mut ranges) = line_ranges else {
+        let reference = times[..line_count].iter().copied().find(|t| !t.is_nan());
+        let target = extractor.parser().parse_input(time, reference)?;
+        return Ok((0..line_count).into_par_iter().find_first(|&idx| times[idx] >= target));
+    };
+    ranges.sort_unstable();
+
+    for (start, end) in ranges {
+        let range = start.max(1) - 1..end.min(line_count);
+        if range.is_empty() {
+            continue;
+        }
+        let reference = times[range.clone()].iter().copied().find(|t| !t.is_nan());
+        let target = extractor.parser().parse_input(time, reference)?;
+        if let Some(idx) = time_range::first_at_or_after(times, range, target) {
+            return Ok(Some(idx));
+        }
+    }
+    Ok(None)
+}
+
+// 时间模式（@10:30:05）的跳转目标
+#[tauri::command]
+async fn find_line_at_time(
+    handle: DocHandle,
+    time: String,
+    timestamp_regex: Option<String>,
+    line_ranges: Option<Vec<(usize, usize)>>, // 会话范围（1-based 闭区间），每个会话内时间单调不减
+    state: State<'_, AppState>
+) -> Result<Option<usize>, String> {
+    let index = state.get_index(handle)?;
+    line_at_time(&index, &time, timestamp_regex.as_deref(), line_ranges)
+}
+
+// 时间戳检查报告中最多列出的失败行数
+const MAX_REPORTED_TIMESTAMP_FAILURES: usize = 20;
+
+#[derive(Debug, Serialize)]
+pub struct TimestampFailure {
+    line_number: usize, // 1-based
+    text: String,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TimestampReport {
+    format: Option<timestamp::Format>, // 识别出的格式；样本中没有时间戳时为 None
+    utc_offset: i32,                   // 不带时区的时间按此偏移（秒）解释
+    parsed: usize,                     // 成功解析的行数
+    failed: usize,                     // 匹配到时间戳正则但无法解析的行数
+    failures: Vec<TimestampFailure>,   // 前若干条失败的行
+}
+
+// 检查时间戳解析：报告识别出的格式与无法解析的行，而不是把它们静默地当作没有时间戳
+#[tauri::command]
+async fn inspect_timestamps(
+    handle: DocHandle,
+    timestamp_regex: Option<String>, // 缺省沿用解析文档时的时间戳正则
+    state: State<'_, AppState>
+) -> Result<TimestampReport, String> {
+    let index = state.get_index(handle)?;
+    let (extractor, timestamps) = line_times(&index, timestamp_regex.as_deref(), None)?
+        .ok_or("Inspecting timestamps needs a timestamp regex")?;
+    let values = timestamps.values();
+
+    // 没有时间戳的行里再找出匹配到正则却解析失败的
+    let failed: Vec<usize> = (0..values.len()).into_par_iter()
+        .filter(|&idx| values[idx].is_nan())
+        .filter(|&idx| matches!(extractor.extract(&index.line_str(idx)), Some(Err(_))))
+        .collect();
+    let failures = failed.iter().take(MAX_REPORTED_TIMESTAMP_FAILURES).filter_map(|&idx| {
+        let error = extractor.extract(&index.line_str(idx))?.err()?;
+        Some(TimestampFailure { line_number: idx + 1, text: error.text, message: error.message })
+    }).collect();
+
+    Ok(TimestampReport {
+        format: extractor.parser().format().cloned(),
+        utc_offset: extractor.parser().utc_offset(),
+        parsed: values.iter().filter(|v| !v.is_nan()).count(),
+        failed: failed.len(),
+        failures,
+    })
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -1353,6 +2592,11 @@ pub fn run() {
         .plugin(tauri_plugin_fs::init())
         .manage(AppState::default()) // 注册全局状态
         .setup(|app| {
+            // 持久化索引缓存放在应用缓存目录下
+            if let Ok(cache_dir) = app.path().app_cache_dir() {
+                index_cache::init(cache_dir.join("index-cache"));
+            }
+
             let window = app.get_webview_window("main").unwrap();
             
             // 监听文件拖放事件
@@ -1370,12 +2614,35 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             greet,
             parse_log_file,
+            close_log_file,
+            detect_log_format,
+            list_archive_members,
+            open_archive_members,
+            open_rotated_logs,
+            list_log_sources,
+            get_line_source,
+            start_tail,
+            stop_tail,
+            create_job,
+            cancel_job,
             parse_log_content,
             parse_log_with_custom_splitters,
             get_log_range,
             get_log_lines_by_indices,
             search_log,
+            search_log_result,
+            build_search_index,
+            validate_regex,
+            get_scope_ranges,
+            find_line_at_time,
+            inspect_timestamps,
             get_filtered_indices,
+            get_result_info,
+            get_result_page,
+            get_result_lines,
+            get_result_rank,
+            intersect_results,
+            release_result,
             analyze_log_patterns,
             extract_metrics,
             analyze_time_gaps,
@@ -0,0 +1,337 @@
+// 紧凑行索引：行偏移按块增量编码，日志级别存为 u8 代码 + 级别名表，亿行级文件也只需数百 MB
+// 另有按正则缓存的逐行派生数据（切分命中、时间戳），由建索引时的单次扫描一并产出
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::{Arc, Mutex, OnceLock};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::timestamp::Extractor;
+
+// 每块的行数；块内偏移相对块首行存储，按块内最大差值选择 1/2/4/8 字节宽度
+const BLOCK_LINES: usize = 64;
+
+// 级别名表的容量（代码 0 表示无级别）
+const MAX_LEVELS: usize = u8::MAX as usize;
+
+// 每个文档最多缓存多少个切分正则的命中结果
+const MAX_CACHED_SPLITTERS: usize = 16;
+
+#[derive(Clone)]
+struct Block {
+    base: u64,  // 块首行的偏移
+    start: u64, // 块内增量在 data 中的起始位置
+    width: u8,
+}
+
+/// 单调不减的行起始偏移序列
+#[derive(Clone, Default)]
+pub struct LineOffsets {
+    blocks: Vec<Block>, // 已封存的整块
+    data: Vec<u8>,      // 各块内每行相对块首行的增量，小端
+    open: Vec<usize>,   // 末尾尚未凑满一块的行
+}
+
+impl LineOffsets {
+    pub fn len(&self) -> usize {
+        self.blocks.len() * BLOCK_LINES + self.open.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn get(&self, idx: usize) -> usize {
+        let (b, i) = (idx / BLOCK_LINES, idx % BLOCK_LINES);
+        let Some(block) = self.blocks.get(b) else {
+            return self.open[idx - self.blocks.len() * BLOCK_LINES];
+        };
+        let width = block.width as usize;
+        let at = block.start as usize + i * width;
+        let mut raw = [0u8; 8];
+        raw[..width].copy_from_slice(&self.data[at..at + width]);
+        (block.base + u64::from_le_bytes(raw)) as usize
+    }
+
+    pub fn last(&self) -> Option<usize> {
+        self.len().checked_sub(1).map(|idx| self.get(idx))
+    }
+
+    pub fn push(&mut self, offset: usize) {
+        self.open.push(offset);
+        if self.open.len() == BLOCK_LINES {
+            self.seal();
+        }
+    }
+
+    pub fn pop(&mut self) -> Option<usize> {
+        if self.open.is_empty() && !self.blocks.is_empty() {
+            self.unseal_last();
+        }
+        self.open.pop()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        if len < self.blocks.len() * BLOCK_LINES {
+            while self.blocks.len() * BLOCK_LINES > len {
+                self.unseal_last();
+            }
+        }
+        self.open.truncate(len - self.blocks.len() * BLOCK_LINES);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.len()).map(|idx| self.get(idx))
+    }
+
+    fn seal(&mut self) {
+        let base = self.open[0] as u64;
+        let span = (self.open[BLOCK_LINES - 1] as u64).saturating_sub(base);
+        let width: u8 = match span {
+            0..=0xff => 1,
+            0x100..=0xffff => 2,
+            0x1_0000..=0xffff_ffff => 4,
+            _ => 8,
+        };
+        let start = self.data.len() as u64;
+        for &offset in &self.open {
+            let delta = (offset as u64 - base).to_le_bytes();
+            self.data.extend_from_slice(&delta[..width as usize]);
+        }
+        self.blocks.push(Block { base, start, width });
+        self.open.clear();
+    }
+
+    // 拆开最后一个整块放回 open（调用前 open 必须为空）
+    fn unseal_last(&mut self) {
+        let Some(block) = self.blocks.last().cloned() else { return };
+        let first = (self.blocks.len() - 1) * BLOCK_LINES;
+        self.open = (first..first + BLOCK_LINES).map(|idx| self.get(idx)).collect();
+        self.blocks.pop();
+        self.data.truncate(block.start as usize);
+    }
+}
+
+impl Extend<usize> for LineOffsets {
+    fn extend<I: IntoIterator<Item = usize>>(&mut self, iter: I) {
+        for offset in iter {
+            self.push(offset);
+        }
+    }
+}
+
+impl FromIterator<usize> for LineOffsets {
+    fn from_iter<I: IntoIterator<Item = usize>>(iter: I) -> Self {
+        let mut offsets = LineOffsets::default();
+        offsets.extend(iter);
+        offsets
+    }
+}
+
+/// 每行的日志级别：u8 代码，n 对应 table[n - 1]，0 表示无级别
+/// 级别取值超过 255 种时多半是级别正则写错了，多出的取值按无级别处理
+#[derive(Clone, Default)]
+pub struct LevelCodes {
+    codes: Vec<u8>,
+    table: Vec<String>,
+}
+
+impl LevelCodes {
+    pub fn len(&self) -> usize {
+        self.codes.len()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&str> {
+        match self.codes.get(idx).copied().unwrap_or(0) {
+            0 => None,
+            code => Some(&self.table[code as usize - 1]),
+        }
+    }
+
+    pub fn code(&self, idx: usize) -> u8 {
+        self.codes[idx]
+    }
+
+    pub fn table(&self) -> &[String] {
+        &self.table
+    }
+
+    pub fn codes(&self) -> &[u8] {
+        &self.codes
+    }
+
+    // 已登记的级别代码（只读，可在并行阶段使用）
+    pub fn lookup(&self, level: &str) -> Option<u8> {
+        self.table.iter().position(|t| t == level).map(|pos| pos as u8 + 1)
+    }
+
+    // 取级别代码，新取值加入级别名表
+    pub fn intern(&mut self, level: &str) -> u8 {
+        if let Some(code) = self.lookup(level) {
+            return code;
+        }
+        if self.table.len() >= MAX_LEVELS {
+            return 0;
+        }
+        self.table.push(level.to_string());
+        self.table.len() as u8
+    }
+
+    pub fn push(&mut self, level: Option<&str>) {
+        let code = level.map_or(0, |l| self.intern(l));
+        self.codes.push(code);
+    }
+
+    pub fn push_code(&mut self, code: u8) {
+        self.codes.push(code);
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.codes.truncate(len);
+    }
+
+    // 由代码与级别名表直接构建（读取持久化缓存时使用）
+    pub fn from_parts(codes: Vec<u8>, table: Vec<String>) -> Self {
+        let limit = table.len() as u8;
+        let codes = codes.into_iter().map(|c| if c > limit { 0 } else { c }).collect();
+        LevelCodes { codes, table }
+    }
+
+    pub fn append(&mut self, other: &LevelCodes) {
+        let remap: Vec<u8> = std::iter::once(0)
+            .chain(other.table.iter().map(|level| self.intern(level)))
+            .collect();
+        self.codes.extend(other.codes.iter().map(|&c| remap[c as usize]));
+    }
+
+    /// range 内各行级别的游程编码，发送给前端
+    pub fn runs(&self, range: Range<usize>) -> LevelRuns {
+        let mut runs: Vec<u32> = Vec::new();
+        for &code in &self.codes[range] {
+            match runs.len() {
+                n if n >= 2 && runs[n - 2] == code as u32 => runs[n - 1] += 1,
+                _ => runs.extend([code as u32, 1]),
+            }
+        }
+        LevelRuns { table: self.table.clone(), runs }
+    }
+}
+
+/// 级别的传输格式：runs 为扁平的 [代码, 行数, 代码, 行数, ...]，代码 n 对应 table[n - 1]，0 表示无级别
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LevelRuns {
+    table: Vec<String>,
+    runs: Vec<u32>,
+}
+
+/// 每行时间戳（秒），NaN 表示该行没有可解析的时间戳
+/// 按时间定位或筛选时使用沿用上一行时间戳后的逐行时间，首次用到时计算
+#[derive(Clone, Default)]
+pub struct LineTimestamps {
+    values: Vec<f64>,
+    carried: OnceLock<Vec<f64>>,
+}
+
+impl LineTimestamps {
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn get(&self, idx: usize) -> Option<f64> {
+        self.values.get(idx).copied().filter(|v| !v.is_nan())
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    /// 没有时间戳的行（如堆栈续行）沿用上一行的时间，首个时间戳之前的行为 NaN
+    pub fn carried(&self) -> &[f64] {
+        self.carried.get_or_init(|| {
+            let mut last = f64::NAN;
+            self.values.iter().map(|&v| {
+                if !v.is_nan() {
+                    last = v;
+                }
+                last
+            }).collect()
+        })
+    }
+
+    /// 首个可解析的时间戳
+    pub fn first(&self) -> Option<f64> {
+        self.values.iter().copied().find(|v| !v.is_nan())
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.values.truncate(len);
+        self.carried = OnceLock::new();
+    }
+
+    pub fn extend(&mut self, values: impl IntoIterator<Item = f64>) {
+        self.values.extend(values);
+        self.carried = OnceLock::new();
+    }
+}
+
+impl From<Vec<f64>> for LineTimestamps {
+    fn from(values: Vec<f64>) -> Self {
+        LineTimestamps { values, carried: OnceLock::new() }
+    }
+}
+
+// 正则 -> (正则, 命中行 (0-based，升序))
+type SplitterHits = HashMap<String, (Regex, Arc<Vec<usize>>)>;
+
+/// 按正则缓存的逐行派生数据：切分正则（启动标识、自定义切分器）的命中行与时间戳
+/// 重新切分会话时直接复用，跟随模式下随索引一起增量扩展
+#[derive(Default)]
+pub struct LineCache {
+    hits: Mutex<SplitterHits>,
+    timestamps: Mutex<Option<(Extractor, Arc<LineTimestamps>)>>, // 只保留最近使用的时间戳正则与解析设置
+}
+
+impl LineCache {
+    pub fn hits(&self, re: &Regex) -> Option<Arc<Vec<usize>>> {
+        self.hits.lock().unwrap().get(re.as_str()).map(|(_, hits)| hits.clone())
+    }
+
+    pub fn store_hits(&self, re: &Regex, hits: Arc<Vec<usize>>) {
+        let mut cache = self.hits.lock().unwrap();
+        if cache.len() >= MAX_CACHED_SPLITTERS && !cache.contains_key(re.as_str()) {
+            cache.clear();
+        }
+        cache.insert(re.as_str().to_string(), (re.clone(), hits));
+    }
+
+    pub fn timestamps(&self, extractor: &Extractor) -> Option<Arc<LineTimestamps>> {
+        self.timestamps.lock().unwrap().as_ref()
+            .filter(|(cached, _)| cached.key() == extractor.key())
+            .map(|(_, values)| values.clone())
+    }
+
+    pub fn store_timestamps(&self, extractor: &Extractor, values: Arc<LineTimestamps>) {
+        *self.timestamps.lock().unwrap() = Some((extractor.clone(), values));
+    }
+
+    // 最近使用的时间戳提取器（含识别出的格式）
+    pub fn extractor(&self) -> Option<Extractor> {
+        self.timestamps.lock().unwrap().as_ref().map(|(ex, _)| ex.clone())
+    }
+
+    // 已缓存的切分正则与时间戳提取器（跟随模式扩展时使用）
+    pub fn patterns(&self) -> (Vec<Regex>, Option<Extractor>) {
+        let hits = self.hits.lock().unwrap().values().map(|(re, _)| re.clone()).collect();
+        (hits, self.extractor())
+    }
+}
+
+impl Clone for LineCache {
+    fn clone(&self) -> Self {
+        LineCache {
+            hits: Mutex::new(self.hits.lock().unwrap().clone()),
+            timestamps: Mutex::new(self.timestamps.lock().unwrap().clone()),
+        }
+    }
+}
@@ -0,0 +1,146 @@
+// 字面量搜索：直接在 mmap 的行字节上匹配，不逐行解码、不逐行转小写
+// 单个模式用 memchr 的 SIMD 子串查找，多个模式或需要忽略大小写时用 Aho-Corasick（ASCII 大小写折叠）；
+// 模式含有非 ASCII 的大小写字母时退回 Unicode 正则。UTF-16 文件把模式编码为 UTF-16 后按码元对齐匹配
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder, Input, MatchKind};
+use memchr::memmem;
+
+use crate::{bytes_to_string_with_encoding, FileEncoding};
+
+enum ByteMatcher {
+    Plain(Box<memmem::Finder<'static>>),
+    Multi(AhoCorasick),
+    Unicode(regex::bytes::Regex),
+}
+
+impl ByteMatcher {
+    fn build(patterns: &[Vec<u8>], case_insensitive: bool) -> Result<Self, String> {
+        if let ([pattern], false) = (patterns, case_insensitive) {
+            return Ok(ByteMatcher::Plain(Box::new(memmem::Finder::new(pattern).into_owned())));
+        }
+        // 没有字母的单个模式不需要大小写折叠
+        if let [pattern] = patterns {
+            if !pattern.iter().any(u8::is_ascii_alphabetic) {
+                return Ok(ByteMatcher::Plain(Box::new(memmem::Finder::new(pattern).into_owned())));
+            }
+        }
+        AhoCorasickBuilder::new()
+            .ascii_case_insensitive(case_insensitive)
+            .match_kind(MatchKind::LeftmostLongest)
+            .build(patterns)
+            .map(ByteMatcher::Multi)
+            .map_err(|e| e.to_string())
+    }
+
+    // 第一个命中的区间（从 from 开始查找）
+    fn find_at(&self, bytes: &[u8], from: usize) -> Option<(usize, usize)> {
+        match self {
+            ByteMatcher::Plain(finder) => finder.find(&bytes[from..])
+                .map(|pos| (from + pos, from + pos + finder.needle().len())),
+            ByteMatcher::Multi(ac) => ac.find(Input::new(bytes).span(from..bytes.len())).map(|m| (m.start(), m.end())),
+            ByteMatcher::Unicode(re) => re.find_at(bytes, from).map(|m| (m.start(), m.end())),
+        }
+    }
+
+    fn find_from(&self, bytes: &[u8], from: usize) -> Option<usize> {
+        self.find_at(bytes, from).map(|(start, _)| start)
+    }
+
+    fn is_match(&self, bytes: &[u8]) -> bool {
+        self.find_from(bytes, 0).is_some()
+    }
+
+    // UTF-16：只接受从码元边界开始的命中
+    fn is_match_aligned(&self, bytes: &[u8]) -> bool {
+        let mut from = 0;
+        while from <= bytes.len() {
+            match self.find_from(bytes, from) {
+                Some(pos) if pos % 2 == 0 => return true,
+                Some(pos) => from = pos + 1,
+                None => return false,
+            }
+        }
+        false
+    }
+}
+
+/// 一组字面量模式，任意一个出现在行中即命中
+pub struct LiteralMatcher {
+    utf8: ByteMatcher,
+    utf16le: Option<ByteMatcher>, // 无法按字节安全折叠大小写时为 None，退回解码后匹配
+    utf16be: Option<ByteMatcher>,
+    ascii: bool, // 模式全为 ASCII：Latin-1 文件可直接按字节匹配
+}
+
+impl LiteralMatcher {
+    pub fn new<S: AsRef<str>>(patterns: &[S], case_insensitive: bool) -> Result<Self, String> {
+        let patterns: Vec<&str> = patterns.iter().map(AsRef::as_ref).collect();
+        let ascii = patterns.iter().all(|p| p.is_ascii());
+        let unicode_case = case_insensitive && patterns.iter()
+            .flat_map(|p| p.chars())
+            .any(|c| !c.is_ascii() && (c.is_lowercase() || c.is_uppercase()));
+
+        let utf8 = if unicode_case {
+            let alternation: Vec<String> = patterns.iter().map(|p| regex::escape(p)).collect();
+            let re = regex::bytes::RegexBuilder::new(&alternation.join("|"))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| e.to_string())?;
+            ByteMatcher::Unicode(re)
+        } else {
+            let bytes: Vec<Vec<u8>> = patterns.iter().map(|p| p.as_bytes().to_vec()).collect();
+            ByteMatcher::build(&bytes, case_insensitive)?
+        };
+
+        // UTF-16 的非 ASCII 码元里可能含有字母字节，只有模式全为 ASCII（或区分大小写）时才能按字节折叠
+        let (utf16le, utf16be) = if ascii || !case_insensitive {
+            let encode = |to_bytes: fn(u16) -> [u8; 2]| -> Vec<Vec<u8>> {
+                patterns.iter().map(|p| p.encode_utf16().flat_map(to_bytes).collect()).collect()
+            };
+            (
+                Some(ByteMatcher::build(&encode(u16::to_le_bytes), case_insensitive)?),
+                Some(ByteMatcher::build(&encode(u16::to_be_bytes), case_insensitive)?),
+            )
+        } else {
+            (None, None)
+        };
+
+        Ok(LiteralMatcher { utf8, utf16le, utf16be, ascii })
+    }
+
+    /// 在某个编码的原始行字节上匹配；GB18030 等多字节编码的尾字节可能落在 ASCII 区间，只能解码后匹配
+    pub fn is_match(&self, bytes: &[u8], encoding: FileEncoding) -> bool {
+        let utf16 = match encoding {
+            FileEncoding::Utf8 => return self.utf8.is_match(bytes),
+            FileEncoding::Latin1 if self.ascii => return self.utf8.is_match(bytes),
+            FileEncoding::Utf16Le => self.utf16le.as_ref(),
+            FileEncoding::Utf16Be => self.utf16be.as_ref(),
+            _ => None,
+        };
+        match utf16 {
+            Some(matcher) => matcher.is_match_aligned(bytes),
+            None => self.is_match_str(&bytes_to_string_with_encoding(bytes, encoding)),
+        }
+    }
+
+    pub fn is_match_str(&self, text: &str) -> bool {
+        self.utf8.is_match(text.as_bytes())
+    }
+
+    /// 解码后的文本中所有不重叠的命中（字节区间，最左最长）；空模式不产生命中
+    pub fn find_iter_str(&self, text: &str) -> Vec<(usize, usize)> {
+        let bytes = text.as_bytes();
+        let mut spans = Vec::new();
+        let mut from = 0;
+        while from <= bytes.len() {
+            match self.utf8.find_at(bytes, from) {
+                Some((start, end)) if end > start => {
+                    spans.push((start, end));
+                    from = end;
+                }
+                Some((start, _)) => from = start + 1,
+                None => break,
+            }
+        }
+        spans
+    }
+}
@@ -0,0 +1,452 @@
+// 过滤查询语言：AND / OR / NOT、括号分组与字段谓词，编译为一棵匹配树
+//   (error OR fatal) AND NOT /timeout \d+/ AND level>=WARN AND tid=1234
+// 词项：普通单词或 "带空格的短语"（不区分大小写）、=精确（区分大小写）、/正则/（不区分大小写）
+// 运算符：NOT（或前缀 !）优先于 AND（或 &&，相邻词项默认为 AND），AND 优先于 OR（或 ||）；关键字须大写
+// 字段谓词：level 按严重程度比较（无级别的行视为 INFO），其他字段在行内查找 `名称=值`、`名称:值` 或 `名称 值`，
+// = / != 按文本比较（不区分大小写），> >= < <= 按数值比较
+use std::cell::OnceCell;
+use std::fmt;
+
+use regex::Regex;
+
+use crate::literal::LiteralMatcher;
+use crate::trigram::{Candidates, TrigramQuery};
+use crate::{bytes_to_string_with_encoding, FileEncoding};
+
+/// 语法错误；column 为查询文本中的字符位置（从 1 开始）
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueryError {
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Query syntax error at column {}: {}", self.column, self.message)
+    }
+}
+
+fn error<T>(pos: usize, message: impl Into<String>) -> Result<T, QueryError> {
+    Err(QueryError { column: pos + 1, message: message.into() })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CmpOp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+impl CmpOp {
+    fn holds(self, ord: std::cmp::Ordering) -> bool {
+        use std::cmp::Ordering::*;
+        match self {
+            CmpOp::Eq => ord == Equal,
+            CmpOp::Ne => ord != Equal,
+            CmpOp::Lt => ord == Less,
+            CmpOp::Le => ord != Greater,
+            CmpOp::Gt => ord == Greater,
+            CmpOp::Ge => ord != Less,
+        }
+    }
+}
+
+// 级别的严重程度，用于 level>=WARN 之类的比较
+fn severity(level: &str) -> Option<u8> {
+    Some(match level.to_ascii_uppercase().as_str() {
+        "TRACE" => 0,
+        "DEBUG" => 1,
+        "INFO" | "NORM" | "SUCCESS" => 2,
+        "WARN" | "WARNING" => 3,
+        "ERROR" | "ERR" => 4,
+        "FATAL" | "CRITICAL" | "CRIT" => 5,
+        _ => return None,
+    })
+}
+
+/// 编译后的匹配树
+pub enum Node {
+    And(Vec<Node>),
+    Or(Vec<Node>),
+    Not(Box<Node>),
+    Literal { matcher: LiteralMatcher, patterns: Vec<String>, case_insensitive: bool }, // 任意一个字面量出现即命中
+    Regex(Regex),
+    Level { op: CmpOp, name: String, rank: Option<u8> },
+    Field { re: Regex, op: CmpOp, value: String, number: Option<f64> },
+}
+
+/// 参与匹配的一行；只有正则与字段谓词才解码行内容
+pub struct LineView<'a> {
+    bytes: &'a [u8],
+    encoding: FileEncoding,
+    level: Option<&'a str>,
+    text: OnceCell<String>,
+}
+
+impl<'a> LineView<'a> {
+    pub fn new(bytes: &'a [u8], encoding: FileEncoding, level: Option<&'a str>) -> Self {
+        LineView { bytes, encoding, level, text: OnceCell::new() }
+    }
+
+    fn text(&self) -> &str {
+        self.text.get_or_init(|| bytes_to_string_with_encoding(self.bytes, self.encoding))
+    }
+}
+
+impl Node {
+    pub fn literal(pattern: &str, case_insensitive: bool) -> Result<Node, String> {
+        Node::literals(&[pattern], case_insensitive)
+    }
+
+    /// 一组字面量合成一个匹配器，每行只扫描一遍
+    pub fn literals<S: AsRef<str>>(patterns: &[S], case_insensitive: bool) -> Result<Node, String> {
+        Ok(Node::Literal {
+            matcher: LiteralMatcher::new(patterns, case_insensitive)?,
+            patterns: patterns.iter().map(|p| p.as_ref().to_string()).collect(),
+            case_insensitive,
+        })
+    }
+
+    pub fn matches(&self, line: &LineView) -> bool {
+        match self {
+            Node::And(nodes) => nodes.iter().all(|n| n.matches(line)),
+            Node::Or(nodes) => nodes.iter().any(|n| n.matches(line)),
+            Node::Not(node) => !node.matches(line),
+            Node::Literal { matcher, .. } => matcher.is_match(line.bytes, line.encoding),
+            Node::Regex(re) => re.is_match(line.text()),
+            Node::Level { op, name, rank } => {
+                let level = line.level.unwrap_or("INFO");
+                match (op, rank) {
+                    (CmpOp::Eq, _) => level.eq_ignore_ascii_case(name),
+                    (CmpOp::Ne, _) => !level.eq_ignore_ascii_case(name),
+                    (_, Some(rank)) => severity(level).is_some_and(|r| op.holds(r.cmp(rank))),
+                    (_, None) => false,
+                }
+            }
+            Node::Field { re, op, value, number } => {
+                let mut values = re.captures_iter(line.text()).filter_map(|c| c.get(1)).map(|m| m.as_str());
+                match (op, number) {
+                    (CmpOp::Eq, _) => values.any(|v| v.eq_ignore_ascii_case(value)),
+                    (CmpOp::Ne, _) => !values.any(|v| v.eq_ignore_ascii_case(value)),
+                    (_, Some(number)) => values.any(|v| {
+                        v.parse::<f64>().ok()
+                            .and_then(|v| v.partial_cmp(number))
+                            .is_some_and(|ord| op.holds(ord))
+                    }),
+                    (_, None) => false,
+                }
+            }
+        }
+    }
+
+    /// 可能命中的 trigram 候选块；无法缩小范围（含 NOT、字段谓词或索引未建立）时返回 None
+    pub fn candidates(&self, lookup: &dyn Fn(&TrigramQuery) -> Option<Candidates>) -> Option<Candidates> {
+        match self {
+            Node::And(nodes) => nodes.iter().fold(None, |acc, n| Candidates::both(acc, n.candidates(lookup))),
+            Node::Or(nodes) => nodes.iter()
+                .map(|n| n.candidates(lookup))
+                .reduce(|a, b| Candidates::either(a?, b?))
+                .flatten(),
+            Node::Literal { patterns, case_insensitive, .. } => {
+                lookup(&TrigramQuery::literals(patterns, *case_insensitive))
+            }
+            Node::Regex(re) => lookup(&TrigramQuery::regex(re.as_str(), true)),
+            Node::Not(_) | Node::Level { .. } | Node::Field { .. } => None,
+        }
+    }
+
+    /// 不在 NOT 之下的文本词项的命中区间（字节偏移，按起始位置排序），用于高亮
+    pub fn find_iter(&self, text: &str) -> Vec<(usize, usize)> {
+        let mut spans = Vec::new();
+        self.collect_spans(text, &mut spans);
+        spans.sort_unstable();
+        spans
+    }
+
+    fn collect_spans(&self, text: &str, spans: &mut Vec<(usize, usize)>) {
+        match self {
+            Node::And(nodes) | Node::Or(nodes) => nodes.iter().for_each(|n| n.collect_spans(text, spans)),
+            Node::Literal { matcher, .. } => spans.extend(matcher.find_iter_str(text)),
+            Node::Regex(re) => spans.extend(re.find_iter(text).filter(|m| !m.is_empty()).map(|m| (m.start(), m.end()))),
+            Node::Not(_) | Node::Level { .. } | Node::Field { .. } => {}
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    LParen,
+    RParen,
+    And,
+    Or,
+    Not,
+    Word(String),
+    Phrase(String),
+    Exact(String),
+    Regex(String),
+    Field(String, CmpOp, String),
+}
+
+// 词法分析：返回 (起始字符位置, 词法单元)
+fn tokenize(chars: &[char]) -> Result<Vec<(usize, Token)>, QueryError> {
+    let mut tokens = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let start = i;
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        let token = match c {
+            '(' => { i += 1; Token::LParen }
+            ')' => { i += 1; Token::RParen }
+            '!' => { i += 1; Token::Not }
+            '"' => Token::Phrase(read_delimited(chars, &mut i, '"', "unterminated quoted phrase")?),
+            '/' => Token::Regex(read_delimited(chars, &mut i, '/', "unterminated regex, expected closing '/'")?),
+            '=' if chars.get(i + 1) == Some(&'"') => {
+                i += 1;
+                Token::Exact(read_delimited(chars, &mut i, '"', "unterminated quoted phrase")?)
+            }
+            _ => {
+                while i < chars.len() && !chars[i].is_whitespace() && chars[i] != '(' && chars[i] != ')' {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                match word.as_str() {
+                    "AND" | "&&" => Token::And,
+                    "OR" | "||" => Token::Or,
+                    "NOT" => Token::Not,
+                    _ => match word.strip_prefix('=') {
+                        Some("") => return error(start, "expected text after '='"),
+                        Some(exact) => Token::Exact(exact.to_string()),
+                        None => field_predicate(&word, start)?.unwrap_or(Token::Word(word)),
+                    },
+                }
+            }
+        };
+        tokens.push((start, token));
+    }
+    Ok(tokens)
+}
+
+// 读取 delim 包围的内容（i 指向起始分隔符），反斜杠转义分隔符本身；正则中的其他转义原样保留
+fn read_delimited(chars: &[char], i: &mut usize, delim: char, unterminated: &str) -> Result<String, QueryError> {
+    let start = *i;
+    let mut out = String::new();
+    *i += 1;
+    while *i < chars.len() {
+        match chars[*i] {
+            '\\' if chars.get(*i + 1) == Some(&delim) => {
+                out.push(delim);
+                *i += 2;
+            }
+            '\\' if delim == '"' && chars.get(*i + 1) == Some(&'\\') => {
+                out.push('\\');
+                *i += 2;
+            }
+            c if c == delim => {
+                *i += 1;
+                return Ok(out);
+            }
+            c => {
+                out.push(c);
+                *i += 1;
+            }
+        }
+    }
+    error(start, unterminated)
+}
+
+// name<op>value 形式的字段谓词；名称须以字母或下划线开头
+fn field_predicate(word: &str, pos: usize) -> Result<Option<Token>, QueryError> {
+    let name_len = word.find(|c: char| !(c.is_alphanumeric() || c == '_' || c == '.' || c == '-')).unwrap_or(word.len());
+    let (name, rest) = word.split_at(name_len);
+    if name.is_empty() || !name.starts_with(|c: char| c.is_alphabetic() || c == '_') {
+        return Ok(None);
+    }
+    let (op, op_len) = match rest.as_bytes() {
+        [b'>', b'=', ..] => (CmpOp::Ge, 2),
+        [b'<', b'=', ..] => (CmpOp::Le, 2),
+        [b'!', b'=', ..] => (CmpOp::Ne, 2),
+        [b'=', ..] => (CmpOp::Eq, 1),
+        [b'>', ..] => (CmpOp::Gt, 1),
+        [b'<', ..] => (CmpOp::Lt, 1),
+        _ => return Ok(None),
+    };
+    let value = rest[op_len..].trim_matches('"');
+    if value.is_empty() {
+        return error(pos + word.chars().count(), format!("expected a value after '{}{}'", name, &rest[..op_len]));
+    }
+    Ok(Some(Token::Field(name.to_string(), op, value.to_string())))
+}
+
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+    end: usize, // 查询文本长度，用于报告“意外结束”的位置
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos).map(|(_, t)| t)
+    }
+
+    fn here(&self) -> usize {
+        self.tokens.get(self.pos).map_or(self.end, |(p, _)| *p)
+    }
+
+    fn or(&mut self) -> Result<Node, QueryError> {
+        let mut nodes = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            nodes.push(self.and()?);
+        }
+        Ok(if nodes.len() == 1 { nodes.pop().unwrap() } else { Node::Or(nodes) })
+    }
+
+    fn and(&mut self) -> Result<Node, QueryError> {
+        let mut nodes = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => {
+                    self.pos += 1;
+                    nodes.push(self.unary()?);
+                }
+                // 相邻的词项默认为 AND
+                Some(Token::Or) | Some(Token::RParen) | None => break,
+                Some(_) => nodes.push(self.unary()?),
+            }
+        }
+        Ok(if nodes.len() == 1 { nodes.pop().unwrap() } else { Node::And(nodes) })
+    }
+
+    fn unary(&mut self) -> Result<Node, QueryError> {
+        let at = self.here();
+        let Some((_, token)) = self.tokens.get(self.pos).cloned() else {
+            return error(at, "unexpected end of query, expected a term");
+        };
+        self.pos += 1;
+        match token {
+            Token::Not => Ok(Node::Not(Box::new(self.unary()?))),
+            Token::LParen => {
+                let inner = self.or()?;
+                if self.peek() != Some(&Token::RParen) {
+                    return error(self.here(), format!("expected ')' to close '(' at column {}", at + 1));
+                }
+                self.pos += 1;
+                Ok(inner)
+            }
+            Token::RParen => error(at, "unexpected ')'"),
+            Token::And | Token::Or => error(at, "expected a term, found an operator"),
+            Token::Word(text) | Token::Phrase(text) => Node::literal(&text, true).or_else(|e| error(at, e)),
+            Token::Exact(text) => Node::literal(&text, false).or_else(|e| error(at, e)),
+            // 正则内的出错位置换算到查询文本中（跳过起始的 /）
+            Token::Regex(pattern) => crate::validate::regex(&pattern, true)
+                .map(Node::Regex)
+                .or_else(|e| error(at + e.column.unwrap_or(0), format!("invalid regex: {}", e.message))),
+            Token::Field(name, op, value) => field_node(&name, op, &value, at),
+        }
+    }
+}
+
+fn field_node(name: &str, op: CmpOp, value: &str, at: usize) -> Result<Node, QueryError> {
+    let ordered = !matches!(op, CmpOp::Eq | CmpOp::Ne);
+    if name.eq_ignore_ascii_case("level") {
+        let rank = severity(value);
+        if ordered && rank.is_none() {
+            return error(at, format!("unknown level '{}' (expected TRACE, DEBUG, INFO, WARN, ERROR or FATAL)", value));
+        }
+        return Ok(Node::Level { op, name: value.to_string(), rank });
+    }
+    let number = value.parse::<f64>().ok();
+    if ordered && number.is_none() {
+        return error(at, format!("'{}' is not a number", value));
+    }
+    let re = Regex::new(&format!(
+        r#"(?i)(?:^|[^\w.]){}(?:\s*[=:]\s*|\s+)"?([^\s,;"'\]\)\}}]+)"#,
+        regex::escape(name)
+    )).map_err(|e| QueryError { column: at + 1, message: e.to_string() })?;
+    Ok(Node::Field { re, op, value: value.to_string(), number })
+}
+
+/// 解析查询文本
+pub fn parse(query: &str) -> Result<Node, QueryError> {
+    let chars: Vec<char> = query.chars().collect();
+    let tokens = tokenize(&chars)?;
+    if tokens.is_empty() {
+        return error(0, "empty query");
+    }
+    let mut parser = Parser { tokens, pos: 0, end: chars.len() };
+    let node = parser.or()?;
+    if parser.pos < parser.tokens.len() {
+        return error(parser.here(), "unexpected ')'");
+    }
+    Ok(node)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 返回命中的行号
+    fn eval(query: &str, lines: &[(&str, Option<&str>)]) -> Vec<usize> {
+        let node = parse(query).unwrap_or_else(|e| panic!("{}: {}", query, e));
+        lines.iter().enumerate()
+            .filter(|(_, (text, level))| node.matches(&LineView::new(text.as_bytes(), FileEncoding::Utf8, *level)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    #[test]
+    fn precedence_and_grouping() {
+        let lines = [("a", None), ("b", None), ("c", None), ("a b", None), ("b c", None), ("a c", None)];
+        // NOT > AND > OR
+        assert_eq!(eval("a OR b AND c", &lines), [0, 3, 4, 5]);
+        assert_eq!(eval("(a OR b) AND c", &lines), [4, 5]);
+        assert_eq!(eval("NOT a AND b", &lines), [1, 4]);
+        assert_eq!(eval("NOT (a AND b)", &lines), [0, 1, 2, 4, 5]);
+        assert_eq!(eval("!a c OR a b", &lines), [2, 3, 4]);
+        // 相邻词项默认为 AND，&& 与 || 同关键字
+        assert_eq!(eval("a b", &lines), eval("a AND b", &lines));
+        assert_eq!(eval("a && c || b", &lines), [1, 3, 4, 5]);
+        // 关键字须大写
+        assert_eq!(eval("a or", &[("a or", None), ("a", None)]), [0]);
+    }
+
+    #[test]
+    fn field_predicates() {
+        let lines = [
+            ("connect tid=1234 latency=250", Some("ERROR")),
+            ("retry tid: 99 latency=50", Some("WARN")),
+            ("idle [tid 1234]", None),
+            ("trace tid=12345", Some("DEBUG")),
+        ];
+        // 无级别的行视为 INFO
+        assert_eq!(eval("level>=WARN", &lines), [0, 1]);
+        assert_eq!(eval("level=info", &lines), [2]);
+        assert_eq!(eval("level<INFO", &lines), [3]);
+        // 文本比较取完整的值
+        assert_eq!(eval("tid=1234", &lines), [0, 2]);
+        assert_eq!(eval("tid!=1234", &lines), [1, 3]);
+        // 数值比较
+        assert_eq!(eval("latency>100", &lines), [0]);
+        assert_eq!(eval("tid<1000 OR latency>=250", &lines), [0, 1]);
+        assert_eq!(eval("level>=WARN AND NOT tid=99", &lines), [0]);
+    }
+
+    #[test]
+    fn error_columns() {
+        let err = |q: &str| parse(q).err().unwrap_or_else(|| panic!("{} parsed", q));
+        assert_eq!(err("(a OR b").column, 8);
+        assert_eq!(err("a OR").column, 5);
+        assert_eq!(err("a )").column, 3);
+        assert_eq!(err("a AND OR b").column, 7);
+        assert_eq!(err("a /(/").column, 4);
+        assert_eq!(err("level>=LOUD").column, 1);
+        assert_eq!(err("tid>abc").column, 1);
+    }
+}
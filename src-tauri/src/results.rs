@@ -0,0 +1,316 @@
+// 结果集：过滤/搜索命中的行保存在后端（位图或区间），前端只持有句柄，按需分页、计数、求交集或导出
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::DocHandle;
+
+/// 结果集句柄：由过滤/搜索命令返回，用完后调用 `release_result` 释放
+pub type ResultId = u64;
+
+// 位图每隔多少个 u64 字记录一次累计命中数，按名次定位时先二分到块再逐字扫描
+const RANK_BLOCK_WORDS: usize = 8;
+
+enum Repr {
+    // 连续命中区间 [start, end)，ranks[i] 为第 i 个区间之前的命中数；适合大段连续的结果（会话范围、上下文扩展）
+    Ranges { runs: Vec<(usize, usize)>, ranks: Vec<usize> },
+    // 每行一位，ranks[b] 为第 b 个块之前的命中数；适合零散的结果
+    Bitmap { words: Vec<u64>, ranks: Vec<usize> },
+}
+
+pub struct ResultSet {
+    doc: DocHandle,
+    line_count: usize,
+    count: usize,
+    repr: Repr,
+    hits: Option<Hits>, // 带上下文的结果中哪些行是命中，其余为上下文；不带上下文时所有行都是命中
+}
+
+struct Hits {
+    words: Vec<u64>,
+    count: usize,
+}
+
+/// 返回给前端的结果集摘要
+#[derive(Debug, Serialize, Clone, Copy)]
+pub struct ResultInfo {
+    id: ResultId,
+    handle: DocHandle,
+    count: usize, // 结果中的行数（含上下文行）
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hit_count: Option<usize>, // 带上下文时其中命中行的数量
+}
+
+impl ResultSet {
+    // 由逐行命中标记构建（0-based 行号即下标）
+    pub fn from_mask(doc: DocHandle, mask: &[bool]) -> Self {
+        let words: Vec<u64> = mask.par_chunks(64).map(|chunk| {
+            chunk.iter().enumerate().fold(0u64, |w, (bit, &hit)| w | ((hit as u64) << bit))
+        }).collect();
+        Self::from_words(doc, mask.len(), words)
+    }
+
+    // 按区间数与位图大小择优存储
+    fn from_words(doc: DocHandle, line_count: usize, words: Vec<u64>) -> Self {
+        let count: usize = words.iter().map(|w| w.count_ones() as usize).sum();
+        let mut run_count = 0usize;
+        let mut carry = 0u64;
+        for &w in &words {
+            run_count += (w & !((w << 1) | carry)).count_ones() as usize;
+            carry = w >> 63;
+        }
+
+        let repr = if run_count * 2 < words.len() {
+            let mut runs: Vec<(usize, usize)> = Vec::with_capacity(run_count);
+            for line in BitIter::new(&words, 0) {
+                match runs.last_mut() {
+                    Some(last) if last.1 == line => last.1 += 1,
+                    _ => runs.push((line, line + 1)),
+                }
+            }
+            let ranks = prefix_counts(runs.iter().map(|(s, e)| e - s));
+            Repr::Ranges { runs, ranks }
+        } else {
+            let ranks = prefix_counts(words.chunks(RANK_BLOCK_WORDS)
+                .map(|block| block.iter().map(|w| w.count_ones() as usize).sum()));
+            Repr::Bitmap { words, ranks }
+        };
+        ResultSet { doc, line_count, count, repr, hits: None }
+    }
+
+    /// 标记结果中的命中行（mask 为逐行命中标记），其余行视为上下文
+    pub fn with_hits(mut self, mask: &[bool]) -> Self {
+        let words: Vec<u64> = mask.par_chunks(64).map(|chunk| {
+            chunk.iter().enumerate().fold(0u64, |w, (bit, &hit)| w | ((hit as u64) << bit))
+        }).collect();
+        let count = BitIter::new(&words, 0).filter(|&line| self.contains(line)).count();
+        self.hits = Some(Hits { words, count });
+        self
+    }
+
+    fn info(&self, id: ResultId) -> ResultInfo {
+        ResultInfo { id, handle: self.doc, count: self.count, hit_count: self.hits.as_ref().map(|h| h.count) }
+    }
+
+    pub fn has_context(&self) -> bool {
+        self.hits.is_some()
+    }
+
+    /// 结果中的行是否为命中（而非上下文）
+    pub fn is_hit(&self, line: usize) -> bool {
+        match &self.hits {
+            Some(hits) => hits.words.get(line / 64).is_some_and(|w| w & (1 << (line % 64)) != 0),
+            None => true,
+        }
+    }
+
+    /// 该行是否开始新的一组：前面还有结果行，但与上一行不相邻（导出时在组之间写入 --）
+    pub fn starts_group(&self, line: usize) -> bool {
+        line > 0 && !self.contains(line - 1) && self.rank(line).is_some_and(|r| r > 0)
+    }
+
+    pub fn contains(&self, line: usize) -> bool {
+        self.rank(line).is_some()
+    }
+
+    /// 逐行标记
+    pub fn to_mask(&self) -> Vec<bool> {
+        let mut mask = vec![false; self.line_count];
+        for line in self.iter() {
+            mask[line] = true;
+        }
+        mask
+    }
+
+    pub fn doc(&self) -> DocHandle {
+        self.doc
+    }
+
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    // 第 rank 个命中（0-based）所在的行
+    fn select(&self, rank: usize) -> Option<usize> {
+        if rank >= self.count {
+            return None;
+        }
+        match &self.repr {
+            Repr::Ranges { runs, ranks } => {
+                let i = ranks.partition_point(|&r| r <= rank) - 1;
+                Some(runs[i].0 + rank - ranks[i])
+            }
+            Repr::Bitmap { words, ranks } => {
+                let block = ranks.partition_point(|&r| r <= rank) - 1;
+                let mut remaining = rank - ranks[block];
+                for (wi, &w) in words.iter().enumerate().skip(block * RANK_BLOCK_WORDS) {
+                    let ones = w.count_ones() as usize;
+                    if remaining < ones {
+                        let mut w = w;
+                        for _ in 0..remaining {
+                            w &= w - 1;
+                        }
+                        return Some(wi * 64 + w.trailing_zeros() as usize);
+                    }
+                    remaining -= ones;
+                }
+                None
+            }
+        }
+    }
+
+    /// 行在结果中的名次（0-based）；该行不在结果中时返回 None
+    pub fn rank(&self, line: usize) -> Option<usize> {
+        match &self.repr {
+            Repr::Ranges { runs, ranks } => {
+                let i = runs.partition_point(|r| r.1 <= line);
+                let (start, _) = *runs.get(i)?;
+                (start <= line).then(|| ranks[i] + line - start)
+            }
+            Repr::Bitmap { words, ranks } => {
+                let (wi, bit) = (line / 64, line % 64);
+                let w = *words.get(wi)?;
+                if w & (1 << bit) == 0 {
+                    return None;
+                }
+                let block = wi / RANK_BLOCK_WORDS;
+                let before: usize = words[block * RANK_BLOCK_WORDS..wi].iter()
+                    .map(|w| w.count_ones() as usize).sum();
+                Some(ranks[block] + before + (w & ((1 << bit) - 1)).count_ones() as usize)
+            }
+        }
+    }
+
+    /// 从 offset 名次开始的一页行号（0-based）
+    pub fn page(&self, offset: usize, limit: usize) -> Vec<usize> {
+        match self.select(offset) {
+            Some(first) => self.iter_from(first).take(limit).collect(),
+            None => Vec::new(),
+        }
+    }
+
+    pub fn iter(&self) -> Box<dyn Iterator<Item = usize> + '_> {
+        self.iter_from(0)
+    }
+
+    // 从 line（含）开始按行号升序遍历命中行
+    fn iter_from(&self, line: usize) -> Box<dyn Iterator<Item = usize> + '_> {
+        match &self.repr {
+            Repr::Ranges { runs, .. } => {
+                let i = runs.partition_point(|r| r.1 <= line);
+                Box::new(runs[i..].iter().flat_map(move |&(s, e)| s.max(line)..e))
+            }
+            Repr::Bitmap { words, .. } => Box::new(BitIter::new(words, line)),
+        }
+    }
+
+    fn to_words(&self) -> Vec<u64> {
+        match &self.repr {
+            Repr::Bitmap { words, .. } => words.clone(),
+            Repr::Ranges { runs, .. } => {
+                let mut words = vec![0u64; self.line_count.div_ceil(64)];
+                for &(s, e) in runs {
+                    for line in s..e {
+                        words[line / 64] |= 1 << (line % 64);
+                    }
+                }
+                words
+            }
+        }
+    }
+
+    /// 只保留按行号升序的前 limit 个命中
+    pub fn truncate(self, limit: usize) -> ResultSet {
+        let Some(cut) = self.select(limit) else { return self };
+        let mut words = self.to_words();
+        words[cut / 64] &= (1u64 << (cut % 64)) - 1;
+        words[cut / 64 + 1..].fill(0);
+        Self::from_words(self.doc, self.line_count, words)
+    }
+
+    /// 两个结果集的交集；只允许同一文档的结果相交
+    pub fn intersect(&self, other: &ResultSet) -> Result<ResultSet, String> {
+        if self.doc != other.doc {
+            return Err(format!("Result sets belong to different documents ({} and {})", self.doc, other.doc));
+        }
+        let words: Vec<u64> = self.to_words().into_iter()
+            .zip(other.to_words())
+            .map(|(a, b)| a & b)
+            .collect();
+        Ok(Self::from_words(self.doc, self.line_count.min(other.line_count), words))
+    }
+}
+
+fn prefix_counts(counts: impl Iterator<Item = usize>) -> Vec<usize> {
+    let mut total = 0;
+    counts.map(|c| {
+        let before = total;
+        total += c;
+        before
+    }).collect()
+}
+
+// 遍历位图中置位的行号
+struct BitIter<'a> {
+    words: &'a [u64],
+    index: usize,
+    current: u64,
+}
+
+impl<'a> BitIter<'a> {
+    fn new(words: &'a [u64], from: usize) -> Self {
+        let index = from / 64;
+        let current = words.get(index).map_or(0, |w| w & (!0u64 << (from % 64)));
+        BitIter { words, index, current }
+    }
+}
+
+impl Iterator for BitIter<'_> {
+    type Item = usize;
+
+    fn next(&mut self) -> Option<usize> {
+        while self.current == 0 {
+            self.index += 1;
+            self.current = *self.words.get(self.index)?;
+        }
+        let bit = self.current.trailing_zeros() as usize;
+        self.current &= self.current - 1;
+        Some(self.index * 64 + bit)
+    }
+}
+
+#[derive(Default)]
+pub struct ResultRegistry {
+    next_id: AtomicU64,
+    sets: Mutex<HashMap<ResultId, Arc<ResultSet>>>,
+}
+
+impl ResultRegistry {
+    pub fn insert(&self, set: ResultSet) -> ResultInfo {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let info = set.info(id);
+        self.sets.lock().unwrap().insert(id, Arc::new(set));
+        info
+    }
+
+    pub fn get(&self, id: ResultId) -> Result<Arc<ResultSet>, String> {
+        self.sets.lock().unwrap().get(&id).cloned()
+            .ok_or_else(|| format!("Unknown result set {}", id))
+    }
+
+    pub fn info(&self, id: ResultId) -> Result<ResultInfo, String> {
+        Ok(self.get(id)?.info(id))
+    }
+
+    pub fn release(&self, id: ResultId) -> bool {
+        self.sets.lock().unwrap().remove(&id).is_some()
+    }
+
+    // 文档关闭时释放其全部结果集
+    pub fn release_document(&self, doc: DocHandle) {
+        self.sets.lock().unwrap().retain(|_, set| set.doc != doc);
+    }
+}
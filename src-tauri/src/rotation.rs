@@ -0,0 +1,160 @@
+// 轮转日志组：展开目录或通配符（app.log*），按轮转后缀或首条时间戳排序后拼接为一个虚拟日志
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+
+use crate::archive;
+use crate::decompress::{self, Compression};
+use crate::timestamp::{Extractor, TimestampOptions};
+use crate::{bytes_to_string_with_encoding, detect_encoding};
+
+// 读取文件开头时最多解码的字节数
+pub const HEAD_SAMPLE_LEN: u64 = 64 * 1024;
+
+// 未配置时间戳正则时使用的默认格式
+const DEFAULT_TIMESTAMP_PATTERN: &str = r"\d{4}-\d{2}-\d{2}[ T_]\d{2}:\d{2}:\d{2}(?:\.\d+)?";
+
+// 简单通配符匹配，只支持 * 和 ?
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let p: Vec<char> = pattern.chars().collect();
+    let n: Vec<char> = name.chars().collect();
+    let (mut pi, mut ni) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+
+    while ni < n.len() {
+        if pi < p.len() && (p[pi] == '?' || p[pi] == n[ni]) {
+            pi += 1;
+            ni += 1;
+        } else if pi < p.len() && p[pi] == '*' {
+            star = Some((pi, ni));
+            pi += 1;
+        } else if let Some((sp, sn)) = star {
+            // 回溯：让上一个 * 多吞一个字符
+            pi = sp + 1;
+            ni = sn + 1;
+            star = Some((sp, sn + 1));
+        } else {
+            return false;
+        }
+    }
+    p[pi..].iter().all(|&c| c == '*')
+}
+
+// 展开为文件列表：目录取其中的普通文件，文件名含 * ? 时按通配符匹配同目录文件
+// 隐藏文件和 zip/tar 压缩包会被跳过（压缩包请通过 open_archive_members 打开）
+pub fn expand_spec(spec: &str) -> Result<Vec<PathBuf>, String> {
+    let path = Path::new(spec);
+    if path.is_file() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let (dir, pattern) = if path.is_dir() {
+        (path.to_path_buf(), None)
+    } else {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
+        if !name.contains(['*', '?']) {
+            return Err(format!("Not a file, directory or glob: {}", spec));
+        }
+        let dir = path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new("."));
+        (dir.to_path_buf(), Some(name.to_string()))
+    };
+
+    let mut files = Vec::new();
+    for entry in fs::read_dir(&dir).map_err(|e| format!("{}: {}", dir.display(), e))? {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if name.starts_with('.') || pattern.as_ref().is_some_and(|p| !glob_match(p, &name)) {
+            continue;
+        }
+        let file_path = entry.path();
+        if !file_path.is_file() || archive::detect_archive(&file_path).ok().flatten().is_some() {
+            continue;
+        }
+        files.push(file_path);
+    }
+
+    if files.is_empty() {
+        return Err(format!("No log files matched: {}", spec));
+    }
+    Ok(files)
+}
+
+// 轮转后缀：app.log -> (app.log, 0)，app.log.1 -> (app.log, 1)，app.log.2.gz -> (app.log, 2)
+// 只认 1~4 位数字，app.log-20240101 这类日期后缀交给时间戳排序
+fn rotation_key(path: &Path) -> (String, u32) {
+    let mut name = path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+    for kind in [Compression::Gzip, Compression::Zstd, Compression::Xz, Compression::Bzip2] {
+        let stripped = kind.strip_extension(&name);
+        if stripped.len() != name.len() {
+            name = stripped;
+            break;
+        }
+    }
+
+    if let Some(pos) = name.rfind(['.', '-', '_']) {
+        let suffix = &name[pos + 1..];
+        if (1..=4).contains(&suffix.len()) && suffix.bytes().all(|b| b.is_ascii_digit()) {
+            if let Ok(n) = suffix.parse() {
+                return (name[..pos].to_string(), n);
+            }
+        }
+    }
+    (name, 0)
+}
+
+// 读取文件开头最多 max_len 字节（压缩文件先解压一小段）并解码为文本，用于识别格式与读取首条时间戳
+pub fn head_text(path: &Path, max_len: u64) -> Option<String> {
+    let file = fs::File::open(path).ok()?;
+    let reader: Box<dyn Read> = match decompress::detect_compression(path).ok()? {
+        Some(kind) => decompress::open_decoder(file, kind).ok()?,
+        None => Box::new(file),
+    };
+    let mut head = Vec::new();
+    reader.take(max_len).read_to_end(&mut head).ok()?;
+
+    let (encoding, start) = detect_encoding(&head);
+    Some(bytes_to_string_with_encoding(&head[start..], encoding))
+}
+
+// 文件的第一条能解析的时间戳；格式按该文件开头的样本识别
+fn first_timestamp(path: &Path, ts_re: &Regex, options: &TimestampOptions) -> Option<f64> {
+    let text = head_text(path, HEAD_SAMPLE_LEN)?;
+    let modified = fs::metadata(path).and_then(|m| m.modified()).ok();
+    let extractor = Extractor::detect_in(ts_re.clone(), &text, options, modified).ok()?;
+    text.lines().find_map(|line| extractor.extract(line)?.ok())
+}
+
+fn modified_secs(path: &Path) -> f64 {
+    fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs_f64())
+        .unwrap_or(0.0)
+}
+
+// 按时间先后排序（最旧的在前）
+// 同一基础名的轮转组按后缀倒序（.2 -> .1 -> 无后缀）；否则按首条时间戳，取不到时退回修改时间
+pub fn order_sources(mut paths: Vec<PathBuf>, timestamp_regex: &str, options: &TimestampOptions) -> Result<Vec<PathBuf>, String> {
+    let keys: Vec<(String, u32)> = paths.iter().map(|p| rotation_key(p)).collect();
+    let same_base = keys.iter().all(|(base, _)| *base == keys[0].0);
+    let mut numbers: Vec<u32> = keys.iter().map(|(_, n)| *n).collect();
+    numbers.sort_unstable();
+    numbers.dedup();
+
+    if same_base && numbers.len() == paths.len() {
+        paths.sort_by_key(|p| std::cmp::Reverse(rotation_key(p).1));
+        return Ok(paths);
+    }
+
+    let pattern = if timestamp_regex.is_empty() { DEFAULT_TIMESTAMP_PATTERN } else { timestamp_regex };
+    let ts_re = Regex::new(pattern).map_err(|e| format!("Invalid timestamp regex: {}", e))?;
+    let mut keyed: Vec<(f64, PathBuf)> = paths.into_iter().map(|p| {
+        let key = first_timestamp(&p, &ts_re, options).unwrap_or_else(|| modified_secs(&p));
+        (key, p)
+    }).collect();
+    keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+    Ok(keyed.into_iter().map(|(_, p)| p).collect())
+}
@@ -0,0 +1,161 @@
+// 标记范围：由成对的起止标记（如 START_STREAM … STOP_STREAM）界定的行范围，可多次出现
+// 计算结果与 line_ranges 格式相同（1-based 闭区间），过滤、搜索与其他按范围工作的分析都可以直接使用
+use serde::Deserialize;
+
+/// 未闭合（到文件末尾也没有遇到结束标记）的块如何处理
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum Unterminated {
+    #[default]
+    ToEnd, // 延伸到文件末尾
+    Drop,  // 丢弃
+}
+
+/// 由起止标记正则界定的范围
+#[derive(Debug, Deserialize, Clone, PartialEq)]
+pub struct MarkerScope {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub nested: bool, // 起始标记可以嵌套，回到最外层的结束标记才闭合；否则第一个结束标记即闭合，块内重复的起始标记忽略
+    #[serde(default = "include_markers_default")]
+    pub include_markers: bool, // 范围是否包含起止标记所在的行
+    #[serde(default)]
+    pub unterminated: Unterminated,
+}
+
+fn include_markers_default() -> bool {
+    true
+}
+
+impl MarkerScope {
+    /// 由起止标记的命中行（0-based，升序）计算范围；同一行同时命中两者时，在块内视为结束，块外视为起始
+    pub fn ranges(&self, starts: &[usize], ends: &[usize], line_count: usize) -> Vec<(usize, usize)> {
+        let (mut si, mut ei) = (0, 0);
+        let mut depth = 0usize;
+        let mut open = 0;
+        let mut blocks = Vec::new(); // (起始行, 结束行, 是否由结束标记闭合)
+        loop {
+            let next_start = starts.get(si).copied();
+            let next_end = ends.get(ei).copied();
+            let line = match (next_start, next_end) {
+                (None, None) => break,
+                (Some(s), Some(e)) => s.min(e),
+                (Some(s), None) => s,
+                (None, Some(e)) => e,
+            };
+            let is_start = next_start == Some(line);
+            let is_end = next_end == Some(line);
+            si += is_start as usize;
+            ei += is_end as usize;
+
+            if is_end && depth > 0 {
+                depth = if self.nested { depth - 1 } else { 0 };
+                if depth == 0 {
+                    blocks.push((open, line, true));
+                }
+            } else if is_start && (depth == 0 || self.nested) {
+                if depth == 0 {
+                    open = line;
+                }
+                depth += 1;
+            }
+        }
+        if depth > 0 && self.unterminated == Unterminated::ToEnd && line_count > 0 {
+            blocks.push((open, line_count - 1, false));
+        }
+
+        // 不含标记时去掉起始标记行；末行只有是结束标记时才去掉，延伸到文件末尾的块保留最后一行
+        blocks.into_iter().filter_map(|(start, end, closed)| {
+            let (start, end) = match (self.include_markers, closed) {
+                (true, _) => (start, end),
+                (false, true) => (start + 1, end.checked_sub(1)?),
+                (false, false) => (start + 1, end),
+            };
+            (start <= end).then_some((start + 1, end + 1))
+        }).collect()
+    }
+}
+
+/// 两组 1-based 闭区间的交集（每组内的区间互不重叠，如会话范围与标记范围）；输入无需有序，结果按起始行排序
+pub fn intersect(a: &[(usize, usize)], b: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let (mut a, mut b) = (a.to_vec(), b.to_vec());
+    a.sort_unstable();
+    b.sort_unstable();
+    let mut out = Vec::new();
+    for &(s1, e1) in &a {
+        let from = b.partition_point(|&(_, e2)| e2 < s1);
+        for &(s2, e2) in b[from..].iter().take_while(|&&(s2, _)| s2 <= e1) {
+            let (s, e) = (s1.max(s2), e1.min(e2));
+            if s <= e {
+                out.push((s, e));
+            }
+        }
+    }
+    out
+}
+
+/// 排序并合并重叠或相邻的区间，结果可直接用 contains 二分查找
+pub fn normalize(ranges: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    let mut sorted = ranges.to_vec();
+    sorted.sort_unstable();
+    let mut out: Vec<(usize, usize)> = Vec::with_capacity(sorted.len());
+    for (start, end) in sorted {
+        match out.last_mut() {
+            Some(last) if start <= last.1.saturating_add(1) => last.1 = last.1.max(end),
+            _ => out.push((start, end)),
+        }
+    }
+    out
+}
+
+/// 行号（1-based）是否落在 normalize 过的区间内
+pub fn contains(ranges: &[(usize, usize)], line: usize) -> bool {
+    let i = ranges.partition_point(|&(_, end)| end < line);
+    ranges.get(i).is_some_and(|&(start, _)| start <= line)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scope(nested: bool, include_markers: bool, unterminated: Unterminated) -> MarkerScope {
+        MarkerScope { start: "S".into(), end: "E".into(), nested, include_markers, unterminated }
+    }
+
+    #[test]
+    fn nested_and_flat_blocks() {
+        // 起始标记在 0、2、8 行，结束标记在 4、6 行
+        let (starts, ends) = ([0, 2, 8], [4, 6]);
+        assert_eq!(scope(false, true, Unterminated::ToEnd).ranges(&starts, &ends, 10), [(1, 5), (9, 10)]);
+        assert_eq!(scope(true, true, Unterminated::ToEnd).ranges(&starts, &ends, 10), [(1, 7), (9, 10)]);
+        assert_eq!(scope(false, false, Unterminated::Drop).ranges(&starts, &ends, 10), [(2, 4)]);
+        assert_eq!(scope(true, false, Unterminated::Drop).ranges(&starts, &ends, 10), [(2, 6)]);
+        // 同一行同时命中起止标记：块内为结束，块外为起始
+        assert_eq!(scope(false, true, Unterminated::Drop).ranges(&[1, 3, 5], &[3, 5], 10), [(2, 4)]);
+        // 不含标记时，没有内部行的块消失
+        assert!(scope(false, false, Unterminated::Drop).ranges(&[3], &[4], 10).is_empty());
+    }
+
+    #[test]
+    fn unterminated_blocks() {
+        assert_eq!(scope(false, true, Unterminated::ToEnd).ranges(&[2], &[], 10), [(3, 10)]);
+        assert!(scope(false, true, Unterminated::Drop).ranges(&[2], &[], 10).is_empty());
+        // 不含标记时延伸到文件末尾的块保留最后一行
+        assert_eq!(scope(false, false, Unterminated::ToEnd).ranges(&[2], &[], 10), [(4, 10)]);
+        assert_eq!(scope(true, false, Unterminated::ToEnd).ranges(&[2, 4], &[6], 10), [(4, 10)]);
+        // 起始标记在最后一行：含标记时只有这一行，不含时为空
+        assert_eq!(scope(false, true, Unterminated::ToEnd).ranges(&[9], &[], 10), [(10, 10)]);
+        assert!(scope(false, false, Unterminated::ToEnd).ranges(&[9], &[], 10).is_empty());
+        assert!(scope(false, true, Unterminated::ToEnd).ranges(&[], &[3], 10).is_empty());
+    }
+
+    #[test]
+    fn range_sets() {
+        assert_eq!(intersect(&[(20, 30), (1, 10)], &[(5, 22), (25, 26), (40, 50)]), [(5, 10), (20, 22), (25, 26)]);
+        let merged = normalize(&[(8, 9), (1, 3), (4, 5), (2, 2)]);
+        assert_eq!(merged, [(1, 5), (8, 9)]);
+        assert!(contains(&merged, 5) && contains(&merged, 8));
+        assert!(!contains(&merged, 6) && !contains(&merged, 10) && !contains(&merged, 0));
+    }
+}
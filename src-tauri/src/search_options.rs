@@ -0,0 +1,95 @@
+// 搜索选项：搜索、定位首个匹配与过滤引擎（追踪关键字）共用同一套匹配语义
+use serde::Deserialize;
+
+use crate::query::Node;
+use crate::validate::{self, PatternError, SyntaxError};
+
+/// 匹配选项；缺省时为不区分大小写的子串匹配，不取反，不限制数量
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct SearchOptions {
+    pub case_sensitive: bool,
+    pub whole_word: bool,   // 命中两侧不能紧挨字母、数字或下划线
+    pub invert_match: bool, // 选出不匹配的行
+    pub max_matches_per_session: Option<usize>, // 每个会话最多保留的命中行数
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+impl SearchOptions {
+    /// 把一组模式（任意一个命中即可）编译成匹配树；rule 为错误信息中的规则名，多个模式时附上序号
+    pub fn compile<S: AsRef<str>>(&self, patterns: &[S], is_regex: bool, rule: &str) -> Result<Node, PatternError> {
+        let case_insensitive = !self.case_sensitive;
+        let rule_of = |i: usize| match patterns.len() {
+            1 => rule.to_string(),
+            _ => format!("{} #{}", rule, i + 1),
+        };
+        let listed = || patterns.iter().map(|p| p.as_ref()).collect::<Vec<_>>().join(", ");
+        let node = if is_regex {
+            let nodes = patterns.iter().enumerate().map(|(i, p)| {
+                let p = p.as_ref();
+                let re = match self.whole_word {
+                    // 包一层非捕获组，再按单词边界编译；出错位置仍按用户输入计算
+                    true => validate::regex(p, case_insensitive)
+                        .and_then(|_| validate::regex(&format!(r"\b(?:{})\b", p), case_insensitive)),
+                    false => validate::regex(p, case_insensitive),
+                };
+                re.map(Node::Regex).map_err(|e| e.in_rule(rule_of(i), p))
+            }).collect::<Result<Vec<_>, _>>()?;
+            one_of(nodes)
+        } else if self.whole_word {
+            // 只在模式的首尾是单词字符时加边界，"foo." 这类模式仍要求前侧是单词边界
+            let alternatives: Vec<String> = patterns.iter().map(|p| {
+                let p = p.as_ref();
+                let start = if p.starts_with(is_word_char) { r"\b" } else { "" };
+                let end = if p.ends_with(is_word_char) { r"\b" } else { "" };
+                format!("{}{}{}", start, regex::escape(p), end)
+            }).collect();
+            // 转义后的字面量只可能超出正则大小限制，没有可定位的语法错误
+            let re = validate::regex(&alternatives.join("|"), case_insensitive)
+                .map_err(|e| SyntaxError::new(e.message).in_rule(rule, &listed()))?;
+            Node::Regex(re)
+        } else {
+            Node::literals(patterns, case_insensitive)
+                .map_err(|e| SyntaxError::new(e).in_rule(rule, &listed()))?
+        };
+        Ok(match self.invert_match {
+            true => Node::Not(Box::new(node)),
+            false => node,
+        })
+    }
+
+    /// 按会话截断命中：sessions 为 1-based 闭区间（未给出时整个文件算一个会话），每个会话只保留前 max_matches_per_session 个命中
+    pub fn limit_per_session(&self, mask: &mut [bool], sessions: Option<&[(usize, usize)]>) {
+        let Some(max) = self.max_matches_per_session else { return };
+        let whole = [(1, mask.len())];
+        for &(start, end) in sessions.unwrap_or(&whole) {
+            let range = start.max(1) - 1..end.min(mask.len());
+            if range.is_empty() {
+                continue;
+            }
+            let mut kept = 0;
+            for hit in mask[range].iter_mut().filter(|m| **m) {
+                if kept < max {
+                    kept += 1;
+                } else {
+                    *hit = false;
+                }
+            }
+        }
+    }
+
+    /// 对最早的一个命中应用每会话上限：与 limit_per_session 的结果一致——上限不小于 1 时每个会话的第一个命中总会保留，上限为 0 时不保留
+    pub fn limit_first(&self, first: Option<usize>) -> Option<usize> {
+        first.filter(|_| self.max_matches_per_session != Some(0))
+    }
+}
+
+fn one_of(mut nodes: Vec<Node>) -> Node {
+    match nodes.len() {
+        1 => nodes.pop().unwrap(),
+        _ => Node::Or(nodes),
+    }
+}
@@ -0,0 +1,198 @@
+// 会话切分：分块并行检测启动标识与时间间隙，再按块顺序衔接跨块的时间状态
+use rayon::prelude::*;
+
+use crate::line_index::LineTimestamps;
+
+// 并行检测时每块的行数
+const CHUNK_LINES: usize = 1 << 20;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SplitReason {
+    Boot,         // 启动标识（或自定义分割器）命中
+    TimeGap(f64), // 与上一条时间戳相差的秒数
+}
+
+// 单块的局部结果：块内首条时间戳是否构成间隙要等汇总时与前面的块比较
+struct ChunkSplits {
+    splits: Vec<(usize, SplitReason)>,
+    first: Option<(usize, f64)>, // 块内第一条参与间隙判断的时间戳
+    last: Option<f64>,           // 块内最后一条参与间隙判断的时间戳
+}
+
+/// 切分点（0-based 行号，升序），每个切分点开始一个新会话
+/// 启动标识命中行（首行除外）直接切分，且不作为时间基准；
+/// 其余带时间戳的行与上一条时间戳相差超过 threshold 秒时切分
+pub fn split_points(
+    line_count: usize,
+    boot_hits: &[usize],
+    timestamps: Option<&LineTimestamps>,
+    threshold: f64,
+) -> Vec<(usize, SplitReason)> {
+    split_in_chunks(line_count, boot_hits, timestamps, threshold, CHUNK_LINES)
+}
+
+fn split_in_chunks(
+    line_count: usize,
+    boot_hits: &[usize],
+    timestamps: Option<&LineTimestamps>,
+    threshold: f64,
+    chunk_lines: usize,
+) -> Vec<(usize, SplitReason)> {
+    let Some(timestamps) = timestamps.filter(|_| threshold > 0.0) else {
+        return boot_hits.iter()
+            .filter(|&&idx| idx > 0 && idx < line_count)
+            .map(|&idx| (idx, SplitReason::Boot))
+            .collect();
+    };
+
+    let chunks: Vec<ChunkSplits> = (0..line_count.div_ceil(chunk_lines)).into_par_iter().map(|c| {
+        let start = c * chunk_lines;
+        let end = (start + chunk_lines).min(line_count);
+        let mut hits = boot_hits[boot_hits.partition_point(|&idx| idx < start)..].iter().copied().peekable();
+        let mut chunk = ChunkSplits { splits: Vec::new(), first: None, last: None };
+
+        for idx in start..end {
+            if hits.next_if_eq(&idx).is_some() && idx > 0 {
+                chunk.splits.push((idx, SplitReason::Boot));
+                continue;
+            }
+            let Some(current) = timestamps.get(idx) else { continue };
+            match chunk.last {
+                None => chunk.first = Some((idx, current)),
+                Some(last) if (current - last).abs() > threshold => {
+                    chunk.splits.push((idx, SplitReason::TimeGap(current - last)));
+                }
+                Some(_) => {}
+            }
+            chunk.last = Some(current);
+        }
+        chunk
+    }).collect();
+
+    // 按块顺序衔接：块内首条时间戳与之前各块的最后一条时间戳比较
+    let mut splits = Vec::new();
+    let mut carry: Option<f64> = None;
+    for chunk in chunks {
+        let gap = match (carry, chunk.first) {
+            (Some(last), Some((idx, current))) if (current - last).abs() > threshold => {
+                Some((idx, SplitReason::TimeGap(current - last)))
+            }
+            _ => None,
+        };
+        let at = gap.map_or(chunk.splits.len(), |(idx, _)| chunk.splits.partition_point(|&(i, _)| i < idx));
+        splits.extend_from_slice(&chunk.splits[..at]);
+        splits.extend(gap);
+        splits.extend_from_slice(&chunk.splits[at..]);
+        carry = chunk.last.or(carry);
+    }
+    splits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 串行参考实现：逐行维护上一条时间戳
+    fn serial(line_count: usize, boot_hits: &[usize], timestamps: Option<&LineTimestamps>, threshold: f64) -> Vec<(usize, SplitReason)> {
+        let mut splits = Vec::new();
+        let mut last: Option<f64> = None;
+        for idx in 0..line_count {
+            if idx > 0 && boot_hits.contains(&idx) {
+                splits.push((idx, SplitReason::Boot));
+                continue;
+            }
+            let Some(current) = timestamps.filter(|_| threshold > 0.0).and_then(|ts| ts.get(idx)) else { continue };
+            if let Some(last) = last.filter(|last| (current - last).abs() > threshold) {
+                splits.push((idx, SplitReason::TimeGap(current - last)));
+            }
+            last = Some(current);
+        }
+        splits
+    }
+
+    fn check(line_count: usize, boot_hits: &[usize], values: &[f64], threshold: f64) {
+        let timestamps = LineTimestamps::from(values.to_vec());
+        let expected = serial(line_count, boot_hits, Some(&timestamps), threshold);
+        for chunk_lines in [1, 2, 3, 4, 7, 64, CHUNK_LINES] {
+            assert_eq!(
+                split_in_chunks(line_count, boot_hits, Some(&timestamps), threshold, chunk_lines),
+                expected,
+                "chunk_lines = {}", chunk_lines
+            );
+        }
+    }
+
+    #[test]
+    fn gaps_across_chunk_boundaries() {
+        // 间隙正好落在块首，以及跨过若干整块没有时间戳的行
+        let nan = f64::NAN;
+        let values = [0.0, 1.0, 20.0, nan, nan, nan, nan, 50.0, 51.0, nan, 100.0, 100.5];
+        check(values.len(), &[], &values, 5.0);
+    }
+
+    #[test]
+    fn boot_hit_at_first_line_is_not_a_split() {
+        let values = [0.0, 1.0, 2.0, 3.0];
+        check(4, &[0, 2], &values, 5.0);
+        assert_eq!(split_points(4, &[0, 2], None, 0.0), [(2, SplitReason::Boot)]);
+    }
+
+    #[test]
+    fn boot_lines_do_not_update_carried_timestamp() {
+        // 启动行的时间戳 100 不作为基准：第 3 行与第 1 行比较，不构成间隙
+        let values = [0.0, 1.0, 100.0, 2.0, 200.0];
+        check(5, &[2], &values, 5.0);
+        let timestamps = LineTimestamps::from(values.to_vec());
+        assert_eq!(
+            split_points(5, &[2], Some(&timestamps), 5.0),
+            [(2, SplitReason::Boot), (4, SplitReason::TimeGap(198.0))]
+        );
+    }
+
+    #[test]
+    fn missing_and_nan_timestamps_are_skipped() {
+        let nan = f64::NAN;
+        // 时间戳数组比行数短时，其余的行视为没有时间戳
+        check(8, &[5], &[nan, 10.0, nan, nan, 30.0], 5.0);
+        check(6, &[], &[nan, nan, nan], 5.0);
+        check(0, &[], &[], 5.0);
+    }
+
+    #[test]
+    fn gap_equal_to_threshold_does_not_split() {
+        let values = [0.0, 5.0, 10.0, 15.001, 10.0];
+        check(5, &[], &values, 5.0);
+        let timestamps = LineTimestamps::from(values.to_vec());
+        let splits = split_points(5, &[], Some(&timestamps), 5.0);
+        assert_eq!(splits.iter().map(|&(idx, _)| idx).collect::<Vec<_>>(), [3, 4]);
+    }
+
+    #[test]
+    fn matches_serial_on_random_input() {
+        let mut seed = 0x9e37_79b9_7f4a_7c15u64;
+        let mut rnd = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            seed
+        };
+        for _ in 0..50 {
+            let line_count = (rnd() % 300) as usize;
+            let mut time = 0.0;
+            let values: Vec<f64> = (0..line_count).map(|_| {
+                time += match rnd() % 20 {
+                    0 => 10.0,
+                    1 => -10.0,
+                    _ => 0.5,
+                };
+                if rnd() % 3 == 0 { f64::NAN } else { time }
+            }).collect();
+            let mut boot_hits: Vec<usize> = (0..line_count / 20).map(|_| (rnd() % line_count as u64) as usize).collect();
+            boot_hits.sort_unstable();
+            boot_hits.dedup();
+            for threshold in [0.0, 5.0, 10.0] {
+                check(line_count, &boot_hits, &values, threshold);
+            }
+        }
+    }
+}
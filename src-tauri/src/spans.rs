@@ -0,0 +1,86 @@
+// 命中区间：用与搜索、过滤相同的匹配规则在返回的行内容上标出每条规则的命中位置，前端据此高亮，不再用 JS 重新匹配
+use serde::{Deserialize, Serialize};
+
+use crate::query::Node;
+use crate::search_options::SearchOptions;
+
+/// 规则来源，决定模式的解释方式
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleKind {
+    Search,     // 搜索查询：is_regex 为真时按正则，否则按字面量，大小写与整词按 options
+    Keyword,    // 过滤关键词：字面量，大小写与整词按 options
+    Refinement, // 精简条件：沿用过滤时的前缀语法（/ 正则、= 区分大小写、? 模糊、& 查询语言），排除条件与 NOT 之下的词项不产生命中
+    Highlight,  // 高亮规则：同搜索查询
+}
+
+/// 需要标出命中位置的一条规则
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpanRule {
+    kind: RuleKind,
+    pattern: String,
+    #[serde(default)]
+    is_regex: bool,
+    #[serde(default)]
+    options: SearchOptions, // 与搜索、过滤时相同的匹配选项；反选时不产生命中
+}
+
+/// 一处命中；byte_* 为 UTF-8 字节偏移，char_* 为字符偏移，均为左闭右开
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub struct MatchSpan {
+    rule: usize, // 产生该命中的规则在请求中的下标
+    byte_start: usize,
+    byte_end: usize,
+    char_start: usize,
+    char_end: usize,
+}
+
+/// 编译好的一组规则；空模式与无法编译的规则不产生命中
+pub struct SpanMatcher {
+    nodes: Vec<(usize, Node)>,
+}
+
+impl SpanMatcher {
+    pub fn new(rules: &[SpanRule]) -> Self {
+        let nodes = rules.iter().enumerate().filter_map(|(i, rule)| {
+            let pattern = rule.pattern.trim_matches(|c: char| c == '\r' || c == '\n');
+            if pattern.trim().is_empty() {
+                return None;
+            }
+            let node = match rule.kind {
+                RuleKind::Search | RuleKind::Highlight => rule.options.compile(&[pattern], rule.is_regex, "span rule").ok(),
+                RuleKind::Keyword => rule.options.compile(&[pattern], false, "span rule").ok(),
+                RuleKind::Refinement => crate::refinement_node(pattern.trim()).ok(),
+            };
+            node.map(|n| (i, n))
+        }).collect();
+        SpanMatcher { nodes }
+    }
+
+    /// 单个已编译正则的命中（如正则预览），规则下标为 0
+    pub fn regex(re: regex::Regex) -> Self {
+        SpanMatcher { nodes: vec![(0, Node::Regex(re))] }
+    }
+
+    /// 行内容上的全部命中，按起始位置排序；不同规则的命中可能重叠
+    pub fn spans(&self, text: &str) -> Vec<MatchSpan> {
+        let mut bytes: Vec<(usize, usize, usize)> = self.nodes.iter()
+            .flat_map(|(rule, node)| node.find_iter(text).into_iter().map(move |(s, e)| (s, e, *rule)))
+            .collect();
+        bytes.sort_unstable();
+
+        // 起始位置有序，字符偏移沿文本递增计算
+        let (mut cursor, mut chars) = (0, 0);
+        bytes.into_iter().map(|(byte_start, byte_end, rule)| {
+            chars += text[cursor..byte_start].chars().count();
+            cursor = byte_start;
+            MatchSpan {
+                rule,
+                byte_start,
+                byte_end,
+                char_start: chars,
+                char_end: chars + text[byte_start..byte_end].chars().count(),
+            }
+        }).collect()
+    }
+}
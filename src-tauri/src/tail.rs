@@ -0,0 +1,201 @@
+// 跟随模式 (tail -f)：轮询仍在写入的日志文件，增量扩展索引
+use std::fs;
+use std::io::Read;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use memmap2::Mmap;
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::index_cache::FileKey;
+use crate::line_index::LevelRuns;
+use crate::{index_sources, AppState, DocHandle, LogIndex, SourceFile};
+
+// 用于判断文件是否被替换（轮转）的头部字节数
+const HEAD_FINGERPRINT_LEN: usize = 256;
+
+/// 跟随失败时推送给前端的事件 (`tail-error`)
+#[derive(Debug, Serialize, Clone)]
+pub struct TailError {
+    handle: DocHandle,
+    message: String,
+}
+
+/// 推送给前端的增量事件 (`log-appended`)
+#[derive(Debug, Serialize, Clone)]
+pub struct LogAppended {
+    handle: DocHandle,
+    start_line: usize, // 1-based，首个发生变化的行（末行被续写时也会包含在内）
+    end_line: usize,   // 1-based，包含
+    line_count: usize,
+    levels: LevelRuns, // start_line..=end_line 的级别（游程编码）
+    reset: bool, // 文件被截断或替换，索引已整体重建
+}
+
+enum FileChange {
+    Unchanged,
+    Appended,
+    Reset,
+}
+
+// 对比 mmap 长度与头部字节，判断文件是追加、截断还是被替换
+// 轮转日志组只跟随最新的（最后一个）分段
+fn detect_change(index: &LogIndex) -> Result<FileChange, String> {
+    let last = index.segments.last().ok_or("Index has no source file")?;
+    let meta = fs::metadata(&last.path).map_err(|e| e.to_string())?;
+    let new_len = meta.len() as usize;
+    let old_len = last.mmap.len();
+
+    // 变短说明被截断；原本没有任何行则无法复用 BOM 检测结果，同样整体重建
+    let had_lines = index.line_count() > last.first_line;
+    if new_len < old_len || (!had_lines && new_len != old_len) {
+        return Ok(FileChange::Reset);
+    }
+
+    let head_len = old_len.min(HEAD_FINGERPRINT_LEN);
+    let mut head = vec![0u8; head_len];
+    let mut file = fs::File::open(&last.path).map_err(|e| e.to_string())?;
+    file.read_exact(&mut head).map_err(|e| e.to_string())?;
+    if head[..] != last.mmap[..head_len] {
+        return Ok(FileChange::Reset);
+    }
+
+    if new_len == old_len {
+        Ok(FileChange::Unchanged)
+    } else {
+        Ok(FileChange::Appended)
+    }
+}
+
+// 在旧索引基础上扩展：重新映射最后一个分段，从原末行起一次扫描新增内容
+fn extend_index(old: Arc<LogIndex>, mmap: Mmap) -> (LogIndex, usize) {
+    // 注册表仍持有旧索引时复制一份（映射本身是共享的），其他命令照常读取旧索引
+    let mut index = Arc::try_unwrap(old).unwrap_or_else(|shared| LogIndex {
+        segments: shared.segments.clone(),
+        offsets: shared.offsets.clone(),
+        levels: shared.levels.clone(),
+        level_re: shared.level_re.clone(),
+        encoding_override: shared.encoding_override,
+        timestamp_options: shared.timestamp_options.clone(),
+        line_cache: shared.line_cache.clone(),
+        trigrams: Mutex::new(shared.trigrams.lock().unwrap().clone()),
+    });
+    if let Some(last) = index.segments.last_mut() {
+        if last.cache_key.is_some() {
+            last.cache_key = FileKey::compute(&last.path, &mmap);
+        }
+        last.mmap = Arc::new(mmap);
+    }
+    // 新增的行与原末行一起分行并提取级别、切分命中与时间戳
+    let keep = index.extend_lines();
+
+    (index, keep)
+}
+
+/// 检查一次文件变化并更新注册表中的索引；没有变化时返回 None
+pub(crate) fn poll_once(state: &AppState, handle: DocHandle) -> Result<Option<LogAppended>, String> {
+    let current = state.get_index(handle)?;
+
+    match detect_change(&current)? {
+        FileChange::Unchanged => Ok(None),
+        FileChange::Reset => {
+            // 截断或轮转：丢弃旧映射，避免读取已失效的内存
+            let sources = current.segments.iter().map(|seg| SourceFile {
+                path: seg.path.clone(),
+                name: seg.name.clone(),
+                temp_file: seg.temp_file.clone(),
+            }).collect();
+            // 沿用已缓存的切分正则与时间戳提取器，重建后重新切分会话仍可直接命中
+            let (splitters, timestamp) = current.line_cache.patterns();
+            let splitters: Vec<&regex::Regex> = splitters.iter().take(64).collect();
+            let mut index = index_sources(sources, current.level_re.clone(), &splitters, timestamp.as_ref(), current.encoding_override, None)?;
+            index.timestamp_options = current.timestamp_options.clone();
+            let line_count = index.line_count();
+            let levels = index.levels.runs(0..line_count);
+            // 重建期间文档可能已被关闭或重新解析，此时不能覆盖
+            let mut docs = state.documents.lock().unwrap();
+            let Some(slot) = docs.get_mut(&handle) else {
+                return Err(format!("No file opened for handle {}", handle));
+            };
+            if !Arc::ptr_eq(slot, &current) {
+                return Ok(None);
+            }
+            *slot = Arc::new(index);
+            drop(docs);
+            drop(current);
+            // 旧索引上的结果集行号已失效
+            state.results.release_document(handle);
+            Ok(Some(LogAppended {
+                handle,
+                start_line: 1,
+                end_line: line_count,
+                line_count,
+                levels,
+                reset: true,
+            }))
+        }
+        FileChange::Appended => {
+            let last = current.segments.last().ok_or("Index has no source file")?;
+            let file = fs::File::open(&last.path).map_err(|e| e.to_string())?;
+            let mmap = unsafe { Mmap::map(&file).map_err(|e| e.to_string())? };
+
+            // 在锁外扫描新增内容，只在替换时短暂持有注册表锁
+            let old_ptr = Arc::as_ptr(&current);
+            let (index, first_changed) = extend_index(current, mmap);
+            let line_count = index.line_count();
+            let levels = index.levels.runs(first_changed..line_count);
+            let mut docs = state.documents.lock().unwrap();
+            let Some(slot) = docs.get_mut(&handle) else {
+                return Err(format!("No file opened for handle {}", handle));
+            };
+            // 扫描期间索引已被替换（例如重新解析），丢弃本次结果，下一轮重新比较
+            if Arc::as_ptr(slot) != old_ptr {
+                return Ok(None);
+            }
+            *slot = Arc::new(index);
+            drop(docs);
+            Ok(Some(LogAppended {
+                handle,
+                start_line: first_changed + 1,
+                end_line: line_count,
+                line_count,
+                levels,
+                reset: false,
+            }))
+        }
+    }
+}
+
+/// 启动后台轮询线程，直到 stop 被置位或文档被关闭
+pub(crate) fn spawn_follower(app: AppHandle, handle: DocHandle, interval: Duration, stop: Arc<AtomicBool>) {
+    std::thread::spawn(move || {
+        while !stop.load(Ordering::Relaxed) {
+            std::thread::sleep(interval);
+            if stop.load(Ordering::Relaxed) {
+                break;
+            }
+
+            let state = app.state::<AppState>();
+            match poll_once(&state, handle) {
+                Ok(Some(event)) => {
+                    let _ = app.emit("log-appended", event);
+                }
+                Ok(None) => {}
+                Err(e) => {
+                    // 文档已关闭则退出，文件暂时不可访问（轮转中）则继续等待
+                    if state.get_index(handle).is_err() {
+                        break;
+                    }
+                    let _ = app.emit("tail-error", TailError { handle, message: e });
+                }
+            }
+        }
+        let state = app.state::<AppState>();
+        let mut tails = state.tails.lock().unwrap();
+        if tails.get(&handle).is_some_and(|s| Arc::ptr_eq(s, &stop)) {
+            tails.remove(&handle);
+        }
+    });
+}
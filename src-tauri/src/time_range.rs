@@ -0,0 +1,51 @@
+// 时间范围：按每行时间戳（没有时间戳的行沿用上一行）筛选行，以及按时间定位行
+// 时间可以写完整的日期时间、纯数字（如内核秒数），或只写时分秒，此时补上参考时间所在的日期
+use std::ops::Range;
+
+use serde::Deserialize;
+
+use crate::timestamp::TimestampParser;
+
+/// 过滤与搜索的时间范围（闭区间）；from、to 可以只给一个
+#[derive(Debug, Deserialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TimeRange {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub timestamp_regex: Option<String>, // 缺省沿用解析文档时的时间戳正则
+}
+
+/// 解析后的时间范围（秒，闭区间）
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub from: f64,
+    pub to: f64,
+}
+
+impl Bounds {
+    /// 时间未知（NaN）的行不在任何范围内
+    pub fn contains(&self, time: f64) -> bool {
+        time >= self.from && time <= self.to
+    }
+}
+
+impl TimeRange {
+    /// 按文档的时间戳设置解析；reference 为文档中首个时间戳，只写时分秒的结束时间早于开始时间时视为跨过午夜
+    pub fn bounds(&self, parser: &TimestampParser, reference: Option<f64>) -> Result<Bounds, String> {
+        let given = |s: &Option<String>| s.as_deref().map(str::trim).filter(|s| !s.is_empty()).map(str::to_string);
+        let from = given(&self.from).map(|s| parser.parse_input(&s, reference)).transpose()?;
+        let to = given(&self.to).map(|s| parser.parse_input(&s, from.or(reference))).transpose()?;
+        Ok(Bounds {
+            from: from.unwrap_or(f64::NEG_INFINITY),
+            to: to.unwrap_or(f64::INFINITY),
+        })
+    }
+}
+
+/// range（0-based 半开区间）内首个时间不早于 target 的行，按二分查找
+/// times 为沿用上一行后的逐行时间；要求范围内时间单调不减（按会话分别查找即可），开头时间未知的行跳过
+pub fn first_at_or_after(times: &[f64], range: Range<usize>, target: f64) -> Option<usize> {
+    let slice = &times[range.clone()];
+    let pos = slice.partition_point(|&t| t.is_nan() || t < target);
+    (pos < slice.len()).then_some(range.start + pos)
+}
@@ -0,0 +1,459 @@
+// 时间戳引擎：索引、会话切分、时间范围与各项分析共用同一套解析
+// 先从样本识别格式（带日期、带时区偏移、不带年份的 syslog/Android、只有时分秒、epoch 秒/毫秒/微秒/纳秒），之后逐行按该格式解析
+// 不带时区的时间按文档设置的时区解释，不带年份的时间按文件修改时间推断年份；无法解析时返回错误，不再当作 0
+use std::borrow::Cow;
+use std::fmt;
+use std::time::SystemTime;
+
+use chrono::{DateTime, Datelike, FixedOffset, Local, NaiveDateTime, NaiveTime, Offset, TimeZone, Timelike, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// 识别格式时最多使用的样本数
+pub const MAX_SAMPLES: usize = 200;
+
+// 带日期的格式；%.f 可以省略小数部分
+const DATE_TIME: &[&str] = &[
+    "%Y-%m-%d %H:%M:%S%.f",
+    "%Y-%m-%dT%H:%M:%S%.f",
+    "%Y-%m-%d_%H:%M:%S%.f",
+    "%Y/%m/%d %H:%M:%S%.f",
+    "%Y%m%d %H:%M:%S%.f",
+    "%d/%b/%Y:%H:%M:%S%.f",
+    "%b %d %Y %H:%M:%S%.f",
+    "%a %b %d %H:%M:%S%.f %Y",
+];
+
+// 带时区偏移的格式（ISO-8601 的 Z 先换成 +00:00）
+const ZONED: &[&str] = &[
+    "%Y-%m-%dT%H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f%z",
+    "%Y-%m-%d %H:%M:%S%.f %z",
+    "%d/%b/%Y:%H:%M:%S%.f %z",
+];
+
+// 不带年份的格式：syslog、Android logcat
+const NO_YEAR: &[&str] = &[
+    "%b %d %H:%M:%S%.f",
+    "%m-%d %H:%M:%S%.f",
+    "%m/%d %H:%M:%S%.f",
+];
+
+const TIME_OF_DAY: &[&str] = &["%H:%M:%S%.f"];
+
+/// 文档的时间戳设置；缺省时自动识别格式，按 UTC 解释，年份按文件修改时间推断
+#[derive(Debug, Deserialize, Serialize, Clone, Default, PartialEq)]
+#[serde(default)]
+pub struct TimestampOptions {
+    pub format: Option<String>,   // 显式格式：epoch_s / epoch_ms / epoch_us / epoch_ns，或 chrono 格式串
+    pub timezone: Option<String>, // 不带时区偏移的时间所在时区："UTC"、"local" 或 "+08:00" 这样的偏移
+    pub year: Option<i32>,        // 不带年份的格式补的年份
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EpochUnit {
+    Seconds,
+    Millis,
+    Micros,
+    Nanos,
+}
+
+impl EpochUnit {
+    fn per_second(self) -> f64 {
+        match self {
+            EpochUnit::Seconds => 1.0,
+            EpochUnit::Millis => 1e3,
+            EpochUnit::Micros => 1e6,
+            EpochUnit::Nanos => 1e9,
+        }
+    }
+
+    // 按量级推断：11 位以内为秒（含启动后秒数），其后依次为毫秒、微秒、纳秒
+    fn of(value: f64) -> Self {
+        match value.abs() {
+            v if v < 1e11 => EpochUnit::Seconds,
+            v if v < 1e14 => EpochUnit::Millis,
+            v if v < 1e17 => EpochUnit::Micros,
+            _ => EpochUnit::Nanos,
+        }
+    }
+}
+
+/// 识别出的时间戳格式
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Format {
+    Epoch { unit: EpochUnit },     // 纯数字
+    DateTime { pattern: String },  // 带日期，按文档时区解释
+    Zoned { pattern: String },     // 带时区偏移
+    NoYear { pattern: String },    // 不带年份，推断年份后按文档时区解释
+    TimeOfDay { pattern: String }, // 只有时分秒，取零点起的秒数
+}
+
+impl Format {
+    // 显式指定的格式；chrono 格式串按其中的字段归类
+    fn from_option(s: &str) -> Format {
+        let unit = match s {
+            "epoch_s" => Some(EpochUnit::Seconds),
+            "epoch_ms" => Some(EpochUnit::Millis),
+            "epoch_us" => Some(EpochUnit::Micros),
+            "epoch_ns" => Some(EpochUnit::Nanos),
+            _ => None,
+        };
+        let has = |fields: &[&str]| fields.iter().any(|f| s.contains(f));
+        let pattern = s.to_string();
+        match unit {
+            Some(unit) => Format::Epoch { unit },
+            None if has(&["%z", "%:z", "%#z"]) => Format::Zoned { pattern },
+            None if has(&["%Y", "%y", "%G", "%F", "%c", "%+"]) => Format::DateTime { pattern },
+            None if has(&["%m", "%b", "%B", "%h", "%d", "%e", "%j"]) => Format::NoYear { pattern },
+            None => Format::TimeOfDay { pattern },
+        }
+    }
+
+    // 自动识别时依次尝试的文本格式
+    fn text_candidates() -> impl Iterator<Item = Format> {
+        let of = |patterns: &'static [&'static str], make: fn(String) -> Format| patterns.iter().map(move |p| make(p.to_string()));
+        of(ZONED, |pattern| Format::Zoned { pattern })
+            .chain(of(DATE_TIME, |pattern| Format::DateTime { pattern }))
+            .chain(of(NO_YEAR, |pattern| Format::NoYear { pattern }))
+            .chain(of(TIME_OF_DAY, |pattern| Format::TimeOfDay { pattern }))
+    }
+}
+
+/// 无法解析的时间戳
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TimestampError {
+    pub text: String,
+    pub message: String,
+}
+
+impl fmt::Display for TimestampError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Unrecognized timestamp `{}`: {}", self.text, self.message)
+    }
+}
+
+impl From<TimestampError> for String {
+    fn from(e: TimestampError) -> Self {
+        e.to_string()
+    }
+}
+
+/// 按文档设置与识别出的格式解析时间戳，结果为秒（epoch 或零点起）
+#[derive(Debug, Clone)]
+pub struct TimestampParser {
+    format: Option<Format>, // None：还没有样本，逐条尝试所有格式
+    offset: FixedOffset,
+    year: Option<i32>,
+    reference: NaiveDateTime, // 推断年份的参考时间（文件修改时间，文档时区）
+}
+
+impl TimestampParser {
+    /// modified 为文件修改时间，缺省取当前时间
+    pub fn new(options: &TimestampOptions, modified: Option<SystemTime>) -> Result<Self, String> {
+        let offset = parse_timezone(options.timezone.as_deref().unwrap_or(""))?;
+        let reference = DateTime::<Utc>::from(modified.unwrap_or_else(SystemTime::now)).with_timezone(&offset).naive_local();
+        Ok(TimestampParser {
+            format: options.format.as_deref().filter(|f| !f.trim().is_empty()).map(|f| Format::from_option(f.trim())),
+            offset,
+            year: options.year,
+            reference,
+        })
+    }
+
+    /// 从样本识别格式：取能解析最多样本的格式；样本都无法解析时报告第一条
+    /// 已显式指定格式时只做校验
+    pub fn detect<S: AsRef<str>>(mut self, samples: &[S]) -> Result<Self, TimestampError> {
+        let samples: Vec<&str> = samples.iter().map(|s| s.as_ref()).take(MAX_SAMPLES).collect();
+        let Some(first) = samples.first() else { return Ok(self) };
+        if let Some(format) = &self.format {
+            if samples.iter().all(|s| self.parse_as(format, s).is_none()) {
+                return Err(self.parse(first).err().unwrap_or_else(|| error(first, "does not match the configured format")));
+            }
+            return Ok(self);
+        }
+
+        let numbers: Vec<f64> = samples.iter().filter_map(|s| parse_number(s)).collect();
+        let mut best: Option<(usize, Format)> = None;
+        if !numbers.is_empty() {
+            let mut sorted = numbers.clone();
+            sorted.sort_by(f64::total_cmp);
+            best = Some((numbers.len(), Format::Epoch { unit: EpochUnit::of(sorted[sorted.len() / 2]) }));
+        }
+        for format in Format::text_candidates() {
+            let count = samples.iter().filter(|s| self.parse_as(&format, s).is_some()).count();
+            if count > best.as_ref().map_or(0, |(n, _)| *n) {
+                best = Some((count, format));
+            }
+        }
+        match best {
+            Some((_, format)) => {
+                self.format = Some(format);
+                Ok(self)
+            }
+            None => Err(error(first, "no known timestamp format matches")),
+        }
+    }
+
+    pub fn format(&self) -> Option<&Format> {
+        self.format.as_ref()
+    }
+
+    /// 文档时区相对 UTC 的秒数
+    pub fn utc_offset(&self) -> i32 {
+        self.offset.local_minus_utc()
+    }
+
+    pub fn parse(&self, text: &str) -> Result<f64, TimestampError> {
+        match &self.format {
+            Some(format) => self.parse_as(format, text).ok_or_else(|| error(text, &match format {
+                Format::Epoch { .. } => "not a number".to_string(),
+                Format::DateTime { pattern } | Format::Zoned { pattern } | Format::NoYear { pattern } | Format::TimeOfDay { pattern } => {
+                    format!("does not match `{}`", pattern)
+                }
+            })),
+            None => self.parse_any(text).ok_or_else(|| error(text, "no known timestamp format matches")),
+        }
+    }
+
+    /// 用户输入的时间：先按文档的格式，再尝试其他格式；只写时分秒时取 reference（秒）所在日期的该时刻，早于 reference 则顺延一天
+    pub fn parse_input(&self, text: &str, reference: Option<f64>) -> Result<f64, TimestampError> {
+        let Some(seconds) = self.parse_time_of_day(text) else {
+            return self.format.as_ref().and_then(|f| self.parse_as(f, text))
+                .or_else(|| self.parse_any(text))
+                .ok_or_else(|| error(text, "expected a date-time, a time of day (HH:MM[:SS[.fff]]) or a number"));
+        };
+        let Some(reference) = reference.filter(|r| r.is_finite()) else { return Ok(seconds) };
+        // 只有时分秒的文档里时间本身就是零点起的秒数，不需要按时区换算
+        let offset = match self.format {
+            Some(Format::TimeOfDay { .. }) => 0.0,
+            _ => self.utc_offset() as f64,
+        };
+        let day = ((reference + offset) / 86400.0).floor() * 86400.0 - offset;
+        let time = day + seconds;
+        Ok(if time < reference { time + 86400.0 } else { time })
+    }
+
+    /// 缓存键：同一正则在不同设置或识别结果下解析出的时间不同
+    pub fn key(&self) -> String {
+        let format = serde_json::to_string(&self.format).unwrap_or_default();
+        // 参考时间只影响不带年份的格式
+        let reference = match self.format {
+            Some(Format::NoYear { .. }) | None if self.year.is_none() => self.reference.date().to_string(),
+            _ => String::new(),
+        };
+        format!("{}|{}|{:?}|{}", format, self.offset, self.year, reference)
+    }
+
+    fn parse_any(&self, text: &str) -> Option<f64> {
+        if let Some(value) = parse_number(text) {
+            return Some(value / EpochUnit::of(value).per_second());
+        }
+        Format::text_candidates().find_map(|format| self.parse_as(&format, text))
+    }
+
+    fn parse_time_of_day(&self, text: &str) -> Option<f64> {
+        let text = normalize(text);
+        ["%H:%M:%S%.f", "%H:%M"].iter()
+            .find_map(|p| NaiveTime::parse_from_str(&text, p).ok())
+            .map(|t| t.num_seconds_from_midnight() as f64 + t.nanosecond() as f64 / 1e9)
+    }
+
+    fn parse_as(&self, format: &Format, text: &str) -> Option<f64> {
+        if let Format::Epoch { unit } = format {
+            return parse_number(text).map(|v| v / unit.per_second());
+        }
+        let text = normalize(text);
+        match format {
+            Format::Epoch { .. } => None,
+            Format::DateTime { pattern } => {
+                let dt = NaiveDateTime::parse_from_str(&text, pattern).ok()?;
+                Some(self.local_seconds(dt))
+            }
+            Format::Zoned { pattern } => DateTime::parse_from_str(&text, pattern).ok().map(|dt| seconds(&dt)),
+            Format::NoYear { pattern } => {
+                let with_year = |year: i32| NaiveDateTime::parse_from_str(&format!("{} {}", year, text), &format!("%Y {}", pattern)).ok();
+                let dt = match self.year {
+                    Some(year) => with_year(year)?,
+                    // 日志不会晚于文件最后修改时间：比参考时间晚一天以上的属于上一年（跨年的日志）
+                    None => {
+                        let year = self.reference.year();
+                        match with_year(year) {
+                            Some(dt) if dt <= self.reference + chrono::Duration::days(1) => dt,
+                            _ => with_year(year - 1)?,
+                        }
+                    }
+                };
+                Some(self.local_seconds(dt))
+            }
+            Format::TimeOfDay { pattern } => {
+                let t = NaiveTime::parse_from_str(&text, pattern).ok()?;
+                Some(t.num_seconds_from_midnight() as f64 + t.nanosecond() as f64 / 1e9)
+            }
+        }
+    }
+
+    fn local_seconds(&self, dt: NaiveDateTime) -> f64 {
+        seconds(&dt.and_utc()) - self.utc_offset() as f64
+    }
+}
+
+/// 时间戳正则 + 解析器：正则的第一个捕获组（没有捕获组时取整个匹配）为时间戳文本
+#[derive(Debug, Clone)]
+pub struct Extractor {
+    re: Regex,
+    parser: TimestampParser,
+}
+
+impl Extractor {
+    pub fn new(re: Regex, parser: TimestampParser) -> Self {
+        Extractor { re, parser }
+    }
+
+    /// 从一段文本（如文件开头）取样识别格式
+    pub fn detect_in(re: Regex, text: &str, options: &TimestampOptions, modified: Option<SystemTime>) -> Result<Self, String> {
+        let samples: Vec<&str> = text.lines().filter_map(|line| capture(&re, line)).take(MAX_SAMPLES).collect();
+        let parser = TimestampParser::new(options, modified)?.detect(&samples)?;
+        Ok(Extractor { re, parser })
+    }
+
+    pub fn regex(&self) -> &Regex {
+        &self.re
+    }
+
+    pub fn parser(&self) -> &TimestampParser {
+        &self.parser
+    }
+
+    /// 行的时间戳（秒）；没有匹配时为 None，匹配到但无法解析时为 Some(Err)
+    pub fn extract(&self, line: &str) -> Option<Result<f64, TimestampError>> {
+        capture(&self.re, line).map(|text| self.parser.parse(text))
+    }
+
+    pub fn key(&self) -> String {
+        format!("{}\u{1f}{}", self.re.as_str(), self.parser.key())
+    }
+}
+
+/// 正则的第一个捕获组，没有捕获组时取整个匹配
+pub fn capture<'a>(re: &Regex, line: &'a str) -> Option<&'a str> {
+    let caps = re.captures(line)?;
+    Some(caps.get(1).or_else(|| caps.get(0))?.as_str())
+}
+
+fn error(text: &str, message: &str) -> TimestampError {
+    TimestampError { text: text.to_string(), message: message.to_string() }
+}
+
+fn seconds<Tz: TimeZone>(dt: &DateTime<Tz>) -> f64 {
+    dt.timestamp() as f64 + dt.timestamp_subsec_nanos() as f64 / 1e9
+}
+
+fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let numeric = !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit() || b == b'.');
+    numeric.then(|| text.parse().ok()).flatten()
+}
+
+// 统一写法：合并连续空白（syslog 的 "Jan  2"），数字间的逗号小数点（log4j 的 ",123"）换成点，结尾的 Z 换成 +00:00
+fn normalize(text: &str) -> Cow<'_, str> {
+    let text = text.trim();
+    let bytes = text.as_bytes();
+    let comma_decimal = bytes.windows(3).any(|w| w[0].is_ascii_digit() && w[1] == b',' && w[2].is_ascii_digit());
+    let zulu = text.len() > 1 && text.ends_with(['Z', 'z']) && bytes[bytes.len() - 2].is_ascii_digit();
+    if !comma_decimal && !zulu && !text.contains("  ") && !text.contains('\t') {
+        return Cow::Borrowed(text);
+    }
+    let mut out: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if comma_decimal {
+        let chars: Vec<char> = out.chars().collect();
+        out = chars.iter().enumerate().map(|(i, &c)| {
+            let between_digits = i > 0 && i + 1 < chars.len() && chars[i - 1].is_ascii_digit() && chars[i + 1].is_ascii_digit();
+            if c == ',' && between_digits { '.' } else { c }
+        }).collect();
+    }
+    if zulu {
+        out.pop();
+        out.push_str("+00:00");
+    }
+    Cow::Owned(out)
+}
+
+/// 时区："UTC"/"Z"/空为 UTC，"local" 为本机当前偏移，其余按 +08:00、+0800、-5 这样的偏移解析
+fn parse_timezone(s: &str) -> Result<FixedOffset, String> {
+    let s = s.trim();
+    let invalid = || format!("Invalid timezone `{}`: expected UTC, local or an offset like +08:00", s);
+    match s.to_ascii_lowercase().as_str() {
+        "" | "utc" | "z" | "gmt" => return Ok(Utc.fix()),
+        "local" => return Ok(*Local::now().offset()),
+        _ => {}
+    }
+    let (sign, rest) = match s.as_bytes()[0] {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return Err(invalid()),
+    };
+    // 只接受数字与冒号：按字节切分前先排除多字节字符
+    if rest.is_empty() || !rest.bytes().all(|b| b.is_ascii_digit() || b == b':') {
+        return Err(invalid());
+    }
+    let (hours, minutes) = match rest.split_once(':') {
+        Some((h, m)) => (h, m),
+        None if rest.len() > 2 => rest.split_at(rest.len() - 2),
+        None => (rest, "0"),
+    };
+    let hours: i32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    FixedOffset::east_opt(sign * (hours * 3600 + minutes * 60)).ok_or_else(invalid)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn timezone_offsets() {
+        let offset = |s: &str| parse_timezone(s).map(|tz| tz.local_minus_utc());
+        assert_eq!(offset(""), Ok(0));
+        assert_eq!(offset("UTC"), Ok(0));
+        assert_eq!(offset("z"), Ok(0));
+        assert_eq!(offset("+08:00"), Ok(8 * 3600));
+        assert_eq!(offset("+0800"), Ok(8 * 3600));
+        assert_eq!(offset("-5"), Ok(-5 * 3600));
+        assert_eq!(offset(" -03:30 "), Ok(-(3 * 3600 + 1800)));
+        assert!(offset("local").is_ok());
+        for invalid in ["Mars", "+", "+24:00", "+08:60", "+8h", "08:00", "+é1", "-１２", "é"] {
+            assert!(offset(invalid).is_err(), "{}", invalid);
+        }
+        assert!(TimestampParser::new(&TimestampOptions { timezone: Some("+é1".into()), ..Default::default() }, None).is_err());
+    }
+
+    #[test]
+    fn epoch_units() {
+        assert_eq!(EpochUnit::of(12.5), EpochUnit::Seconds);
+        assert_eq!(EpochUnit::of(1_704_067_200.0), EpochUnit::Seconds);
+        assert_eq!(EpochUnit::of(1_704_067_200_123.0), EpochUnit::Millis);
+        assert_eq!(EpochUnit::of(1_704_067_200_123_456.0), EpochUnit::Micros);
+        assert_eq!(EpochUnit::of(1_704_067_200_123_456_789.0), EpochUnit::Nanos);
+
+        // 按样本的中位数识别单位
+        let detect = |samples: &[&str]| TimestampParser::new(&TimestampOptions::default(), None).unwrap().detect(samples).unwrap();
+        let p = detect(&["1704067200123", "1704067201000", "1704067202500"]);
+        assert_eq!(p.format(), Some(&Format::Epoch { unit: EpochUnit::Millis }));
+        assert_eq!(p.parse("1704067200123").unwrap(), 1_704_067_200.123);
+        let p = detect(&["1704067200123456"]);
+        assert!((p.parse("1704067200123456").unwrap() - 1_704_067_200.123456).abs() < 1e-3);
+
+        // 显式单位不按量级推断
+        for (format, expected) in [("epoch_s", 1500.0), ("epoch_ms", 1.5), ("epoch_us", 0.0015), ("epoch_ns", 0.0000015)] {
+            let p = TimestampParser::new(&TimestampOptions { format: Some(format.into()), ..Default::default() }, None).unwrap();
+            assert_eq!(p.parse("1500").unwrap(), expected, "{}", format);
+            assert!(p.parse("15:00").is_err());
+        }
+    }
+}
@@ -0,0 +1,324 @@
+// Trigram 索引：每 BLOCK_LINES 行为一块，记录块内出现过的三字节片段（ASCII 小写后哈希到固定数量的桶）
+// 搜索前先取查询中必然出现的片段，排除不含这些片段的块，精确匹配只在候选块中进行；字面量与正则查询都适用
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use rayon::prelude::*;
+use regex_syntax::hir::{Class, Hir, HirKind};
+
+use crate::jobs;
+
+// 每块的行数
+pub const BLOCK_LINES: usize = 4096;
+// 片段哈希桶数（每块一个 BUCKETS 位的集合）
+const BUCKETS: usize = 1 << 16;
+
+pub struct TrigramIndex {
+    blocks: usize, // 已收录的整块数；之后的行（含建索引时可能仍在写入的末行所在块）总是候选
+    words: usize,  // 每个桶的块位图字数
+    rows: Vec<u64>, // rows[bucket * words + block / 64] 的第 block % 64 位：该块含有落入此桶的片段
+}
+
+fn bucket(a: u8, b: u8, c: u8) -> usize {
+    let t = u32::from_le_bytes([a.to_ascii_lowercase(), b.to_ascii_lowercase(), c.to_ascii_lowercase(), 0]);
+    (t.wrapping_mul(0x9E37_79B1) >> 16) as usize
+}
+
+impl TrigramIndex {
+    /// 为前 line_count 行建立索引；传入 base 时复用其已收录的块，只扫描之后的块
+    /// line_of 返回行的 UTF-8 文本；任务被取消时提前返回，结果不可用
+    pub fn build<'a>(
+        base: Option<&TrigramIndex>,
+        line_count: usize,
+        line_of: &(dyn Fn(usize) -> Cow<'a, [u8]> + Sync),
+        job: Option<&jobs::Job>,
+    ) -> Self {
+        // 末行可能还没写完，它所在的块不收录
+        let blocks = line_count.saturating_sub(1) / BLOCK_LINES;
+        let words = blocks.div_ceil(64);
+        let mut rows: Vec<AtomicU64> = (0..BUCKETS * words).map(|_| AtomicU64::new(0)).collect();
+
+        let reused = base.map_or(0, |b| b.blocks.min(blocks));
+        if let Some(base) = base.filter(|b| b.words > 0) {
+            let copy = reused.div_ceil(64);
+            for bucket in 0..BUCKETS {
+                for w in 0..copy {
+                    let mut bits = base.rows[bucket * base.words + w];
+                    if (w + 1) * 64 > reused {
+                        bits &= (1u64 << (reused % 64)) - 1;
+                    }
+                    *rows[bucket * words + w].get_mut() = bits;
+                }
+            }
+        }
+
+        (reused..blocks).into_par_iter().for_each(|block| {
+            if job.is_some_and(|j| j.is_cancelled()) {
+                return;
+            }
+            let mut seen = vec![0u64; BUCKETS / 64];
+            for idx in block * BLOCK_LINES..(block + 1) * BLOCK_LINES {
+                if let Some(job) = job {
+                    job.tick(idx);
+                }
+                for t in line_of(idx).windows(3) {
+                    let b = bucket(t[0], t[1], t[2]);
+                    seen[b / 64] |= 1 << (b % 64);
+                }
+            }
+            let (word, bit) = (block / 64, 1u64 << (block % 64));
+            for (i, &w) in seen.iter().enumerate() {
+                let mut w = w;
+                while w != 0 {
+                    let b = i * 64 + w.trailing_zeros() as usize;
+                    rows[b * words + word].fetch_or(bit, Ordering::Relaxed);
+                    w &= w - 1;
+                }
+            }
+        });
+
+        TrigramIndex { blocks, words, rows: rows.into_iter().map(AtomicU64::into_inner).collect() }
+    }
+
+    // 由持久化缓存恢复
+    pub fn from_parts(blocks: usize, rows: Vec<u64>) -> Option<Self> {
+        let words = blocks.div_ceil(64);
+        (rows.len() == BUCKETS * words).then_some(TrigramIndex { blocks, words, rows })
+    }
+
+    pub fn blocks(&self) -> usize {
+        self.blocks
+    }
+
+    pub fn rows(&self) -> &[u64] {
+        &self.rows
+    }
+
+    /// 可能命中查询的块；查询提取不出片段时返回 None（无法缩小范围）
+    pub fn candidates(&self, query: &TrigramQuery) -> Option<Candidates> {
+        if query.alternatives.is_empty() || query.alternatives.iter().any(Vec::is_empty) {
+            return None;
+        }
+        let mut words = vec![0u64; self.words];
+        for alternative in &query.alternatives {
+            let mut all = vec![!0u64; self.words];
+            for &b in alternative {
+                for (acc, &row) in all.iter_mut().zip(&self.rows[b * self.words..(b + 1) * self.words]) {
+                    *acc &= row;
+                }
+            }
+            for (out, acc) in words.iter_mut().zip(all) {
+                *out |= acc;
+            }
+        }
+        Some(Candidates { blocks: self.blocks, words })
+    }
+}
+
+/// 候选块集合；索引未收录的块总是候选
+pub struct Candidates {
+    blocks: usize,
+    words: Vec<u64>,
+}
+
+impl Candidates {
+    pub fn contains(&self, line: usize) -> bool {
+        let block = line / BLOCK_LINES;
+        block >= self.blocks || (self.words[block / 64] >> (block % 64)) & 1 == 1
+    }
+
+    /// 两组候选的交集（同一索引产生），任一方为 None 时取另一方
+    pub fn both(a: Option<Candidates>, b: Option<Candidates>) -> Option<Candidates> {
+        match (a, b) {
+            (Some(mut a), Some(b)) => {
+                for (x, y) in a.words.iter_mut().zip(&b.words) {
+                    *x &= y;
+                }
+                Some(a)
+            }
+            (a, b) => a.or(b),
+        }
+    }
+
+    /// 两组候选的并集（同一索引产生）
+    pub fn either(mut a: Candidates, b: Candidates) -> Option<Candidates> {
+        for (x, y) in a.words.iter_mut().zip(&b.words) {
+            *x |= y;
+        }
+        Some(a)
+    }
+}
+
+/// 查询中必然出现的片段：alternatives 之间为"或"，每个 alternative 内的桶为"且"
+pub struct TrigramQuery {
+    alternatives: Vec<Vec<usize>>,
+}
+
+impl TrigramQuery {
+    /// 任意一个字面量命中即可
+    pub fn literals<S: AsRef<str>>(patterns: &[S], case_insensitive: bool) -> Self {
+        let alternatives = patterns.iter()
+            .map(|p| buckets(&literal_runs(p.as_ref(), case_insensitive)))
+            .collect();
+        TrigramQuery { alternatives }
+    }
+
+    /// 正则必然匹配到的字面量片段；解析失败时不缩小范围
+    pub fn regex(pattern: &str, case_insensitive: bool) -> Self {
+        let runs = regex_syntax::ParserBuilder::new()
+            .case_insensitive(case_insensitive)
+            .build()
+            .parse(pattern)
+            .map(|hir| {
+                let (mut runs, mut current) = (Vec::new(), Vec::new());
+                required_runs(&hir, &mut runs, &mut current);
+                flush(&mut runs, &mut current);
+                runs
+            })
+            .unwrap_or_default();
+        TrigramQuery { alternatives: vec![buckets(&runs)] }
+    }
+}
+
+fn buckets(runs: &[Vec<u8>]) -> Vec<usize> {
+    let mut out: Vec<usize> = runs.iter()
+        .flat_map(|run| run.windows(3).map(|t| bucket(t[0], t[1], t[2])))
+        .collect();
+    out.sort_unstable();
+    out.dedup();
+    out
+}
+
+fn is_cased(c: char) -> bool {
+    c.is_lowercase() || c.is_uppercase()
+}
+
+// 字面量中大小写折叠后字节不变的连续片段
+// 含非 ASCII 大小写字母时按 Unicode 规则匹配，k、s 还可能匹配开尔文符号、长 s，也不能作为片段
+fn literal_runs(pattern: &str, case_insensitive: bool) -> Vec<Vec<u8>> {
+    let unicode = case_insensitive && pattern.chars().any(|c| !c.is_ascii() && is_cased(c));
+    let (mut runs, mut current) = (Vec::new(), Vec::new());
+    for c in pattern.chars() {
+        let stable = !case_insensitive || match c.is_ascii() {
+            true => !(unicode && matches!(c.to_ascii_lowercase(), 'k' | 's')),
+            false => !is_cased(c),
+        };
+        if stable {
+            let mut buf = [0u8; 4];
+            current.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+        } else {
+            flush(&mut runs, &mut current);
+        }
+    }
+    flush(&mut runs, &mut current);
+    runs
+}
+
+fn flush(runs: &mut Vec<Vec<u8>>, current: &mut Vec<u8>) {
+    if current.len() >= 3 {
+        runs.push(std::mem::take(current));
+    } else {
+        current.clear();
+    }
+}
+
+// 收集正则每次匹配都必然包含的连续字面量
+fn required_runs(hir: &Hir, runs: &mut Vec<Vec<u8>>, current: &mut Vec<u8>) {
+    match hir.kind() {
+        HirKind::Empty | HirKind::Look(_) => {}
+        HirKind::Literal(lit) => current.extend_from_slice(&lit.0),
+        HirKind::Class(class) => match case_folded_byte(class) {
+            Some(b) => current.push(b),
+            None => flush(runs, current),
+        },
+        HirKind::Capture(cap) => required_runs(&cap.sub, runs, current),
+        HirKind::Concat(subs) => {
+            for sub in subs {
+                required_runs(sub, runs, current);
+            }
+        }
+        HirKind::Repetition(rep) if rep.min == 1 && rep.max == Some(1) => required_runs(&rep.sub, runs, current),
+        HirKind::Repetition(rep) => {
+            flush(runs, current);
+            if rep.min >= 1 {
+                required_runs(&rep.sub, runs, current);
+                flush(runs, current);
+            }
+        }
+        HirKind::Alternation(_) => flush(runs, current),
+    }
+}
+
+// 只含单个 ASCII 字符（或其大小写两种形式）的字符类，视为该字符
+fn case_folded_byte(class: &Class) -> Option<u8> {
+    let chars: Vec<u32> = match class {
+        Class::Unicode(c) => c.ranges().iter().flat_map(|r| r.start() as u32..=r.end() as u32).take(3).collect(),
+        Class::Bytes(c) => c.ranges().iter().flat_map(|r| r.start() as u32..=r.end() as u32).take(3).collect(),
+    };
+    match chars[..] {
+        [one] if one < 0x80 => Some(one as u8),
+        [a, b] if a < 0x80 && b < 0x80 && (a as u8).eq_ignore_ascii_case(&(b as u8)) => Some((a as u8).to_ascii_lowercase()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 大部分块只有填充行，少数块含待查的词
+    fn sample_lines(n: usize) -> Vec<String> {
+        let words = ["alpha", "Bravo", "charlie", "DELTA", "Kelvin", "straße", "ÜBER", "ERROR: disk", "timeout=30s"];
+        (0..n).map(|i| match i % 1999 {
+            0 => format!("{} x{}", words[i / 1999 % words.len()], i),
+            _ => format!("filler line {}", i % 100),
+        }).collect()
+    }
+
+    #[test]
+    fn candidates_cover_every_match() {
+        let n = BLOCK_LINES * 6 + 17;
+        let lines = sample_lines(n);
+        let line_of = |i: usize| Cow::Borrowed(lines[i].as_bytes());
+        let index = TrigramIndex::build(None, n, &line_of, None);
+        let mut narrowed = false;
+
+        for words in [&["alpha"][..], &["bravo", "delta"], &["KELVIN"], &["über"], &["error: DISK"], &["ab"]] {
+            for case_insensitive in [true, false] {
+                let Some(c) = index.candidates(&TrigramQuery::literals(words, case_insensitive)) else { continue };
+                let matcher = crate::literal::LiteralMatcher::new(words, case_insensitive).unwrap();
+                for (i, line) in lines.iter().enumerate() {
+                    assert!(!matcher.is_match_str(line) || c.contains(i), "{:?} {} {}", words, case_insensitive, line);
+                }
+                narrowed |= (0..n).any(|i| !c.contains(i));
+            }
+        }
+        assert!(narrowed);
+
+        for pattern in [r"alpha|bravo", r"char(lie)+", r"time\w+=\d+s", r"ke.vin", r"ERROR:\s+disk", r"k", r"(unclosed"] {
+            for case_insensitive in [true, false] {
+                let c = index.candidates(&TrigramQuery::regex(pattern, case_insensitive));
+                let Ok(re) = regex::RegexBuilder::new(pattern).case_insensitive(case_insensitive).build() else {
+                    assert!(c.is_none());
+                    continue;
+                };
+                let Some(c) = c else { continue };
+                for (i, line) in lines.iter().enumerate() {
+                    assert!(!re.is_match(line) || c.contains(i), "{} {} {}", pattern, case_insensitive, line);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn incremental_build_matches_full_build() {
+        let n = BLOCK_LINES * 5 + 3;
+        let lines = sample_lines(n);
+        let line_of = |i: usize| Cow::Borrowed(lines[i].as_bytes());
+        let full = TrigramIndex::build(None, n, &line_of, None);
+        let half = TrigramIndex::build(None, BLOCK_LINES * 2 + 1, &line_of, None);
+        let extended = TrigramIndex::build(Some(&half), n, &line_of, None);
+        assert_eq!(full.blocks(), extended.blocks());
+        assert_eq!(full.rows(), extended.rows());
+    }
+}
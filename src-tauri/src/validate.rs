@@ -0,0 +1,118 @@
+// 模式校验：编译正则前先解析，出错时给出出错位置，并指明是哪条过滤条件或分割器，不再静默忽略或退化为匹配所有行
+use std::fmt;
+
+use regex::{Regex, RegexBuilder};
+use serde::Serialize;
+
+use crate::query::QueryError;
+
+/// 模式中的一处错误；column 为模式文本中的字符位置（从 1 开始），无法定位（如超出大小限制）时为 None
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SyntaxError {
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl SyntaxError {
+    pub fn new(message: impl Into<String>) -> Self {
+        SyntaxError { column: None, message: message.into() }
+    }
+
+    /// 模式前还有 prefix 个字符（如过滤条件的前缀符号）时，换算成整条规则中的位置
+    pub fn shifted(mut self, prefix: usize) -> Self {
+        self.column = self.column.map(|c| c + prefix);
+        self
+    }
+
+    /// 归属到某条规则
+    pub fn in_rule(self, rule: impl Into<String>, pattern: &str) -> PatternError {
+        PatternError { rule: rule.into(), pattern: pattern.to_string(), column: self.column, message: self.message }
+    }
+}
+
+impl From<QueryError> for SyntaxError {
+    fn from(e: QueryError) -> Self {
+        SyntaxError { column: Some(e.column), message: e.message }
+    }
+}
+
+/// 某条规则的模式错误；rule 指明出错的过滤条件或分割器（如 "refinement #2"）
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PatternError {
+    pub rule: String,
+    pub pattern: String,
+    pub column: Option<usize>,
+    pub message: String,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Invalid {} `{}`", self.rule, self.pattern)?;
+        if let Some(column) = self.column {
+            write!(f, " at column {}", column)?;
+        }
+        write!(f, ": {}", self.message)
+    }
+}
+
+impl From<PatternError> for String {
+    fn from(e: PatternError) -> Self {
+        e.to_string()
+    }
+}
+
+/// 命令返回给前端的错误：模式错误保持结构化（前端据此指出出错的规则与位置），其余错误为文本
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum CommandError {
+    Pattern(PatternError),
+    Other { message: String },
+}
+
+impl fmt::Display for CommandError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CommandError::Pattern(e) => e.fmt(f),
+            CommandError::Other { message } => f.write_str(message),
+        }
+    }
+}
+
+impl From<PatternError> for CommandError {
+    fn from(e: PatternError) -> Self {
+        CommandError::Pattern(e)
+    }
+}
+
+impl From<String> for CommandError {
+    fn from(message: String) -> Self {
+        CommandError::Other { message }
+    }
+}
+
+impl From<&str> for CommandError {
+    fn from(message: &str) -> Self {
+        CommandError::Other { message: message.to_string() }
+    }
+}
+
+/// 编译正则；语法错误带上出错位置
+pub fn regex(pattern: &str, case_insensitive: bool) -> Result<Regex, SyntaxError> {
+    // regex 的错误只有文本描述，先用 regex-syntax 解析一遍取得位置
+    let parsed = regex_syntax::ParserBuilder::new()
+        .case_insensitive(case_insensitive)
+        .build()
+        .parse(pattern);
+    if let Err(e) = parsed {
+        let (column, message) = match &e {
+            regex_syntax::Error::Parse(e) => (Some(e.span().start.column), e.kind().to_string()),
+            regex_syntax::Error::Translate(e) => (Some(e.span().start.column), e.kind().to_string()),
+            _ => (None, e.to_string()),
+        };
+        return Err(SyntaxError { column, message });
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(case_insensitive)
+        .build()
+        .map_err(|e| SyntaxError::new(e.to_string()))
+}
@@ -1,6 +1,6 @@
 import { useEffect } from 'react';
 import { listen } from '@tauri-apps/api/event';
-import { useLogStore } from './store';
+import { useLogStore, LogAppendedEvent, TailErrorEvent, DecompressProgress, JobProgress } from './store';
 import Header from "./components/Header";
 import Sidebar from "./components/Sidebar";
 import LogViewer from "./components/LogViewer";
@@ -36,6 +36,46 @@ function App() {
     };
   }, []);
 
+  useEffect(() => {
+    const unlistenPromise = listen<DecompressProgress>('decompress-progress', (event) => {
+      useLogStore.setState({ decompressProgress: event.payload.done ? null : event.payload });
+    });
+
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlistenPromise = listen<JobProgress>('job-progress', (event) => {
+      useLogStore.getState().applyJobProgress(event.payload);
+    });
+
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlistenPromise = listen<LogAppendedEvent>('log-appended', (event) => {
+      useLogStore.getState().applyLogAppended(event.payload);
+    });
+
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, []);
+
+  useEffect(() => {
+    const unlistenPromise = listen<TailErrorEvent>('tail-error', (event) => {
+      useLogStore.getState().applyTailError(event.payload);
+    });
+
+    return () => {
+      unlistenPromise.then(unlisten => unlisten());
+    };
+  }, []);
+
   useEffect(() => {
     const handleWheel = (e: WheelEvent) => {
       if (e.ctrlKey) {
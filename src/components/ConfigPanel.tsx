@@ -11,6 +11,7 @@ export default function ConfigPanel() {
     bootMarkerRegex, 
     logLevelRegex,
     timestampRegex,
+    timestampOptions,
     timeGapThreshold,
     logLevelFilter,
     files,
@@ -18,8 +19,15 @@ export default function ConfigPanel() {
     setBootMarkerRegex,
     setLogLevelRegex,
     setTimestampRegex,
+    setTimestampOptions,
     setTimeGapThreshold,
     setLogLevelFilter,
+    searchIndexEnabled,
+    setSearchIndexEnabled,
+    searchMaxResults,
+    setSearchMaxResults,
+    searchOptions,
+    setSearchOptions,
     addProfile,
     updateProfile,
     deleteProfile,
@@ -37,6 +45,9 @@ export default function ConfigPanel() {
   const [bootInput, setBootInput] = useState(bootMarkerRegex);
   const [levelInput, setLevelInput] = useState(logLevelRegex);
   const [timestampInput, setTimestampInput] = useState(timestampRegex);
+  const [timestampOptionsInput, setTimestampOptionsInput] = useState(timestampOptions);
+  const [timestampReport, setTimestampReport] = useState<string | null>(null);
+  const [formatReport, setFormatReport] = useState<string | null>(null);
   const [timeGapInput, setTimeGapInput] = useState(timeGapThreshold);
   const [profileName, setProfileName] = useState('');
 
@@ -53,6 +64,7 @@ export default function ConfigPanel() {
     setBootInput(bootMarkerRegex);
     setLevelInput(logLevelRegex);
     setTimestampInput(timestampRegex);
+    setTimestampOptionsInput(timestampOptions);
     setTimeGapInput(timeGapThreshold);
     setEndpointInput(aiEndpoint);
     setModelInput(aiModel);
@@ -63,7 +75,7 @@ export default function ConfigPanel() {
     if (activeProfile) {
       setProfileName(activeProfile.name);
     }
-  }, [bootMarkerRegex, logLevelRegex, timestampRegex, activeProfileId, profiles, aiEndpoint, aiModel, aiApiKey, aiSystemPrompt, aiMaxIterations]);
+  }, [bootMarkerRegex, logLevelRegex, timestampRegex, timestampOptions, activeProfileId, profiles, aiEndpoint, aiModel, aiApiKey, aiSystemPrompt, aiMaxIterations]);
 
   const logLevels = ['DEBUG', 'INFO', 'WARN', 'ERROR', 'FATAL'];
 
@@ -79,6 +91,7 @@ export default function ConfigPanel() {
     setBootMarkerRegex(bootInput);
     setLogLevelRegex(levelInput);
     setTimestampRegex(timestampInput);
+    setTimestampOptions(timestampOptionsInput);
     setTimeGapThreshold(timeGapInput);
     
     // 如果有当前文件，触发重新解析
@@ -91,6 +104,60 @@ export default function ConfigPanel() {
     }
   };
 
+  // 自动识别格式：取样当前文件，把推荐的正则填入输入框（点击应用后生效）
+  const handleDetectFormat = async () => {
+    if (!currentFile) return;
+    try {
+      const report = await invoke<{
+        lines_sampled: number;
+        timestamp: { regex: string; column: number; confidence: number } | null;
+        level: { regex: string; vocabulary: { level: string; count: number }[]; confidence: number } | null;
+        timestamp_resets: number;
+        boot_markers: { regex: string; example: string; confidence: number }[];
+      }>('detect_log_format', { path: currentFile.path, timestampOptions: timestampOptionsInput });
+      const pct = (c: number) => `${Math.round(c * 100)}%`;
+      const lines = [`取样 ${report.lines_sampled} 行`];
+      if (report.timestamp) {
+        setTimestampInput(report.timestamp.regex);
+        lines.push(`时间戳 ${pct(report.timestamp.confidence)}：第 ${report.timestamp.column} 列`);
+      }
+      if (report.level) {
+        setLevelInput(report.level.regex);
+        lines.push(`级别 ${pct(report.level.confidence)}：${report.level.vocabulary.map(v => v.level).join(' ')}`);
+      }
+      const boot = report.boot_markers[0];
+      if (boot) {
+        setBootInput(boot.regex);
+        lines.push(`启动标识 ${pct(boot.confidence)}（${report.timestamp_resets} 次时间回退）：${boot.example}`);
+      } else {
+        lines.push('未发现时间戳回退，启动标识未修改');
+      }
+      setFormatReport(lines.join('\n'));
+    } catch (err) {
+      setFormatReport(String(err));
+    }
+  };
+
+  // 检查时间戳解析：显示识别出的格式与无法解析的行
+  const handleInspectTimestamps = async () => {
+    if (!currentFile) return;
+    try {
+      const report = await invoke<{
+        format: { kind: string; pattern?: string; unit?: string } | null;
+        utc_offset: number;
+        parsed: number;
+        failed: number;
+        failures: { line_number: number; text: string; message: string }[];
+      }>('inspect_timestamps', { handle: currentFile.handle, timestampRegex: timestampInput || null });
+      const format = report.format ? `${report.format.kind} ${report.format.pattern ?? report.format.unit ?? ''}` : '未识别';
+      const lines = [`格式: ${format}，解析成功 ${report.parsed} 行，失败 ${report.failed} 行`];
+      report.failures.forEach(f => lines.push(`#${f.line_number}: ${f.message} (${f.text})`));
+      setTimestampReport(lines.join('\n'));
+    } catch (err) {
+      setTimestampReport(String(err));
+    }
+  };
+
   const handleSaveProfile = () => {
     const activeProfile = profiles.find(p => p.id === activeProfileId);
     if (activeProfile && activeProfile.id !== 'default') {
@@ -221,6 +288,21 @@ export default function ConfigPanel() {
 
       <hr className="border-gray-700" />
 
+      {/* 自动识别格式 */}
+      {currentFile && !currentFile.archiveMember && !currentFile.rotatedSet && (
+        <div>
+          <button
+            onClick={handleDetectFormat}
+            className="w-full px-3 py-1.5 bg-gray-700 hover:bg-gray-600 text-gray-200 rounded text-xs"
+          >
+            自动识别格式（填入下方正则）
+          </button>
+          {formatReport && (
+            <pre className="mt-2 p-2 bg-gray-900 text-[10px] text-gray-400 rounded whitespace-pre-wrap max-h-40 overflow-y-auto">{formatReport}</pre>
+          )}
+        </div>
+      )}
+
       {/* Boot 标识符配置 */}
       <div>
         <h3 className="text-sm font-semibold text-gray-400 mb-3">Boot 标识符 (正则)</h3>
@@ -258,6 +340,43 @@ export default function ConfigPanel() {
           placeholder="例如: \[(.*?)\]"
           className="w-full px-3 py-2 bg-gray-800 text-white rounded-lg border border-gray-700 focus:border-blue-500 focus:outline-none text-sm font-mono"
         />
+        <div className="grid grid-cols-3 gap-2 mt-2">
+          <input
+            type="text"
+            value={timestampOptionsInput.timezone ?? ''}
+            onChange={(e) => setTimestampOptionsInput({ ...timestampOptionsInput, timezone: e.target.value || null })}
+            placeholder="时区 (UTC / local / +08:00)"
+            className="px-2 py-1 bg-gray-800 text-white rounded border border-gray-700 focus:border-blue-500 focus:outline-none text-xs font-mono"
+          />
+          <input
+            type="number"
+            value={timestampOptionsInput.year ?? ''}
+            onChange={(e) => setTimestampOptionsInput({ ...timestampOptionsInput, year: parseInt(e.target.value) || null })}
+            placeholder="年份 (自动推断)"
+            className="px-2 py-1 bg-gray-800 text-white rounded border border-gray-700 focus:border-blue-500 focus:outline-none text-xs font-mono"
+          />
+          <input
+            type="text"
+            value={timestampOptionsInput.format ?? ''}
+            onChange={(e) => setTimestampOptionsInput({ ...timestampOptionsInput, format: e.target.value || null })}
+            placeholder="格式 (自动识别)"
+            className="px-2 py-1 bg-gray-800 text-white rounded border border-gray-700 focus:border-blue-500 focus:outline-none text-xs font-mono"
+          />
+        </div>
+        <p className="text-[10px] text-gray-500 mt-1">
+          提示：格式可填 epoch_ms 等或 chrono 格式串；不带年份的时间（如 syslog）按文件修改时间推断年份
+        </p>
+        {currentFile && (
+          <button
+            onClick={handleInspectTimestamps}
+            className="mt-2 px-3 py-1 bg-gray-700 hover:bg-gray-600 text-gray-200 rounded text-xs"
+          >
+            检查时间戳解析
+          </button>
+        )}
+        {timestampReport && (
+          <pre className="mt-2 p-2 bg-gray-900 text-[10px] text-gray-400 rounded whitespace-pre-wrap max-h-40 overflow-y-auto">{timestampReport}</pre>
+        )}
         <div className="flex items-center justify-between mt-3">
           <h3 className="text-sm font-semibold text-gray-400">时间间隙切分会话 (秒)</h3>
           <span className="text-[10px] text-gray-500">0 表示禁用</span>
@@ -274,6 +393,49 @@ export default function ConfigPanel() {
         </p>
       </div>
 
+      {/* 搜索索引 */}
+      <div>
+        <label className="flex items-center space-x-2 cursor-pointer">
+          <input
+            type="checkbox"
+            checked={searchIndexEnabled}
+            onChange={(e) => setSearchIndexEnabled(e.target.checked)}
+            className="w-3 h-3"
+          />
+          <span className="text-sm font-semibold text-gray-400">为大文件建立搜索索引 (trigram)</span>
+        </label>
+        <p className="text-[10px] text-gray-500 mt-1">
+          提示：打开文件后在后台建立并随缓存保存，重复搜索与过滤只扫描可能命中的行块
+        </p>
+        <div className="flex items-center justify-between mt-3">
+          <h3 className="text-sm font-semibold text-gray-400">搜索结果上限 (条)</h3>
+          <span className="text-[10px] text-gray-500">0 表示不限</span>
+        </div>
+        <input
+          type="number"
+          min="0"
+          value={searchMaxResults}
+          onChange={(e) => setSearchMaxResults(Math.max(0, parseInt(e.target.value) || 0))}
+          className="w-full px-3 py-2 bg-gray-800 text-blue-400 rounded-lg border border-gray-700 focus:border-blue-500 focus:outline-none text-sm font-bold"
+        />
+        <p className="text-[10px] text-gray-500 mt-1">
+          提示：总匹配数始终完整统计，超出上限的命中不进入结果列表
+        </p>
+        <div className="flex items-center justify-between mt-3">
+          <h3 className="text-sm font-semibold text-gray-400">每个会话最多命中 (行)</h3>
+          <span className="text-[10px] text-gray-500">留空表示不限</span>
+        </div>
+        <input
+          type="number"
+          min="0"
+          value={searchOptions.max_matches_per_session ?? ''}
+          onChange={(e) => setSearchOptions({
+            max_matches_per_session: e.target.value === '' ? null : Math.max(0, parseInt(e.target.value) || 0)
+          })}
+          className="w-full px-3 py-2 bg-gray-800 text-blue-400 rounded-lg border border-gray-700 focus:border-blue-500 focus:outline-none text-sm font-bold"
+        />
+      </div>
+
       <button
         onClick={handleApply}
         className="w-full py-2 px-4 bg-blue-600 hover:bg-blue-700 text-white rounded-lg transition-colors text-sm font-medium"
@@ -1,6 +1,6 @@
 import { useState, useMemo } from 'react';
 import { invoke } from '@tauri-apps/api/core';
-import { useLogStore } from '../store';
+import { useLogStore, createJob, cancelJob, isCancelledError } from '../store';
 
 import {
   XAxis,
@@ -44,6 +44,7 @@ export default function Dashboard() {
   const [idRegex, setIdRegex] = useState('');
   const [isIntervalMode, setIsIntervalMode] = useState(false);
   const [workflowLoading, setWorkflowLoading] = useState(false);
+  const [workflowJobId, setWorkflowJobId] = useState<number | null>(null);
   const [errorMsg, setErrorMsg] = useState<string | null>(null);
 
   const workflowStats = useMemo(() => {
@@ -68,15 +69,20 @@ export default function Dashboard() {
       let results;
       if (isIntervalMode) {
         results = await invoke<WorkflowSegment[]>('analyze_recurrent_intervals', {
+          handle: currentFile.handle,
           regex: startRegex,
           timestampRegex
         });
       } else {
+        const jobId = await createJob();
+        setWorkflowJobId(jobId);
         results = await invoke<WorkflowSegment[]>('analyze_workflow_duration', {
+          handle: currentFile.handle,
           startRegex,
           endRegex,
           timestampRegex,
-          idRegex: idRegex || null
+          idRegex: idRegex || null,
+          jobId
         });
       }
       
@@ -85,9 +91,10 @@ export default function Dashboard() {
       }
       setAnalysisWorkflowResults(results);
     } catch (err: any) {
-      setErrorMsg('计算失败: ' + err);
+      if (!isCancelledError(err)) setErrorMsg('计算失败: ' + err);
     } finally {
       setWorkflowLoading(false);
+      setWorkflowJobId(null);
     }
   };
 
@@ -270,6 +277,14 @@ export default function Dashboard() {
                 </>
               )}
             </button>
+            {workflowJobId !== null && (
+              <button
+                onClick={() => cancelJob(workflowJobId)}
+                className="mt-2 text-[10px] text-gray-400 hover:text-red-400 transition-colors"
+              >
+                取消计算
+              </button>
+            )}
             {errorMsg && <p className="text-[10px] text-red-400 mt-2 text-center bg-red-900/10 py-1 rounded border border-red-900/20 px-2">{errorMsg}</p>}
           </div>
         </div>
@@ -3,10 +3,10 @@ import { useLogStore } from '../store';
 export default function DebugPanel() {
   const showOnlyHighlights = useLogStore((state) => state.showOnlyHighlights);
   const highlights = useLogStore((state) => state.highlights);
-  const filteredIndices = useLogStore((state) => state.filteredIndices);
+  const filteredCount = useLogStore((state) => state.filterResult?.count ?? 0);
   const lineCount = useLogStore((state) => state.lineCount);
   const logLevelFilter = useLogStore((state) => state.logLevelFilter);
-  const highlightContextLines = useLogStore((state) => state.highlightContextLines);
+  const highlightContext = useLogStore((state) => state.highlightContext);
 
   const activeHighlights = highlights.filter(h => h.enabled);
 
@@ -62,13 +62,13 @@ export default function DebugPanel() {
           
           <div className="flex justify-between">
             <span>过滤后行数:</span>
-            <span className="text-purple-400">{filteredIndices.length.toLocaleString()}</span>
+            <span className="text-purple-400">{filteredCount.toLocaleString()}</span>
           </div>
           
           <div className="flex justify-between">
             <span>过滤比例:</span>
             <span className="text-yellow-400">
-              {lineCount > 0 ? ((filteredIndices.length / lineCount) * 100).toFixed(2) : 0}%
+              {lineCount > 0 ? ((filteredCount / lineCount) * 100).toFixed(2) : 0}%
             </span>
           </div>
         </div>
@@ -76,7 +76,12 @@ export default function DebugPanel() {
         <div className="border-t border-gray-700 my-2 pt-2">
           <div className="flex justify-between">
             <span>上下文轮廓:</span>
-            <span className="text-cyan-400">{highlightContextLines} 行</span>
+            <span className="text-cyan-400">
+              {highlightContext.time_window_ms != null
+                ? `±${highlightContext.time_window_ms} ms`
+                : `前 ${highlightContext.before} / 后 ${highlightContext.after} 行`}
+              {highlightContext.same_thread && ' (同线程)'}
+            </span>
           </div>
           
           <div className="flex justify-between">
@@ -91,7 +96,7 @@ export default function DebugPanel() {
           </div>
         )}
 
-        {!showOnlyHighlights && filteredIndices.length === lineCount && lineCount > 0 && (
+        {!showOnlyHighlights && filteredCount === lineCount && lineCount > 0 && (
           <div className="border-t border-green-700 mt-2 pt-2 text-green-400">
             ✓ 正常模式：显示所有行
           </div>
@@ -104,8 +109,7 @@ export default function DebugPanel() {
           console.log('=== 完整状态导出 ===');
           console.log('showOnlyHighlights:', store.showOnlyHighlights);
           console.log('highlights:', store.highlights);
-          console.log('filteredIndices length:', store.filteredIndices.length);
-          console.log('filteredIndices (first 20):', store.filteredIndices.slice(0, 20));
+          console.log('filterResult:', store.filterResult);
           console.log('lineCount:', store.lineCount);
           console.log('logLevelFilter:', store.logLevelFilter);
         }}
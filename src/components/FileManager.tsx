@@ -1,12 +1,38 @@
-import { useCallback } from 'react';
-import { useLogStore } from '../store';
+import { useCallback, useEffect, useState } from 'react';
+import { useLogStore, cancelJob } from '../store';
 import { open } from '@tauri-apps/plugin-dialog';
-import { loadLogFile } from '../utils/logLoader';
+import { loadLogFile, openArchiveMembers, openRotatedLogs, ENCODING_OPTIONS } from '../utils/logLoader';
 
 export default function FileManager() {
   const files = useLogStore((state) => state.files);
   const currentFileId = useLogStore((state) => state.currentFileId);
   const removeFile = useLogStore((state) => state.removeFile);
+  const decompressProgress = useLogStore((state) => state.decompressProgress);
+  const archiveBrowser = useLogStore((state) => state.archiveBrowser);
+  const loadJob = useLogStore((state) => state.loadJobId !== null ? state.jobs[state.loadJobId] : undefined);
+  const [selectedMembers, setSelectedMembers] = useState<string[]>([]);
+  const [isOpeningMembers, setIsOpeningMembers] = useState(false);
+
+  useEffect(() => {
+    setSelectedMembers([]);
+  }, [archiveBrowser]);
+
+  const toggleMember = (name: string) => {
+    setSelectedMembers(prev => prev.includes(name) ? prev.filter(n => n !== name) : [...prev, name]);
+  };
+
+  const handleOpenMembers = async () => {
+    if (!archiveBrowser) return;
+    setIsOpeningMembers(true);
+    try {
+      await openArchiveMembers(archiveBrowser.path, selectedMembers);
+    } catch (error) {
+      console.error('Failed to open archive members:', error);
+      alert('打开压缩包成员失败: ' + error);
+    } finally {
+      setIsOpeningMembers(false);
+    }
+  };
 
   const handleOpenFile = useCallback(async () => {
     try {
@@ -14,7 +40,7 @@ export default function FileManager() {
         multiple: false,
         filters: [{
           name: 'Log Files',
-          extensions: ['log', 'txt']
+          extensions: ['log', 'txt', 'gz', 'zst', 'xz', 'bz2', 'zip', 'tar', 'tgz']
         }]
       });
 
@@ -28,6 +54,19 @@ export default function FileManager() {
     }
   }, []);
 
+  // 轮转日志组：选择目录，目录下的日志按时间先后拼接为一个文档
+  const handleOpenRotated = useCallback(async () => {
+    try {
+      const selected = await open({ directory: true, multiple: false });
+      if (typeof selected === 'string') {
+        await openRotatedLogs(selected);
+      }
+    } catch (error) {
+      console.error('Failed to open rotated logs:', error);
+      alert('打开轮转日志组失败: ' + error);
+    }
+  }, []);
+
   return (
     <div className="p-4">
       <div className="mb-4">
@@ -37,8 +76,85 @@ export default function FileManager() {
         >
           + 打开日志文件
         </button>
+        <button
+          onClick={handleOpenRotated}
+          className="mt-2 w-full py-1.5 px-4 bg-gray-700 hover:bg-gray-600 text-gray-200 rounded-lg transition-colors text-sm"
+        >
+          打开轮转日志组 (目录)
+        </button>
+        {loadJob && !decompressProgress && (
+          <div className="mt-2">
+            <div className="flex justify-between items-center text-[10px] text-gray-400 mb-1">
+              <span className="truncate">
+                {loadJob.phase === 'index' ? '建立索引...' : loadJob.phase === 'sessions' ? '统计会话...' : '加载中...'}
+              </span>
+              <button
+                onClick={() => cancelJob(loadJob.job_id)}
+                className="text-red-400 hover:text-red-300"
+              >
+                取消
+              </button>
+            </div>
+            <div className="h-1 bg-gray-800 rounded overflow-hidden">
+              <div
+                className="h-full bg-blue-500 transition-all"
+                style={{ width: `${loadJob.total > 0 ? Math.min(100, loadJob.done / loadJob.total * 100) : 0}%` }}
+              />
+            </div>
+          </div>
+        )}
+        {decompressProgress && (
+          <div className="mt-2">
+            <div className="flex justify-between text-[10px] text-gray-400 mb-1">
+              <span className="truncate">解压中...</span>
+              <span>{(decompressProgress.bytes_written / 1024 / 1024).toFixed(1)} MB</span>
+            </div>
+            <div className="h-1 bg-gray-800 rounded overflow-hidden">
+              <div
+                className="h-full bg-blue-500 transition-all"
+                style={{ width: `${decompressProgress.total_bytes > 0 ? Math.min(100, decompressProgress.bytes_read / decompressProgress.total_bytes * 100) : 0}%` }}
+              />
+            </div>
+          </div>
+        )}
       </div>
 
+      {archiveBrowser && (
+        <div className="mb-4 p-3 bg-gray-800 border border-gray-700 rounded-lg">
+          <div className="flex items-center justify-between mb-2">
+            <p className="text-xs font-bold text-gray-300 truncate" title={archiveBrowser.path}>
+              📦 {archiveBrowser.path.split(/[\\/]/).pop()}
+            </p>
+            <button
+              onClick={() => useLogStore.setState({ archiveBrowser: null })}
+              className="text-gray-500 hover:text-gray-300 text-xs"
+            >
+              ✕
+            </button>
+          </div>
+          <div className="max-h-60 overflow-y-auto space-y-1">
+            {archiveBrowser.members.map((m) => (
+              <label key={m.name} className="flex items-center space-x-2 text-xs cursor-pointer hover:bg-gray-700/50 rounded px-1 py-0.5">
+                <input
+                  type="checkbox"
+                  checked={selectedMembers.includes(m.name)}
+                  onChange={() => toggleMember(m.name)}
+                />
+                <span className="flex-1 truncate text-gray-300" title={m.name}>{m.name}</span>
+                <span className="text-gray-500 shrink-0">{(m.size / 1024).toFixed(1)} KB</span>
+              </label>
+            ))}
+          </div>
+          <button
+            onClick={handleOpenMembers}
+            disabled={selectedMembers.length === 0 || isOpeningMembers}
+            className="mt-2 w-full py-1.5 text-xs bg-blue-600 hover:bg-blue-700 disabled:opacity-40 text-white rounded transition-colors"
+          >
+            {isOpeningMembers ? '打开中...' : `打开所选 (${selectedMembers.length})`}
+          </button>
+        </div>
+      )}
+
       <div className="space-y-2">
         {files.length === 0 ? (
           <div className="text-center text-gray-500 py-8">
@@ -66,6 +182,23 @@ export default function FileManager() {
                   <p className="text-xs opacity-75 mt-1">
                     {(file.size / 1024).toFixed(2)} KB · {file.lines} 行
                   </p>
+                  {!file.archiveMember && (
+                    <select
+                      value={file.encodingOverride ?? ''}
+                      onClick={(e) => e.stopPropagation()}
+                      onChange={(e) => {
+                        loadLogFile(file.path, e.target.value).catch(err => alert('重新加载失败: ' + err));
+                      }}
+                      className="mt-1 text-[10px] bg-gray-900/60 border border-gray-600 rounded px-1 py-0.5 text-gray-200"
+                      title="文件编码"
+                    >
+                      {ENCODING_OPTIONS.map(enc => (
+                        <option key={enc} value={enc}>
+                          {enc === '' ? `自动 (${file.encoding ?? '未知'})` : enc}
+                        </option>
+                      ))}
+                    </select>
+                  )}
                 </div>
                 <button
                   onClick={(e) => {
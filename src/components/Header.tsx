@@ -12,6 +12,8 @@ export default function Header() {
   const selectedSessionIds = useLogStore((state) => state.selectedSessionIds);
   const isSearchRegex = useLogStore((state) => state.isSearchRegex);
   const setSearchRegex = useLogStore((state) => state.setSearchRegex);
+  const searchOptions = useLogStore((state) => state.searchOptions);
+  const setSearchOptions = useLogStore((state) => state.setSearchOptions);
   const isSidebarOpen = useLogStore((state) => state.isSidebarOpen);
   const setSidebarOpen = useLogStore((state) => state.setSidebarOpen);
   const isAiPanelOpen = useLogStore((state) => state.isAiPanelOpen);
@@ -20,6 +22,9 @@ export default function Header() {
   const setDualPane = useLogStore((state) => state.setDualPane);
   const isWordWrap = useLogStore((state) => state.isWordWrap);
   const setWordWrap = useLogStore((state) => state.setWordWrap);
+  const isTailing = useLogStore((state) => state.isTailing);
+  const setTailing = useLogStore((state) => state.setTailing);
+  const tailError = useLogStore((state) => state.tailError);
   
   const currentFile = files.find(f => f.id === currentFileId);
   const inputRef = useRef<HTMLInputElement>(null);
@@ -96,6 +101,17 @@ export default function Header() {
           </span>
         </button>
 
+        <button
+          onClick={() => setTailing(!isTailing)}
+          disabled={!currentFile}
+          title={tailError ? `跟随出错: ${tailError}` : isTailing ? "停止跟随 (tail -f)" : "跟随文件追加 (tail -f)"}
+          className={`p-1.5 h-8 flex items-center rounded-md transition-all disabled:opacity-40 ${isTailing && tailError ? 'text-red-400 bg-red-500/10 border border-red-500/30' : isTailing ? 'text-amber-400 bg-amber-500/10 border border-amber-500/30' : 'text-gray-400 hover:bg-gray-700 hover:text-amber-300'}`}
+        >
+          <span className="text-[10px] font-bold font-mono tracking-tighter uppercase">
+            {isTailing ? 'Follow On' : 'Follow Off'}
+          </span>
+        </button>
+
         <div className="flex items-baseline space-x-2">
           <h1 className="text-xl font-bold bg-gradient-to-r from-blue-400 to-indigo-500 bg-clip-text text-transparent">LogView</h1>
           <span className="text-[10px] font-mono text-gray-500 px-1.5 py-0.5 bg-gray-900 border border-gray-700 rounded select-none">v1.2.0</span>
@@ -130,6 +146,27 @@ export default function Header() {
             >
               .*
             </button>
+            <button
+              onClick={() => setSearchOptions({ case_sensitive: !searchOptions.case_sensitive })}
+              title="区分大小写"
+              className={`p-1 rounded text-xs transition-colors font-mono ${searchOptions.case_sensitive ? 'bg-blue-600 text-white' : 'text-gray-500 hover:bg-gray-800'}`}
+            >
+              Aa
+            </button>
+            <button
+              onClick={() => setSearchOptions({ whole_word: !searchOptions.whole_word })}
+              title="全词匹配"
+              className={`p-1 rounded text-xs transition-colors font-mono underline ${searchOptions.whole_word ? 'bg-blue-600 text-white' : 'text-gray-500 hover:bg-gray-800'}`}
+            >
+              ab
+            </button>
+            <button
+              onClick={() => setSearchOptions({ invert_match: !searchOptions.invert_match })}
+              title="反选：列出不匹配的行"
+              className={`p-1 rounded text-xs transition-colors font-mono ${searchOptions.invert_match ? 'bg-blue-600 text-white' : 'text-gray-500 hover:bg-gray-800'}`}
+            >
+              !=
+            </button>
             <button
               onMouseEnter={() => setShowRegexHelp(true)}
               onMouseLeave={() => setShowRegexHelp(false)}
@@ -152,7 +189,7 @@ export default function Header() {
                 ))}
               </div>
               <p className="mt-3 pt-3 border-t border-gray-700 text-[9px] text-gray-500">
-                提示：正则表达式默认不区分大小写。
+                提示：默认不区分大小写，可用 Aa 切换；选项同样作用于跳转与追踪关键字过滤。
               </p>
             </div>
           )}
@@ -7,17 +7,19 @@ export default function KeyPathTracker() {
   const { 
     highlights, 
     showOnlyHighlights, 
-    highlightContextLines,
+    highlightContext,
+    markerScope,
     lineContents,
     addHighlight, 
     removeHighlight, 
     toggleHighlight, 
     setShowOnlyHighlights,
-    setHighlightContextLines,
+    setHighlightContext,
+    setMarkerScope,
     setScrollTargetLine,
     exportHighlights,
     importHighlights,
-    filteredIndices,
+    filterResult,
     refinementFilters,
     addRefinementFilter,
     removeRefinementFilter,
@@ -28,6 +30,13 @@ export default function KeyPathTracker() {
 
   const [input, setInput] = useState('');
   const [refinementInput, setRefinementInput] = useState('');
+  const [scopeStart, setScopeStart] = useState(markerScope?.start ?? '');
+  const [scopeEnd, setScopeEnd] = useState(markerScope?.end ?? '');
+
+  const applyScope = () => {
+    if (!scopeStart.trim() || !scopeEnd.trim()) return;
+    setMarkerScope({ ...markerScope, start: scopeStart.trim(), end: scopeEnd.trim() });
+  };
 
   // 计算每个关键字的出现次数和行号 (仅基于当前缓存的行)
   const highlightStats = useMemo(() => {
@@ -124,7 +133,7 @@ export default function KeyPathTracker() {
   };
 
   const handleExportResult = async () => {
-    if (filteredIndices.length === 0) return;
+    if (!filterResult || filterResult.count === 0) return;
     
     try {
       setExporting(true);
@@ -135,8 +144,8 @@ export default function KeyPathTracker() {
 
       if (path) {
         await invoke('save_filtered_logs', { 
-          path, 
-          indices: filteredIndices 
+          resultId: filterResult.id,
+          path
         });
         alert('导出成功！');
       }
@@ -155,10 +164,10 @@ export default function KeyPathTracker() {
           <div className="flex items-center space-x-1">
             <button 
               onClick={handleExportResult}
-              disabled={exporting || filteredIndices.length === 0}
+              disabled={exporting || !filterResult || filterResult.count === 0}
               title="导出当前过滤后的追踪结果日志"
               className={`px-3 py-1.5 text-xs rounded border transition-colors flex items-center space-x-2 ${
-                exporting || filteredIndices.length === 0
+                exporting || !filterResult || filterResult.count === 0
                 ? 'bg-gray-800 text-gray-600 border-gray-800 cursor-not-allowed'
                 : 'bg-emerald-900/30 text-emerald-400 border-emerald-800/50 hover:bg-emerald-800/50'
               }`}
@@ -191,17 +200,63 @@ export default function KeyPathTracker() {
         </div>
 
         <div className="flex items-center justify-between bg-gray-800/30 p-3 rounded-lg border border-gray-700/30">
-          <div className="flex items-center space-x-4">
-            <label className="flex items-center space-x-3 cursor-pointer group">
-              <span className="text-xs text-gray-500 group-hover:text-gray-400 transition-colors">上下文轮廓</span>
+          <div className="flex flex-wrap items-center gap-3">
+            <span className="text-xs text-gray-500">上下文轮廓</span>
+            {highlightContext.time_window_ms == null ? (
+              <>
+                <label className="flex items-center space-x-1.5 cursor-pointer group" title="命中行之前带出的行数">
+                  <span className="text-[10px] text-gray-500 group-hover:text-gray-400 transition-colors">前</span>
+                  <input
+                    type="number"
+                    min="0"
+                    max="100"
+                    value={highlightContext.before}
+                    onChange={(e) => setHighlightContext({ before: parseInt(e.target.value) || 0 })}
+                    className="w-14 px-2 py-1.5 bg-gray-900 text-blue-400 rounded-md border border-gray-700 focus:border-blue-500 focus:outline-none text-xs text-center font-bold"
+                  />
+                </label>
+                <label className="flex items-center space-x-1.5 cursor-pointer group" title="命中行之后带出的行数">
+                  <span className="text-[10px] text-gray-500 group-hover:text-gray-400 transition-colors">后</span>
+                  <input
+                    type="number"
+                    min="0"
+                    max="100"
+                    value={highlightContext.after}
+                    onChange={(e) => setHighlightContext({ after: parseInt(e.target.value) || 0 })}
+                    className="w-14 px-2 py-1.5 bg-gray-900 text-blue-400 rounded-md border border-gray-700 focus:border-blue-500 focus:outline-none text-xs text-center font-bold"
+                  />
+                </label>
+              </>
+            ) : (
+              <label className="flex items-center space-x-1.5 cursor-pointer group" title="按时间戳取命中行前后 N 毫秒内的行">
+                <span className="text-[10px] text-gray-500 group-hover:text-gray-400 transition-colors">±</span>
+                <input
+                  type="number"
+                  min="0"
+                  value={highlightContext.time_window_ms}
+                  onChange={(e) => setHighlightContext({ time_window_ms: Math.max(0, parseInt(e.target.value) || 0) })}
+                  className="w-20 px-2 py-1.5 bg-gray-900 text-blue-400 rounded-md border border-gray-700 focus:border-blue-500 focus:outline-none text-xs text-center font-bold"
+                />
+                <span className="text-[10px] text-gray-500">ms</span>
+              </label>
+            )}
+            <label className="flex items-center space-x-1.5 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={highlightContext.time_window_ms != null}
+                onChange={(e) => setHighlightContext({ time_window_ms: e.target.checked ? 500 : null })}
+                className="w-3 h-3"
+              />
+              <span className="text-[10px] text-gray-500">按时间</span>
+            </label>
+            <label className="flex items-center space-x-1.5 cursor-pointer" title="只带出与命中行同一线程/进程的行（识别 tid=、pid=、[thread-N] 等）">
               <input
-                type="number"
-                min="0"
-                max="100"
-                value={highlightContextLines}
-                onChange={(e) => setHighlightContextLines(parseInt(e.target.value) || 0)}
-                className="w-14 px-2 py-1.5 bg-gray-900 text-blue-400 rounded-md border border-gray-700 focus:border-blue-500 focus:outline-none text-xs text-center font-bold"
+                type="checkbox"
+                checked={!!highlightContext.same_thread}
+                onChange={(e) => setHighlightContext({ same_thread: e.target.checked })}
+                className="w-3 h-3"
               />
+              <span className="text-[10px] text-gray-500">同线程</span>
             </label>
           </div>
           
@@ -297,6 +352,75 @@ export default function KeyPathTracker() {
         )}
       </div>
 
+      <div className="flex flex-col space-y-2 pt-2 border-t border-gray-800/50">
+        <label className="text-[10px] font-bold text-gray-500 uppercase">3. 标记范围 (起止正则)</label>
+        <div className="flex space-x-2">
+          <input
+            type="text"
+            value={scopeStart}
+            onChange={(e) => setScopeStart(e.target.value)}
+            onKeyDown={(e) => e.key === 'Enter' && applyScope()}
+            placeholder="起始标记，如 START_STREAM"
+            className="flex-1 min-w-0 px-3 py-2 bg-gray-900/50 text-white rounded-lg border border-gray-700/50 focus:border-blue-500/50 focus:outline-none text-sm font-mono placeholder:text-gray-600"
+          />
+          <input
+            type="text"
+            value={scopeEnd}
+            onChange={(e) => setScopeEnd(e.target.value)}
+            onKeyDown={(e) => e.key === 'Enter' && applyScope()}
+            placeholder="结束标记，如 STOP_STREAM"
+            className="flex-1 min-w-0 px-3 py-2 bg-gray-900/50 text-white rounded-lg border border-gray-700/50 focus:border-blue-500/50 focus:outline-none text-sm font-mono placeholder:text-gray-600"
+          />
+          {markerScope ? (
+            <button
+              onClick={() => setMarkerScope(null)}
+              className="px-4 py-2 bg-gray-800 hover:bg-gray-700 text-red-400 rounded-lg transition-colors text-sm border border-gray-700"
+            >
+              清除
+            </button>
+          ) : (
+            <button
+              onClick={applyScope}
+              disabled={!scopeStart.trim() || !scopeEnd.trim()}
+              className="px-4 py-2 bg-gray-800 hover:bg-gray-700 text-blue-400 rounded-lg transition-colors text-sm border border-gray-700 disabled:opacity-40"
+            >
+              应用
+            </button>
+          )}
+        </div>
+        {markerScope && (
+          <div className="flex flex-wrap items-center gap-3">
+            <label className="flex items-center space-x-1.5 cursor-pointer" title="起始标记可以嵌套，回到最外层的结束标记才闭合">
+              <input
+                type="checkbox"
+                checked={!!markerScope.nested}
+                onChange={(e) => setMarkerScope({ ...markerScope, nested: e.target.checked })}
+                className="w-3 h-3"
+              />
+              <span className="text-[10px] text-gray-500">嵌套</span>
+            </label>
+            <label className="flex items-center space-x-1.5 cursor-pointer">
+              <input
+                type="checkbox"
+                checked={markerScope.include_markers ?? true}
+                onChange={(e) => setMarkerScope({ ...markerScope, include_markers: e.target.checked })}
+                className="w-3 h-3"
+              />
+              <span className="text-[10px] text-gray-500">包含标记行</span>
+            </label>
+            <label className="flex items-center space-x-1.5 cursor-pointer" title="没有结束标记的块是否延伸到文件末尾">
+              <input
+                type="checkbox"
+                checked={markerScope.unterminated !== 'drop'}
+                onChange={(e) => setMarkerScope({ ...markerScope, unterminated: e.target.checked ? 'to_end' : 'drop' })}
+                className="w-3 h-3"
+              />
+              <span className="text-[10px] text-gray-500">未闭合延伸到末尾</span>
+            </label>
+          </div>
+        )}
+      </div>
+
       <div className="pt-2 border-t border-gray-800/50">
         <label className="text-[10px] font-bold text-gray-600 uppercase mb-2 block">已添加的踪迹关键字</label>
         <div className="space-y-2">
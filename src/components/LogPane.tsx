@@ -1,6 +1,6 @@
 import React, { useState, useEffect, useRef } from 'react';
 import { Virtuoso, VirtuosoHandle } from 'react-virtuoso';
-import { useLogStore } from '../store';
+import { useLogStore, levelName, describeError } from '../store';
 import { invoke } from '@tauri-apps/api/core';
 
 interface LogPaneProps {
@@ -46,7 +46,8 @@ export default function LogPane({ side }: LogPaneProps) {
   const isLeft = side === 'left';
   
   // 选择侧相关的 Store 状态 - 使用更精准的选择器减小重渲染范围
-  const filteredIndices = useLogStore((state) => isLeft ? state.filteredIndices : state.rightFilteredIndices);
+  const filterResult = useLogStore((state) => isLeft ? state.filterResult : state.rightFilterResult);
+  const filterError = useLogStore((state) => isLeft ? state.filterError : state.rightFilterError);
   const refinementFilters = useLogStore((state) => isLeft ? state.refinementFilters : state.rightRefinementFilters);
   const scrollTargetLine = useLogStore((state) => isLeft ? state.scrollTargetLine : state.rightScrollTargetLine);
   
@@ -57,6 +58,7 @@ export default function LogPane({ side }: LogPaneProps) {
   
   // 共享的 Store 状态
   const lineLevels = useLogStore((state) => state.lineLevels);
+  const levelTable = useLogStore((state) => state.levelTable);
   const lineContents = useLogStore((state) => state.lineContents);
   const highlights = useLogStore((state) => state.highlights);
   const fontSize = useLogStore((state) => state.fontSize);
@@ -78,10 +80,14 @@ export default function LogPane({ side }: LogPaneProps) {
 
   // 本地搜索项
   const [localSearch, setLocalSearch] = useState('');
-  const [refinementMode, setRefinementMode] = useState<'include' | 'exclude' | 'regex' | 'exact' | 'ai' | 'command' | 'time'>('include');
+  const [refinementMode, setRefinementMode] = useState<'include' | 'exclude' | 'regex' | 'exact' | 'query' | 'ai' | 'command' | 'time'>('include');
   const filterInputRef = useRef<HTMLInputElement>(null);
 
-  const displayIndices = filteredIndices;
+  // 过滤结果保存在后端，这里只缓存已拉取页的 “显示行 -> 原始行(0-based)” 映射
+  const displayCount = filterResult?.count ?? 0;
+  const [rowLines, setRowLines] = useState<Map<number, number>>(new Map());
+  const rowLinesRef = useRef(rowLines);
+  rowLinesRef.current = rowLines;
 
   const virtuosoRef = useRef<VirtuosoHandle>(null);
   const rangeRef = useRef<{ startIndex: number; endIndex: number } | null>(null);
@@ -92,6 +98,7 @@ export default function LogPane({ side }: LogPaneProps) {
     if (filter.startsWith('!')) return { label: 'Exclude', text: filter.substring(1), icon: '✕', color: 'text-red-400', bg: 'bg-red-900/40', border: 'border-red-900/50' };
     if (filter.startsWith('/')) return { label: 'Regex', text: filter.substring(1), icon: '◈', color: 'text-purple-400', bg: 'bg-purple-900/40', border: 'border-purple-900/50' };
     if (filter.startsWith('=')) return { label: 'Exact', text: filter.substring(1), icon: '≡', color: 'text-emerald-400', bg: 'bg-emerald-900/40', border: 'border-emerald-900/50' };
+    if (filter.startsWith('&')) return { label: 'Query', text: filter.substring(1), icon: '∧', color: 'text-pink-400', bg: 'bg-pink-900/40', border: 'border-pink-900/50' };
     if (filter.startsWith('?')) return { label: 'AI', text: filter.substring(1), icon: '✨', color: 'text-blue-400', bg: 'bg-blue-900/40', border: 'border-blue-700/50' };
     if (filter.startsWith(':')) return { label: 'Command', text: filter.substring(1), icon: '⌨', color: 'text-amber-400', bg: 'bg-amber-900/40', border: 'border-amber-900/50' };
     if (filter.startsWith('@')) return { label: 'Time', text: filter.substring(1), icon: '🕒', color: 'text-cyan-400', bg: 'bg-cyan-900/40', border: 'border-cyan-900/50' };
@@ -103,6 +110,7 @@ export default function LogPane({ side }: LogPaneProps) {
       case 'exclude': return { label: 'Exclude', color: 'text-red-400', bg: 'bg-red-500/20', prefix: '!' };
       case 'regex': return { label: 'Regex', color: 'text-purple-400', bg: 'bg-purple-500/20', prefix: '/' };
       case 'exact': return { label: 'Exact', color: 'text-emerald-400', bg: 'bg-emerald-500/20', prefix: '=' };
+      case 'query': return { label: 'Query', color: 'text-pink-400', bg: 'bg-pink-500/20', prefix: '&' };
       case 'ai': return { label: 'AI', color: 'text-blue-400', bg: 'bg-blue-500/20', prefix: '?' };
       case 'command': return { label: 'Command', color: 'text-amber-400', bg: 'bg-amber-500/20', prefix: ':' };
       case 'time': return { label: 'Time', color: 'text-cyan-400', bg: 'bg-cyan-500/20', prefix: '@' };
@@ -145,6 +153,7 @@ export default function LogPane({ side }: LogPaneProps) {
         '!': 'exclude',
         '/': 'regex',
         '=': 'exact',
+        '&': 'query',
         '?': 'ai',
         ':': 'command',
         '@': 'time'
@@ -217,23 +226,33 @@ export default function LogPane({ side }: LogPaneProps) {
   };
 
   const fetchLogs = async (startIndex: number, endIndex: number) => {
+    if (!filterResult) return;
     // 增加数据预取范围：多抓取前后各 100 行，减少 IPC 调用频率
     const buffer = 100;
     const bufferedStart = Math.max(0, startIndex - buffer);
-    const bufferedEnd = Math.min(displayIndices.length - 1, endIndex + buffer);
-    
-    const visibleIndices = displayIndices.slice(bufferedStart, bufferedEnd + 1);
-    if (visibleIndices.length === 0) return;
+    const bufferedEnd = Math.min(displayCount - 1, endIndex + buffer);
+    if (bufferedEnd < bufferedStart) return;
 
-    // 关键优化：只请求缓存中不存在的行，极大提升后续滑动性能
+    // 关键优化：只请求映射或内容尚未缓存的那一段，极大提升后续滑动性能
     const store = useLogStore.getState();
-    const indicesToFetch = visibleIndices.filter(idx => !store.lineContents.has(idx + 1));
-    
-    if (indicesToFetch.length === 0) return;
+    const isMissing = (row: number) => {
+      const line = rowLinesRef.current.get(row);
+      return line === undefined || !store.lineContents.has(line + 1);
+    };
+    let first = bufferedStart;
+    while (first <= bufferedEnd && !isMissing(first)) first++;
+    let last = bufferedEnd;
+    while (last >= first && !isMissing(last)) last--;
+    if (first > last) return;
 
     try {
-      const results = await invoke<any[]>('get_log_lines_by_indices', { indices: indicesToFetch });
+      const results = await invoke<any[]>('get_result_lines', { resultId: filterResult.id, offset: first, limit: last - first + 1 });
       if (results.length > 0) {
+        setRowLines(prev => {
+          const next = new Map(prev);
+          results.forEach((r, i) => next.set(first + i, r.line_number - 1));
+          return next;
+        });
         store.updateLogLinesContent(results.map(r => ({
           lineNumber: r.line_number,
           content: r.content,
@@ -246,18 +265,21 @@ export default function LogPane({ side }: LogPaneProps) {
   };
 
   useEffect(() => {
-    if (scrollTargetLine !== null && virtuosoRef.current) {
-      const index = displayIndices.indexOf(scrollTargetLine - 1);
-      if (index !== -1) {
+    if (scrollTargetLine === null || !filterResult) return;
+    let cancelled = false;
+    invoke<number | null>('get_result_rank', { resultId: filterResult.id, lineIndex: scrollTargetLine - 1 })
+      .then(index => {
+        if (cancelled || index === null || !virtuosoRef.current) return;
         isProgrammaticScroll.current = true;
         virtuosoRef.current.scrollToIndex({ index, align: 'center', behavior: 'auto' });
         setTimeout(() => {
           setScrollTargetLineStore(null);
           isProgrammaticScroll.current = false;
         }, 100);
-      }
-    }
-  }, [scrollTargetLine, displayIndices]);
+      })
+      .catch(e => console.error('Locate line failed:', e));
+    return () => { cancelled = true; };
+  }, [scrollTargetLine, filterResult]);
 
   const handleScroll = (range: { startIndex: number; endIndex: number }) => {
     rangeRef.current = range;
@@ -265,14 +287,16 @@ export default function LogPane({ side }: LogPaneProps) {
     fetchTimeoutRef.current = setTimeout(() => fetchLogs(range.startIndex, range.endIndex), 20); // 压低到 20ms
   };
 
-  // 当日志索引发生变化（如加载新文件或过滤条件改变）时，立即拉取当前可见区域的内容
+  // 当日志索引发生变化（如加载新文件或过滤条件改变）时，清空行映射并立即拉取当前可见区域的内容
   useEffect(() => {
-    if (currentFileId && displayIndices.length > 0) {
+    rowLinesRef.current = new Map();
+    setRowLines(rowLinesRef.current);
+    if (currentFileId && displayCount > 0) {
       const start = rangeRef.current?.startIndex || 0;
       const end = rangeRef.current?.endIndex || 50;
       fetchLogs(start, end);
     }
-  }, [displayIndices, currentFileId]);
+  }, [filterResult, currentFileId]);
 
   const highlightContent = (content: string) => {
     if (!highlightRegexes.length) return content;
@@ -340,7 +364,7 @@ export default function LogPane({ side }: LogPaneProps) {
                 {getActiveModeInfo().label}
               </span>
               <span className="text-gray-600 text-xs">
-                {refinementMode === 'include' ? 'Type to filter...' : 'Enter expression...'}
+                {refinementMode === 'include' ? 'Type to filter...' : refinementMode === 'query' ? '(error OR fatal) AND level>=WARN' : 'Enter expression...'}
               </span>
             </div>
           )}
@@ -400,7 +424,14 @@ export default function LogPane({ side }: LogPaneProps) {
         </div>
         
         <div className="flex items-center gap-2 ml-auto text-[10px] text-gray-500">
-          <span>{displayIndices.length.toLocaleString()} lines</span>
+          {filterError && (
+            <span className="text-red-400 truncate max-w-xs" title={describeError(filterError)}>
+              {filterError.kind === 'pattern'
+                ? `${filterError.rule}${filterError.column !== null ? ` (col ${filterError.column})` : ''}: ${filterError.message}`
+                : filterError.message}
+            </span>
+          )}
+          <span>{displayCount.toLocaleString()} lines</span>
         </div>
       </div>
 
@@ -408,19 +439,19 @@ export default function LogPane({ side }: LogPaneProps) {
         <Virtuoso
           ref={virtuosoRef}
           style={{ height: '100%' }}
-          totalCount={displayIndices.length}
+          totalCount={displayCount}
           rangeChanged={handleScroll}
           itemContent={(index) => {
-            const originalLineIndex = displayIndices[index];
+            const originalLineIndex = rowLines.get(index) ?? -1;
             const content = lineContents.get(originalLineIndex + 1) || 'Loading...';
-            const level = lineLevels[originalLineIndex];
-            const isHighlighted = flashLine === (originalLineIndex + 1);
+            const level = originalLineIndex >= 0 ? levelName(lineLevels, levelTable, originalLineIndex) : null;
+            const isHighlighted = originalLineIndex >= 0 && flashLine === (originalLineIndex + 1);
             
             let timeDelta = null;
             if (index > 0) {
-              const prevIndex = displayIndices[index - 1];
-              const prevContent = lineContents.get(prevIndex + 1);
-              if (prevContent) timeDelta = calculateTimeDelta(content, prevContent);
+              const prevIndex = rowLines.get(index - 1);
+              const prevContent = prevIndex !== undefined ? lineContents.get(prevIndex + 1) : undefined;
+              if (prevContent && originalLineIndex >= 0) timeDelta = calculateTimeDelta(content, prevContent);
             }
 
             return (
@@ -69,6 +69,7 @@ export default function MetricsPanel() {
     setIsExtracting(true);
     try {
       const data = await invoke('extract_metrics', {
+        handle: currentFile.handle,
         regex: regex
       });
       updateMetricData(metricId, data as any[]);
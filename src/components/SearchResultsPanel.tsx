@@ -4,6 +4,9 @@ import { useState, useEffect } from 'react';
 
 export default function SearchResultsPanel() {
   const searchResults = useLogStore((state) => state.searchResults);
+  const searchResult = useLogStore((state) => state.searchResult);
+  const searchTotal = useLogStore((state) => state.searchTotal);
+  const loadMoreSearchResults = useLogStore((state) => state.loadMoreSearchResults);
   const searchQuery = useLogStore((state) => state.searchQuery);
   const isSearchPanelOpen = useLogStore((state) => state.isSearchPanelOpen);
   const setSearchPanelOpen = useLogStore((state) => state.setSearchPanelOpen);
@@ -14,7 +17,8 @@ export default function SearchResultsPanel() {
   const fontSize = useLogStore((state) => state.fontSize);
   const searchOnlySelectedSessions = useLogStore((state) => state.searchOnlySelectedSessions);
   const selectedSessionIds = useLogStore((state) => state.selectedSessionIds);
-  const isSearchRegex = useLogStore((state) => state.isSearchRegex);
+  const searchContext = useLogStore((state) => state.searchContext);
+  const setSearchContext = useLogStore((state) => state.setSearchContext);
 
   const [isResizing, setIsResizing] = useState(false);
 
@@ -57,19 +61,22 @@ export default function SearchResultsPanel() {
     }, 50);
   };
 
-  const highlightMatch = (content: string, query: string) => {
-    if (!query) return content;
-    try {
-      const regex = new RegExp(`(${isSearchRegex ? query : query.replace(/[.*+?^${}()|[\]\\]/g, '\\$&')})`, 'gi');
-      const parts = content.split(regex);
-      return parts.map((part, i) => (
-        regex.test(part) ? (
-          <mark key={i} className="bg-yellow-500/40 text-yellow-100 rounded-px px-0.5">{part}</mark>
-        ) : part
-      ));
-    } catch (e) {
-      return content;
-    }
+  // 按后端返回的命中区间（字符偏移）高亮，与搜索的匹配语义一致
+  const highlightMatch = (line: LogLine) => {
+    if (!line.spans?.length) return line.content;
+    const chars = Array.from(line.content);
+    const parts: React.ReactNode[] = [];
+    let cursor = 0;
+    line.spans.forEach((span, i) => {
+      if (span.char_start < cursor) return;
+      if (span.char_start > cursor) parts.push(chars.slice(cursor, span.char_start).join(''));
+      parts.push(
+        <mark key={i} className="bg-yellow-500/40 text-yellow-100 rounded-px px-0.5">{chars.slice(span.char_start, span.char_end).join('')}</mark>
+      );
+      cursor = span.char_end;
+    });
+    if (cursor < chars.length) parts.push(chars.slice(cursor).join(''));
+    return parts;
   };
 
   return (
@@ -101,7 +108,30 @@ export default function SearchResultsPanel() {
             )
           )}
           <span className="text-xs text-gray-500">
-            共找到 {searchResults.length} 处匹配
+            共找到 {searchTotal} 处匹配
+          </span>
+          {searchResult && searchTotal > (searchResult.hit_count ?? searchResult.count) && (
+            <span className="text-[10px] text-yellow-500">
+              仅保留前 {searchResult.hit_count ?? searchResult.count} 条，可在配置中调整上限
+            </span>
+          )}
+          <span className="flex items-center space-x-1 text-[10px] text-gray-500" title="命中前后带出的上下文行数，同 grep -B / -A">
+            <span>上文</span>
+            <input
+              type="number"
+              min="0"
+              value={searchContext.before}
+              onChange={(e) => setSearchContext({ ...searchContext, before: Math.max(0, parseInt(e.target.value) || 0) })}
+              className="w-10 px-1 py-0.5 bg-gray-900 text-blue-400 rounded border border-gray-700 focus:border-blue-500 focus:outline-none text-center"
+            />
+            <span>下文</span>
+            <input
+              type="number"
+              min="0"
+              value={searchContext.after}
+              onChange={(e) => setSearchContext({ ...searchContext, after: Math.max(0, parseInt(e.target.value) || 0) })}
+              className="w-10 px-1 py-0.5 bg-gray-900 text-blue-400 rounded border border-gray-700 focus:border-blue-500 focus:outline-none text-center"
+            />
           </span>
         </div>
         <button 
@@ -121,20 +151,28 @@ export default function SearchResultsPanel() {
           <Virtuoso
             style={{ height: '100%' }}
             totalCount={searchResults.length}
+            endReached={() => loadMoreSearchResults()}
             itemContent={(index) => {
               const line = searchResults[index];
+              // 上下文行（hit 为 false）淡化显示，各组之间以 -- 分隔，同 grep
+              const isContext = line.hit === false;
               return (
-                <div 
-                  onClick={() => handleResultClick(line)}
-                  className="px-4 py-1.5 border-b border-gray-800/50 hover:bg-blue-600/10 cursor-pointer group flex items-start font-mono transition-colors"
-                  style={{ fontSize: `${fontSize}px` }}
-                >
-                  <span className="text-gray-500 mr-4 shrink-0 w-12 text-right group-hover:text-blue-400 transition-colors" style={{ fontSize: `${Math.max(10, fontSize - 2)}px` }}>
-                    {line.lineNumber}
-                  </span>
-                  <span className="text-gray-300 whitespace-pre-wrap break-all leading-relaxed">
-                    {highlightMatch(line.content, searchQuery)}
-                  </span>
+                <div>
+                  {line.groupStart && (
+                    <div className="px-4 text-gray-600 font-mono text-[10px] border-b border-gray-800/50 select-none">--</div>
+                  )}
+                  <div 
+                    onClick={() => handleResultClick(line)}
+                    className={`px-4 ${isContext ? 'py-0.5 opacity-50' : 'py-1.5'} border-b border-gray-800/50 hover:bg-blue-600/10 cursor-pointer group flex items-start font-mono transition-colors`}
+                    style={{ fontSize: `${fontSize}px` }}
+                  >
+                    <span className="text-gray-500 mr-4 shrink-0 w-12 text-right group-hover:text-blue-400 transition-colors" style={{ fontSize: `${Math.max(10, fontSize - 2)}px` }}>
+                      {line.lineNumber}{line.hit === undefined ? '' : isContext ? '-' : ':'}
+                    </span>
+                    <span className="text-gray-300 whitespace-pre-wrap break-all leading-relaxed">
+                      {isContext ? line.content : highlightMatch(line)}
+                    </span>
+                  </div>
                 </div>
               );
             }}
@@ -44,7 +44,7 @@ export default function SessionList() {
 
       // 调用后端保存
       await invoke('save_sessions', {
-        sourcePath: currentFile.path,
+        handle: currentFile.handle,
         targetPath: savePath,
         ranges: ranges
       });
@@ -1,5 +1,5 @@
-import { useState } from 'react';
-import { useLogStore } from '../store';
+import { useState, useEffect } from 'react';
+import { useLogStore, LevelRuns, RegexPreview, describeError, toCommandError } from '../store';
 import { invoke } from '@tauri-apps/api/core';
 
 export default function SessionSplitterManager() {
@@ -20,8 +20,26 @@ export default function SessionSplitterManager() {
   const [isApplying, setIsApplying] = useState(false);
   const [isExpanded, setIsExpanded] = useState(false);
 
+  const [preview, setPreview] = useState<RegexPreview | null>(null);
+
   const currentFile = files.find(f => f.id === currentFileId);
 
+  // 正则模式下输入停顿后校验，并预览在当前文件中的命中
+  useEffect(() => {
+    if (!isRegexMode || !newRegex) {
+      setPreview(null);
+      return;
+    }
+    const timer = setTimeout(() => {
+      invoke<RegexPreview>('validate_regex', {
+        handle: currentFile?.handle ?? null,
+        pattern: newRegex,
+        limit: 3
+      }).then(setPreview).catch(() => setPreview(null));
+    }, 300);
+    return () => clearTimeout(timer);
+  }, [isRegexMode, newRegex, currentFile?.handle]);
+
   // 辅助函数：转义正则特殊字符
   const escapeRegex = (string: string) => {
     return string.replace(/[.*+?^${}()|[\]\\]/g, '\\$&');
@@ -61,9 +79,9 @@ export default function SessionSplitterManager() {
           boot_marker: string;
         }>;
         line_count: number;
-        levels: (string|null)[];
+        levels: LevelRuns;
       }>('parse_log_with_custom_splitters', {
-        path: currentFile.path,
+        handle: currentFile.handle,
         splitterRegexes: activeSessionMode === 'boot' 
           ? [useLogStore.getState().bootMarkerRegex] 
           : enabledRegexes,
@@ -83,7 +101,7 @@ export default function SessionSplitterManager() {
       });
     } catch (error) {
       console.error('Failed to apply splitters:', error);
-      alert('应用失败: ' + error);
+      alert('应用失败: ' + describeError(toCommandError(error)));
     } finally {
       setIsApplying(false);
     }
@@ -170,8 +188,28 @@ export default function SessionSplitterManager() {
                   placeholder={isRegexMode ? "正则表达式" : "匹配关键字"}
                   value={newRegex}
                   onChange={(e) => setNewRegex(e.target.value)}
-                  className="w-full px-2 py-1.5 bg-gray-700 text-white rounded border border-gray-600 focus:border-blue-500 outline-none text-xs font-mono"
+                  className={`w-full px-2 py-1.5 bg-gray-700 text-white rounded border outline-none text-xs font-mono ${
+                    preview?.error ? 'border-red-500' : 'border-gray-600 focus:border-blue-500'
+                  }`}
                 />
+                {preview?.error && (
+                  <p className="text-[10px] text-red-400 font-mono break-all">
+                    {preview.error.column !== null && `第 ${preview.error.column} 列: `}{preview.error.message}
+                  </p>
+                )}
+                {preview && !preview.error && currentFile && (
+                  <div className="text-[10px] text-gray-500 space-y-0.5">
+                    {preview.samples.length === 0 ? (
+                      <p>当前文件中没有匹配的行</p>
+                    ) : (
+                      preview.samples.map(sample => (
+                        <p key={sample.line.line_number} className="font-mono truncate" title={sample.line.content}>
+                          <span className="text-gray-600 mr-1">{sample.line.line_number}</span>{sample.line.content}
+                        </p>
+                      ))
+                    )}
+                  </div>
+                )}
                 <div className="flex items-center justify-between">
                   <label className="flex items-center space-x-1.5 cursor-pointer">
                     <input
@@ -184,7 +222,7 @@ export default function SessionSplitterManager() {
                   </label>
                   <button
                     onClick={handleAdd}
-                    disabled={!newName || !newRegex}
+                    disabled={!newName || !newRegex || !!preview?.error}
                     className="px-4 py-1.5 bg-blue-600 hover:bg-blue-500 disabled:bg-gray-700 text-white rounded text-xs font-medium transition-colors"
                   >
                     添加
@@ -3,11 +3,21 @@ import { invoke } from '@tauri-apps/api/core';
 
 export interface LogFile {
   id: string;
+  handle: number; // 后端文档句柄 (parse_log_file 返回)
   name: string;
   path: string;
   size: number;
   lines: number;
   sessions: number;
+  archiveMember?: boolean; // 来自 zip/tar 压缩包的成员，path 形如 archive!member
+  rotatedSet?: boolean; // 轮转日志组，path 为目录或通配符
+  encoding?: string; // 实际使用的编码（后端识别结果）
+  encodingOverride?: string; // 手动指定的编码，重新加载时沿用
+}
+
+export interface ArchiveMember {
+  name: string;
+  size: number;
 }
 
 export interface LogSession {
@@ -29,11 +39,149 @@ export interface SessionSplitter {
   color?: string; // 用于UI显示
 }
 
+export interface LogAppendedEvent {
+  handle: number;
+  start_line: number;
+  end_line: number;
+  line_count: number;
+  levels: LevelRuns;
+  reset: boolean;
+}
+
+// 跟随轮询失败时的事件 (`tail-error`)
+export interface TailErrorEvent {
+  handle: number;
+  message: string;
+}
+
+// 后端发送的级别编码：runs 为扁平的 [代码, 行数, ...]，代码 n 对应 table[n - 1]，0 表示无级别
+export interface LevelRuns {
+  table: string[];
+  runs: number[];
+}
+
+export interface DecompressProgress {
+  path: string;
+  bytes_read: number;
+  total_bytes: number;
+  bytes_written: number;
+  done: boolean;
+}
+
+export interface JobProgress {
+  job_id: number;
+  kind: string;
+  phase: string;
+  done: number;
+  total: number;
+  finished: boolean;
+  cancelled: boolean;
+}
+
+// 后端结果集摘要：命中行保存在后端，前端按 id 分页读取
+export interface ResultSetInfo {
+  id: number;
+  handle: number;
+  count: number; // 结果中的行数（含上下文行）
+  hit_count?: number; // 带上下文时其中命中行的数量
+}
+
+// 命中前后带出的上下文（类似 grep -B/-A）；脱水模式还可以按时间窗口、只取同线程的行
+export interface ContextOptions {
+  before: number;
+  after: number;
+  time_window_ms?: number | null; // 命中行前后 N 毫秒内的行，取代 before/after
+  same_thread?: boolean; // 只取与命中行同一线程/进程的行
+  timestamp_regex?: string;
+  sessions?: [number, number][]; // 上下文不跨越的会话边界
+}
+
+// 由起止标记正则界定的范围（如 START_STREAM … STOP_STREAM），过滤与搜索只在这些范围内进行
+export interface MarkerScope {
+  start: string;
+  end: string;
+  nested?: boolean; // 起始标记可以嵌套，回到最外层的结束标记才闭合
+  include_markers?: boolean; // 范围包含起止标记所在的行，缺省为 true
+  unterminated?: 'to_end' | 'drop'; // 没有结束标记的块延伸到文件末尾或丢弃
+}
+
+// 时间戳设置：缺省自动识别格式，按 UTC 解释，不带年份时按文件修改时间推断年份
+export interface TimestampOptions {
+  format?: string | null; // epoch_s / epoch_ms / epoch_us / epoch_ns，或 chrono 格式串（如 %d/%m/%Y %H:%M:%S）
+  timezone?: string | null; // "UTC"、"local" 或 "+08:00"
+  year?: number | null; // syslog、logcat 等不带年份的格式补的年份
+}
+
+// 时间范围（闭区间），from/to 可只给一个；可写完整日期时间、纯数字或只写时分秒
+export interface TimeRange {
+  from?: string | null;
+  to?: string | null;
+  timestamp_regex?: string;
+}
+
 export interface LogLine {
   lineNumber: number;
   content: string;
   level?: string;
   timestamp?: string;
+  spans?: MatchSpan[]; // 后端按匹配规则算出的命中区间
+  hit?: boolean; // 带上下文的结果中：命中行为 true，上下文行为 false
+  groupStart?: boolean; // 带上下文的结果中开始新的一组
+}
+
+// 命中区间：rule 为请求中规则的下标，byte_* / char_* 为左闭右开的字节与字符偏移
+export interface MatchSpan {
+  rule: number;
+  byte_start: number;
+  byte_end: number;
+  char_start: number;
+  char_end: number;
+}
+
+// 搜索、定位与过滤关键字共用的匹配选项
+export interface SearchOptions {
+  case_sensitive: boolean;
+  whole_word: boolean;
+  invert_match: boolean; // 选出不匹配的行
+  max_matches_per_session: number | null; // 每个会话最多保留的命中行数，null 表示不限
+}
+
+const DEFAULT_SEARCH_OPTIONS: SearchOptions = {
+  case_sensitive: false,
+  whole_word: false,
+  invert_match: false,
+  max_matches_per_session: null
+};
+
+// 请求命中区间时传给后端的规则
+export interface SpanRule {
+  kind: 'search' | 'keyword' | 'refinement' | 'highlight';
+  pattern: string;
+  is_regex?: boolean;
+  options?: SearchOptions;
+}
+
+// 命令返回的错误：模式错误带出错的规则、模式与位置（从 1 开始的字符列），其余为文本
+export type CommandError =
+  | { kind: 'pattern'; rule: string; pattern: string; column: number | null; message: string }
+  | { kind: 'other'; message: string };
+
+// 统一成 CommandError：只返回文本错误的命令包装为 other
+export const toCommandError = (err: unknown): CommandError => {
+  if (typeof err === 'object' && err !== null && 'kind' in err) return err as CommandError;
+  return { kind: 'other', message: String(err) };
+};
+
+export const describeError = (err: CommandError): string => err.kind === 'pattern'
+  ? `Invalid ${err.rule} \`${err.pattern}\`${err.column !== null ? ` at column ${err.column}` : ''}: ${err.message}`
+  : err.message;
+
+// validate_regex 的结果：无效时带出错位置（从 1 开始的字符列），有效时附带当前文件中的示例命中
+export interface RegexPreview {
+  error: { column: number | null; message: string } | null;
+  samples: Array<{ line: { line_number: number; content: string; spans?: MatchSpan[] }; captures: Array<string | null> }>;
+  scanned_lines: number;
+  line_count: number;
 }
 
 export interface LogProfile {
@@ -87,22 +235,26 @@ interface LogViewState {
   activeSessionMode: 'boot' | 'custom';
   
   // 核心数据优化：不再存储几十万个对象
-  lineLevels: (string | null)[];
+  lineLevels: Uint8Array; // 每行的级别代码，含义见 levelTable
+  levelTable: string[];
   lineCount: number;
   // 缓存已拉取内容的文件行 (只有可见区域才会有 content)
   lineContents: Map<number, string>;
   
-  filteredIndices: number[]; // 存储过滤后的行号索引（0-based）
+  filterResult: ResultSetInfo | null; // 过滤结果集（行号保存在后端）
+  filterError: CommandError | null; // 过滤条件的错误（如查询语法错误及其位置）
   
   profiles: LogProfile[];
   activeProfileId: string;
   bootMarkerRegex: string;
   logLevelRegex: string;
   timestampRegex: string;
+  timestampOptions: TimestampOptions; // 打开文件时传给后端
   timeGapThreshold: number; // 新增：时间间隙阈值（秒），0 表示禁用
   logLevelFilter: string[];
   highlights: LogHighlight[];
-  highlightContextLines: number;
+  highlightContext: ContextOptions; // 脱水模式的上下文设置
+  markerScope: MarkerScope | null; // 标记范围，过滤与搜索只在其中进行
   showOnlyHighlights: boolean;
 
   // 跳转控制
@@ -113,20 +265,34 @@ interface LogViewState {
 
   // 独立查询结果
   searchQuery: string;
-  searchResults: LogLine[];
+  searchResult: ResultSetInfo | null; // 搜索结果集（行号保存在后端，最多保留 searchMaxResults 条）
+  searchTotal: number; // 全部命中数，可能多于结果集保留的条数
+  searchResults: LogLine[]; // 已加载的命中行，滚动到底时按页续取
+  searchMaxResults: number; // 搜索结果最多保留的条数，0 表示不限
   isSearchPanelOpen: boolean;
   searchPanelHeight: number;
   searchOnlySelectedSessions: boolean;
   isSearchRegex: boolean;
+  searchOptions: SearchOptions;
+  searchContext: ContextOptions;
 
   // 字体大小
   fontSize: number;
   isWordWrap: boolean;
+  searchIndexEnabled: boolean; // 打开文件后在后台建立 trigram 搜索索引
+  isTailing: boolean; // 跟随模式：文件追加时自动刷新
+  tailError: string | null; // 最近一次跟随轮询失败的原因
+  decompressProgress: DecompressProgress | null; // 打开压缩日志时的解压进度
+  archiveBrowser: { path: string; members: ArchiveMember[] } | null; // 正在选择成员的压缩包
+  jobs: Record<number, JobProgress>; // 进行中的后台任务
+  searchJobId: number | null; // 当前搜索任务，新的搜索开始时取消它
+  loadJobId: number | null; // 当前文件加载任务
   refinementFilters: string[];
   transientRefinement: string;
 
   // 双分窗模式
-  isDualPane: boolean;  dualPaneSplit: number;  rightFilteredIndices: number[];
+  isDualPane: boolean;  dualPaneSplit: number;  rightFilterResult: ResultSetInfo | null;
+  rightFilterError: CommandError | null;
   rightRefinementFilters: string[];
   rightTransientRefinement: string;
   rightScrollTargetLine: number | null;
@@ -171,7 +337,7 @@ interface LogViewState {
   setSelectedSessions: (ids: number[]) => void;
   
   // 新的高性能加载 Action
-  setParsedLog: (parsed: { sessions: LogSession[], levels: (string|null)[], line_count: number }) => void;
+  setParsedLog: (parsed: { sessions: LogSession[], levels: LevelRuns, line_count: number }) => void;
   
   // 会话分割器管理
   addSessionSplitter: (name: string, regex: string, isRegex: boolean) => void;
@@ -182,6 +348,7 @@ interface LogViewState {
   applySessionSplitters: () => Promise<void>;
   setTimeGapThreshold: (threshold: number) => void;
   setTimestampRegex: (regex: string) => void;
+  setTimestampOptions: (options: TimestampOptions) => void;
   setBootMarkerRegex: (regex: string) => void;
   setLogLevelRegex: (regex: string) => void;
   setLogLevelFilter: (levels: string[]) => void;
@@ -189,7 +356,8 @@ interface LogViewState {
   removeHighlight: (id: string) => void;
   toggleHighlight: (id: string) => void;
   setShowOnlyHighlights: (show: boolean) => void;
-  setHighlightContextLines: (lines: number) => void;
+  setHighlightContext: (context: Partial<ContextOptions>) => void;
+  setMarkerScope: (scope: MarkerScope | null) => void;
   addProfile: (profile: LogProfile) => void;
   updateProfile: (profile: LogProfile) => void;
   deleteProfile: (id: string) => void;
@@ -206,10 +374,14 @@ interface LogViewState {
   // 独立查询 Actions
   setSearchQuery: (query: string) => void;
   performSearch: () => void;
+  loadMoreSearchResults: () => Promise<void>;
+  setSearchMaxResults: (max: number) => void;
   setSearchPanelOpen: (open: boolean) => void;
   setSearchPanelHeight: (height: number) => void;
   setSearchOnlySelectedSessions: (only: boolean) => void;
   setSearchRegex: (isRegex: boolean) => void;
+  setSearchOptions: (options: Partial<SearchOptions>) => void;
+  setSearchContext: (context: ContextOptions) => void;
   addRefinementFilter: (term: string) => void;
   removeRefinementFilter: (index: number) => void;
   setRefinementFilters: (filters: string[]) => void;
@@ -227,6 +399,11 @@ interface LogViewState {
 
   setFontSize: (size: number | ((prev: number) => number)) => void;
   setWordWrap: (wrap: boolean) => void;
+  setSearchIndexEnabled: (enabled: boolean) => void;
+  setTailing: (on: boolean) => Promise<void>;
+  applyLogAppended: (event: LogAppendedEvent) => void;
+  applyTailError: (event: TailErrorEvent) => void;
+  applyJobProgress: (progress: JobProgress) => void;
 
   setAiMessages: (messages: Array<{ 
     role: 'user' | 'assistant' | 'system' | 'tool', 
@@ -277,19 +454,29 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   selectedSessionIds: [],
   sessionSplitters: JSON.parse(localStorage.getItem('session_splitters') || '[]'),
   activeSessionMode: (localStorage.getItem('active_session_mode') as 'boot' | 'custom') || 'boot',
-  lineLevels: [],
+  lineLevels: new Uint8Array(0),
+  levelTable: [],
   lineCount: 0,
   lineContents: new Map(),
-  filteredIndices: [],
+  filterResult: null,
+  filterError: null,
   profiles: JSON.parse(localStorage.getItem('log_profiles') || JSON.stringify(DEFAULT_PROFILES)),
   activeProfileId: localStorage.getItem('active_profile_id') || 'default',
   bootMarkerRegex: localStorage.getItem('boot_marker_regex') || '',
   logLevelRegex: localStorage.getItem('log_level_regex') || '',
   timestampRegex: localStorage.getItem('timestamp_regex') || '\\[(.*?)\\]', 
+  timestampOptions: JSON.parse(localStorage.getItem('timestamp_options') || '{}'),
   timeGapThreshold: Number(localStorage.getItem('time_gap_threshold')) || 0,
   logLevelFilter: JSON.parse(localStorage.getItem('log_level_filter') || '["DEBUG", "INFO", "NORM", "WARN", "ERROR", "FATAL", "TRACE", "SUCCESS"]'),
   highlights: JSON.parse(localStorage.getItem('highlights') || '[]'),
-  highlightContextLines: Number(localStorage.getItem('highlight_context_lines')) || 0,
+  highlightContext: JSON.parse(localStorage.getItem('highlight_context') || 'null') ?? {
+    // 旧版本只保存了对称的行数
+    before: Number(localStorage.getItem('highlight_context_lines')) || 0,
+    after: Number(localStorage.getItem('highlight_context_lines')) || 0,
+    time_window_ms: null,
+    same_thread: false
+  },
+  markerScope: JSON.parse(localStorage.getItem('marker_scope') || 'null'),
   showOnlyHighlights: localStorage.getItem('show_only_highlights') === 'true',
   metrics: JSON.parse(localStorage.getItem('metrics') || '[]'),
   scrollTargetLine: null,
@@ -297,20 +484,34 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   currentVisibleLine: 1,
   activeView: 'log',
   searchQuery: '',
+  searchResult: null,
+  searchTotal: 0,
   searchResults: [],
+  searchMaxResults: Number(localStorage.getItem('search_max_results') ?? 100000),
   isSearchPanelOpen: false,
   searchPanelHeight: Number(localStorage.getItem('search_panel_height')) || 256,
   searchOnlySelectedSessions: false,
   isSearchRegex: false,
+  searchOptions: { ...DEFAULT_SEARCH_OPTIONS, ...JSON.parse(localStorage.getItem('search_options') || '{}') },
+  searchContext: { before: 0, after: 0, ...JSON.parse(localStorage.getItem('search_context') || '{}') },
   fontSize: Number(localStorage.getItem('font_size')) || 12,
   isWordWrap: localStorage.getItem('is_word_wrap') !== 'false', // 默认开启
+  searchIndexEnabled: localStorage.getItem('search_index_enabled') === 'true',
+  isTailing: false,
+  tailError: null,
+  decompressProgress: null,
+  archiveBrowser: null,
+  jobs: {},
+  searchJobId: null,
+  loadJobId: null,
   refinementFilters: [],
   transientRefinement: '',
 
   // 双分窗模式初始状态
   isDualPane: localStorage.getItem('is_dual_pane') === 'true',
   dualPaneSplit: Number(localStorage.getItem('dual_pane_split')) || 50,
-  rightFilteredIndices: [],
+  rightFilterResult: null,
+  rightFilterError: null,
   rightRefinementFilters: [],
   rightTransientRefinement: '',
   rightScrollTargetLine: null,
@@ -350,13 +551,17 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   
   removeFile: (id) => set((state) => {
     const isCurrent = state.currentFileId === id;
+    const removed = state.files.find(f => f.id === id);
+    if (removed) {
+      invoke('close_log_file', { handle: removed.handle }).catch(e => console.error('Failed to close file:', e));
+    }
     return {
       files: state.files.filter(f => f.id !== id),
       currentFileId: isCurrent ? null : state.currentFileId,
-      lineLevels: isCurrent ? [] : state.lineLevels,
+      lineLevels: isCurrent ? new Uint8Array(0) : state.lineLevels,
       lineCount: isCurrent ? 0 : state.lineCount,
       lineContents: isCurrent ? new Map() : state.lineContents,
-      filteredIndices: isCurrent ? [] : state.filteredIndices,
+      filterResult: isCurrent ? null : state.filterResult,
       refinementFilters: isCurrent ? [] : state.refinementFilters,
       transientRefinement: isCurrent ? '' : state.transientRefinement,
       sessions: isCurrent ? [] : state.sessions,
@@ -371,24 +576,38 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     };
   }),
   
-  setCurrentFile: (id) => set((state) => ({ 
-    currentFileId: id,
-    lineLevels: [],
-    lineCount: 0,
-    lineContents: new Map(),
-    filteredIndices: [],
-    sessions: [],
-    selectedSessionIds: [],
-    refinementFilters: [],
-    transientRefinement: '',
-    metrics: state.metrics.map(m => ({ ...m, data: [] })),
-    scrollTargetLine: null,
-    analysisStats: [],
-    analysisTimeGaps: [],
-    analysisWorkflows: [],
-    hasAnalyzedStats: false,
-    hasAnalyzedWorkflows: false
-  })),
+  setCurrentFile: (id) => set((state) => {
+    // 切换文件时停止上一个文件的跟随
+    if (state.isTailing && id !== state.currentFileId) {
+      const prev = state.files.find(f => f.id === state.currentFileId);
+      if (prev) invoke('stop_tail', { handle: prev.handle }).catch(e => console.error('Failed to stop tail:', e));
+    }
+    releaseResult(state.filterResult);
+    releaseResult(state.rightFilterResult);
+    return {
+      currentFileId: id,
+      isTailing: id === state.currentFileId ? state.isTailing : false,
+      tailError: id === state.currentFileId ? state.tailError : null,
+      lineLevels: new Uint8Array(0),
+      lineCount: 0,
+      lineContents: new Map(),
+      filterResult: null,
+      filterError: null,
+      rightFilterResult: null,
+      rightFilterError: null,
+      sessions: [],
+      selectedSessionIds: [],
+      refinementFilters: [],
+      transientRefinement: '',
+      metrics: state.metrics.map(m => ({ ...m, data: [] })),
+      scrollTargetLine: null,
+      analysisStats: [],
+      analysisTimeGaps: [],
+      analysisWorkflows: [],
+      hasAnalyzedStats: false,
+      hasAnalyzedWorkflows: false
+    };
+  }),
   setSessions: (sessions) => set({ sessions }),
   setSelectedSessions: (ids) => {
     set({ selectedSessionIds: ids });
@@ -402,7 +621,8 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   setParsedLog: (parsed) => {
     set({ 
       sessions: parsed.sessions, 
-      lineLevels: parsed.levels, 
+      lineLevels: decodeLevelRuns(parsed.levels.runs, parsed.line_count),
+      levelTable: parsed.levels.table,
       lineCount: parsed.line_count,
       lineContents: new Map()
     });
@@ -418,6 +638,10 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     localStorage.setItem('timestamp_regex', regex);
     set({ timestampRegex: regex });
   },
+  setTimestampOptions: (options) => {
+    localStorage.setItem('timestamp_options', JSON.stringify(options));
+    set({ timestampOptions: options });
+  },
   setBootMarkerRegex: (regex) => {
     localStorage.setItem('boot_marker_regex', regex);
     set({ bootMarkerRegex: regex });
@@ -486,12 +710,19 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     get().filterLogLines();
     if (get().isDualPane) get().filterRightLogLines();
   },
-  setHighlightContextLines: (lines) => {
-    localStorage.setItem('highlight_context_lines', lines.toString());
-    set({ highlightContextLines: lines });
+  setHighlightContext: (context) => {
+    const highlightContext = { ...get().highlightContext, ...context };
+    localStorage.setItem('highlight_context', JSON.stringify(highlightContext));
+    set({ highlightContext });
     get().filterLogLines();
     if (get().isDualPane) get().filterRightLogLines();
   },
+  setMarkerScope: (scope) => {
+    localStorage.setItem('marker_scope', JSON.stringify(scope));
+    set({ markerScope: scope });
+    get().filterLogLines();
+    get().performSearch();
+  },
 
   addProfile: (profile) => set((state) => {
     const newProfiles = [...state.profiles, profile];
@@ -538,39 +769,108 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   
   setSearchQuery: (query) => set({ searchQuery: query }),
   performSearch: async () => {
-    const { searchQuery, isSearchRegex, searchOnlySelectedSessions, selectedSessionIds, sessions } = get();
+    const { searchQuery, isSearchRegex, searchOptions, searchOnlySelectedSessions, selectedSessionIds, sessions } = get();
     if (!searchQuery.trim()) {
-      set({ searchResults: [], isSearchPanelOpen: false });
+      releaseResult(get().searchResult);
+      set({ searchResult: null, searchTotal: 0, searchResults: [], isSearchPanelOpen: false });
       return;
     }
     
+    // 新的搜索开始时取消尚未完成的上一次搜索
+    const previousJob = get().searchJobId;
+    if (previousJob !== null) {
+      cancelJob(previousJob);
+    }
+    const jobId = await createJob();
+    set({ searchJobId: jobId });
+
     try {
-      let lineRanges = null;
+      let lineRanges: [number, number][] | null = null;
       if (searchOnlySelectedSessions && selectedSessionIds.length > 0) {
         lineRanges = selectedSessionIds.map(id => {
           const s = sessions.find(sess => sess.id === id);
           return s ? [s.startLine, s.endLine] : null;
-        }).filter(Boolean);
+        }).filter((r): r is [number, number] => r !== null);
       }
 
-      const results = await invoke<any[]>('search_log', {
+      // 后端返回总数与首页，结果集保留在后端，其余页滚动时再取
+      const page = await invoke<{ result: ResultSetInfo; total: number; lines: any[] }>('search_log', {
+        handle: getActiveHandle(),
         query: searchQuery,
         isRegex: isSearchRegex,
-        lineRanges
+        lineRanges: withSessionBounds(lineRanges, sessions, searchOptions),
+        scope: activeScope(get().markerScope),
+        // 仅限选中会话时同样沿用左侧视图的时间范围
+        timeRange: searchOnlySelectedSessions ? timeRangeOf(get().refinementFilters, get().timestampRegex) : null,
+        options: searchOptions,
+        context: get().searchContext,
+        pageSize: SEARCH_PAGE_SIZE,
+        maxResults: get().searchMaxResults || null,
+        spanRules: searchSpanRules(searchQuery, isSearchRegex, searchOptions),
+        jobId
       });
+      if (get().searchJobId !== jobId) {
+        releaseResult(page.result);
+        return;
+      }
       
+      releaseResult(get().searchResult);
       set({ 
-        searchResults: results.map(l => ({
+        searchResult: page.result,
+        searchTotal: page.total,
+        searchResults: page.lines.map(l => ({
           lineNumber: l.line_number,
           content: l.content,
-          level: l.level as any
+          level: l.level as any,
+          spans: l.spans,
+          hit: l.hit,
+          groupStart: l.group_start
         })), 
         isSearchPanelOpen: true 
       });
     } catch (e) {
-      console.error('Search failed:', e);
+      if (!isCancelledError(e)) {
+        console.error('Search failed:', e);
+      }
+    } finally {
+      if (get().searchJobId === jobId) {
+        set({ searchJobId: null });
+      }
     }
   },
+  loadMoreSearchResults: async () => {
+    const { searchResult, searchResults, searchQuery, isSearchRegex, searchOptions } = get();
+    if (!searchResult || searchResults.length >= searchResult.count || loadingSearchPage) return;
+    loadingSearchPage = true;
+    try {
+      const lines = await invoke<any[]>('get_result_lines', {
+        resultId: searchResult.id,
+        offset: searchResults.length,
+        limit: SEARCH_PAGE_SIZE,
+        spanRules: searchSpanRules(searchQuery, isSearchRegex, searchOptions)
+      });
+      // 等待期间可能已开始新的搜索
+      if (get().searchResult?.id !== searchResult.id) return;
+      set(state => ({
+        searchResults: [...state.searchResults, ...lines.map(l => ({
+          lineNumber: l.line_number,
+          content: l.content.replace(/\r?\n$/, ''),
+          level: l.level as any,
+          spans: l.spans,
+          hit: l.hit,
+          groupStart: l.group_start
+        }))]
+      }));
+    } catch (e) {
+      console.error('Failed to load search results:', e);
+    } finally {
+      loadingSearchPage = false;
+    }
+  },
+  setSearchMaxResults: (max) => {
+    localStorage.setItem('search_max_results', max.toString());
+    set({ searchMaxResults: max });
+  },
   setSearchPanelOpen: (open) => set({ isSearchPanelOpen: open }),
   setSearchPanelHeight: (height) => {
     localStorage.setItem('search_panel_height', height.toString());
@@ -584,6 +884,18 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     set({ isSearchRegex: isRegex });
     get().performSearch();
   },
+  setSearchOptions: (options) => {
+    const searchOptions = { ...get().searchOptions, ...options };
+    localStorage.setItem('search_options', JSON.stringify(searchOptions));
+    set({ searchOptions });
+    get().performSearch();
+    get().filterLogLines();
+  },
+  setSearchContext: (context) => {
+    localStorage.setItem('search_context', JSON.stringify(context));
+    set({ searchContext: context });
+    get().performSearch();
+  },
   addRefinementFilter: (term) => {
     if (!term.trim()) return;
     set(state => ({ refinementFilters: [...state.refinementFilters, term.trim()] }));
@@ -634,7 +946,7 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   filterRightLogLines: async () => {
     const { 
       logLevelFilter, selectedSessionIds, sessions, 
-      rightRefinementFilters, rightTransientRefinement, lineCount
+      rightRefinementFilters, rightTransientRefinement
     } = get();
     
     let lineRanges: [number, number][] | null = null;
@@ -646,24 +958,21 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     }
 
     try {
-      const indices = await invoke<number[]>('get_filtered_indices', {
+      const result = await invoke<ResultSetInfo>('get_filtered_indices', {
+        handle: getActiveHandle(),
         logLevels: logLevelFilter,
         lineRanges: lineRanges,
         highlights: [], 
         contextLines: 0,
-        refinements: [...rightRefinementFilters, rightTransientRefinement].filter(r => r.trim() !== '')
+        timeRange: timeRangeOf(rightRefinementFilters, get().timestampRegex),
+        refinements: [...rightRefinementFilters, rightTransientRefinement].filter(r => r.trim() !== '' && !isTimeRefinement(r))
       });
-      set({ rightFilteredIndices: indices });
+      releaseResult(get().rightFilterResult);
+      set({ rightFilterResult: result, rightFilterError: null });
     } catch (err) {
-      const simpleIndices = [];
-      if (lineRanges) {
-        for (const [start, end] of lineRanges) {
-          for (let i = start - 1; i < end; i++) simpleIndices.push(i);
-        }
-      } else {
-        for (let i = 0; i < lineCount; i++) simpleIndices.push(i);
-      }
-      set({ rightFilteredIndices: simpleIndices });
+      console.error('Failed to filter log lines:', err);
+      releaseResult(get().rightFilterResult);
+      set({ rightFilterResult: null, rightFilterError: toCommandError(err) });
     }
   },
 
@@ -679,6 +988,76 @@ export const useLogStore = create<LogViewState>((set, get) => ({
     set({ isWordWrap: wrap });
   },
 
+  setSearchIndexEnabled: (enabled) => {
+    localStorage.setItem('search_index_enabled', enabled.toString());
+    set({ searchIndexEnabled: enabled });
+  },
+
+  setTailing: async (on) => {
+    const handle = getActiveHandle();
+    if (handle === null) return;
+    try {
+      await invoke(on ? 'start_tail' : 'stop_tail', { handle });
+      set({ isTailing: on, tailError: null });
+    } catch (e) {
+      console.error('Failed to toggle tail mode:', e);
+    }
+  },
+
+  applyLogAppended: (event) => {
+    const state = get();
+    if (event.handle !== getActiveHandle()) return;
+
+    if (event.reset) {
+      // 文件被截断或轮转：后端已在原句柄上重建索引，只需重新拉取会话与级别
+      set({
+        tailError: null,
+        files: state.files.map(f => f.handle === event.handle ? { ...f, lines: event.line_count } : f)
+      });
+      import('./utils/logLoader').then(({ loadDocumentContent }) => loadDocumentContent(event.handle))
+        .catch(e => console.error('Failed to reload tailed file:', e));
+      return;
+    }
+
+    // 末行可能被续写，从 start_line 起覆盖级别并清除对应的内容缓存
+    const firstIdx = event.start_line - 1;
+    const lineLevels = new Uint8Array(event.line_count);
+    lineLevels.set(state.lineLevels.subarray(0, firstIdx));
+    lineLevels.set(decodeLevelRuns(event.levels.runs, event.line_count - firstIdx), firstIdx);
+    const lineContents = new Map(state.lineContents);
+    lineContents.delete(event.start_line);
+    const sessions = state.sessions.length > 0
+      ? state.sessions.map((s, i) => i === state.sessions.length - 1 ? { ...s, endLine: event.line_count } : s)
+      : state.sessions;
+
+    set({
+      lineLevels,
+      levelTable: event.levels.table,
+      lineContents,
+      sessions,
+      lineCount: event.line_count,
+      tailError: null,
+      files: state.files.map(f => f.handle === event.handle ? { ...f, lines: event.line_count } : f)
+    });
+    get().filterLogLines();
+    if (get().isDualPane) get().filterRightLogLines();
+  },
+
+  applyTailError: (event) => {
+    if (event.handle !== getActiveHandle()) return;
+    set({ tailError: event.message });
+  },
+
+  applyJobProgress: (progress) => set((state) => {
+    const jobs = { ...state.jobs };
+    if (progress.finished) {
+      delete jobs[progress.job_id];
+    } else {
+      jobs[progress.job_id] = progress;
+    }
+    return { jobs };
+  }),
+
   setAiMessages: (messages) => set({ aiMessages: messages }),
   addAiMessage: (message) => set((state) => ({ aiMessages: [...state.aiMessages, message] })),
   setAiLoading: (loading) => set({ isAiLoading: loading }),
@@ -862,7 +1241,7 @@ export const useLogStore = create<LogViewState>((set, get) => ({
   filterLogLines: async () => {
     const { 
       logLevelFilter, selectedSessionIds, sessions, 
-      highlights, showOnlyHighlights, highlightContextLines, lineCount
+      highlights, showOnlyHighlights, highlightContext, timestampRegex
     } = get();
     
     let lineRanges: [number, number][] | null = null;
@@ -879,32 +1258,110 @@ export const useLogStore = create<LogViewState>((set, get) => ({
         : [];
 
       if (showOnlyHighlights && activeHighlights.length === 0) {
-        set({ filteredIndices: [] });
+        releaseResult(get().filterResult);
+        set({ filterResult: null });
         return;
       }
 
-      const indices = await invoke<number[]>('get_filtered_indices', {
+      const result = await invoke<ResultSetInfo>('get_filtered_indices', {
+        handle: getActiveHandle(),
         logLevels: logLevelFilter,
-        lineRanges: lineRanges,
+        lineRanges: withSessionBounds(lineRanges, sessions, get().searchOptions),
+        scope: activeScope(get().markerScope),
         highlights: activeHighlights,
-        contextLines: showOnlyHighlights ? highlightContextLines : 0,
-        refinements: [...get().refinementFilters, get().transientRefinement].filter(r => r.trim() !== '')
+        contextLines: 0,
+        // 上下文不跨越会话边界；按时间窗口时使用当前配置的时间戳正则
+        context: showOnlyHighlights ? {
+          ...highlightContext,
+          timestamp_regex: timestampRegex,
+          sessions: sessions.map(s => [s.startLine, s.endLine])
+        } : null,
+        timeRange: timeRangeOf(get().refinementFilters, timestampRegex),
+        refinements: [...get().refinementFilters, get().transientRefinement].filter(r => r.trim() !== '' && !isTimeRefinement(r)),
+        options: get().searchOptions
       });
-      set({ filteredIndices: indices });
+      releaseResult(get().filterResult);
+      set({ filterResult: result, filterError: null });
     } catch (err) {
-      const simpleIndices = [];
-      if (lineRanges) {
-        for (const [start, end] of lineRanges) {
-          for (let i = start - 1; i < end; i++) simpleIndices.push(i);
-        }
-      } else {
-        for (let i = 0; i < lineCount; i++) simpleIndices.push(i);
-      }
-      set({ filteredIndices: simpleIndices });
+      console.error('Failed to filter log lines:', err);
+      releaseResult(get().filterResult);
+      set({ filterResult: null, filterError: toCommandError(err) });
     }
   },
 }));
 
+// 展开游程编码的级别代码
+export const decodeLevelRuns = (runs: number[], length: number): Uint8Array => {
+  const codes = new Uint8Array(length);
+  let pos = 0;
+  for (let i = 0; i + 1 < runs.length && pos < length; i += 2) {
+    codes.fill(runs[i], pos, Math.min(length, pos + runs[i + 1]));
+    pos += runs[i + 1];
+  }
+  return codes;
+};
+
+// 行的级别名，无级别时为 null
+export const levelName = (codes: Uint8Array, table: string[], idx: number): string | null => {
+  const code = codes[idx];
+  return code ? table[code - 1] ?? null : null;
+};
+
+/**
+ * 当前活动文件对应的后端文档句柄，未打开文件时为 null
+ */
+export const getActiveHandle = (): number | null => {
+  const { files, currentFileId } = useLogStore.getState();
+  return files.find(f => f.id === currentFileId)?.handle ?? null;
+};
+
+// 结果集由后端持有，替换或不再使用时释放
+// 搜索结果每页条数
+const SEARCH_PAGE_SIZE = 200;
+// 正在续取搜索结果的下一页，避免滚动到底时重复请求
+let loadingSearchPage = false;
+
+// 搜索结果的高亮规则：与后端搜索的匹配语义一致
+const searchSpanRules = (query: string, isRegex: boolean, options: SearchOptions): SpanRule[] => [
+  { kind: 'search', pattern: query, is_regex: isRegex, options }
+];
+
+// 设置了每会话命中上限而没有限定范围时，传入全部会话的范围作为会话边界
+const withSessionBounds = (
+  lineRanges: [number, number][] | null,
+  sessions: LogSession[],
+  options: SearchOptions
+): [number, number][] | null => {
+  if (lineRanges || options.max_matches_per_session === null || sessions.length === 0) return lineRanges;
+  return sessions.map(s => [s.startLine, s.endLine]);
+};
+
+// 时间模式的面包屑 "@from~to" 作为时间范围传给后端，不参与文本匹配
+const isTimeRefinement = (r: string) => r.startsWith('@');
+
+const timeRangeOf = (refinements: string[], timestampRegex: string): TimeRange | null => {
+  const last = [...refinements].reverse().find(isTimeRefinement);
+  if (!last) return null;
+  const [from, to] = last.substring(1).split('~').map(t => t.trim() || null);
+  return { from, to: to ?? null, timestamp_regex: timestampRegex };
+};
+
+// 起止标记都填写了才生效
+const activeScope = (scope: MarkerScope | null): MarkerScope | null =>
+  scope && scope.start.trim() && scope.end.trim() ? scope : null;
+
+export const releaseResult = (result: ResultSetInfo | null) => {
+  if (result) invoke('release_result', { resultId: result.id }).catch(() => {});
+};
+
+// 后台任务：先分配任务号再随命令传入，进度通过 job-progress 事件推送
+export const createJob = () => invoke<number>('create_job');
+
+export const cancelJob = (jobId: number) => invoke<boolean>('cancel_job', { jobId }).catch(() => false);
+
+// 被取消的任务以 "Job N cancelled" 错误返回
+export const isCancelledError = (error: unknown) => /^Job \d+ cancelled$/.test(String(error));
+
 const initialState = useLogStore.getState();
 if (!localStorage.getItem('boot_marker_regex') && initialState.profiles.length > 0) {
   const activeProfile = initialState.profiles.find(p => p.id === initialState.activeProfileId) || initialState.profiles[0];
@@ -14,26 +14,24 @@ export interface LogSample {
  */
 export const collectAiContext = async (): Promise<LogSample> => {
   const store = useLogStore.getState();
-  const indices = store.filteredIndices;
-  const total = indices.length;
+  const result = store.filterResult;
+  const total = result?.count ?? 0;
   
-  // 采样策略：如果行数多，采样前 150 行 + 后 150 行
-  let targetIndices: number[] = [];
-  if (total <= 400) {
-    targetIndices = indices;
-  } else {
-    // 头部采样
-    const head = indices.slice(0, 200);
-    // 尾部采样
-    const tail = indices.slice(-200);
-    targetIndices = [...head, ...tail];
+  // 采样策略：如果行数多，采样前 200 行 + 后 200 行
+  // 从后端结果集按页获取行内容 (这里不走主列表缓存，确保获取到最新最全的内容)
+  let lines: any[] = [];
+  if (result && total > 0) {
+    if (total <= 400) {
+      lines = await invoke<any[]>('get_result_lines', { resultId: result.id, offset: 0, limit: total });
+    } else {
+      const [head, tail] = await Promise.all([
+        invoke<any[]>('get_result_lines', { resultId: result.id, offset: 0, limit: 200 }),
+        invoke<any[]>('get_result_lines', { resultId: result.id, offset: total - 200, limit: 200 })
+      ]);
+      lines = [...head, ...tail];
+    }
   }
 
-  // 从后端获取行内容 (这里不走主列表缓存，确保获取到最新最全的内容)
-  const lines = await invoke<any[]>('get_log_lines_by_indices', { 
-    indices: targetIndices 
-  });
-
   // 格式化日志
   let contextText = "";
   if (total <= 400) {
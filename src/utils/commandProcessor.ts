@@ -1,4 +1,4 @@
-import { useLogStore } from '../store';
+import { useLogStore, getActiveHandle, releaseResult, ResultSetInfo } from '../store';
 import { invoke } from '@tauri-apps/api/core';
 import { open, save } from '@tauri-apps/plugin-dialog';
 import { loadLogFile } from './logLoader';
@@ -7,7 +7,7 @@ import { collectAiContext } from './aiContextCollector';
 export interface CommandResult {
   success: boolean;
   message?: string;
-  action?: 'export' | 'jump' | 'clear' | 'open' | 'none';
+  action?: 'export' | 'jump' | 'clear' | 'open' | 'filter' | 'none';
 }
 
 const LOG_TOOLS = [
@@ -180,6 +180,7 @@ async function handleToolCalls(tool_calls: any[]): Promise<any[]> {
       const endLine = lineNum + ctx;
       try {
         const lines = await invoke<Array<{ line_number: number; content: string; level: string }>>('get_log_range', {
+          handle: getActiveHandle(),
           startLine,
           endLine
         });
@@ -194,16 +195,21 @@ async function handleToolCalls(tool_calls: any[]): Promise<any[]> {
     } else if (name === 'search_log_content') {
       const maxResults = Math.min(100, Math.max(1, Math.floor(args.max_results ?? 20)));
       try {
-        const lines = await invoke<Array<{ line_number: number; content: string; level: string }>>('search_log', {
+        // 只需要总数和前 maxResults 行，结果集用完即释放
+        const page = await invoke<{ result: ResultSetInfo; total: number; lines: Array<{ line_number: number; content: string; level: string }> }>('search_log', {
+          handle: getActiveHandle(),
           query: args.query as string,
           isRegex: args.is_regex ?? false,
-          lineRanges: null
+          lineRanges: null,
+          pageSize: maxResults,
+          maxResults
         });
-        if (lines.length === 0) {
+        releaseResult(page.result);
+        if (page.total === 0) {
           result = `No matches found for: "${args.query}"`;
         } else {
-          const shown = lines.slice(0, maxResults);
-          result = `Found ${lines.length} match(es), showing first ${shown.length}:\n` +
+          const shown = page.lines;
+          result = `Found ${page.total} match(es), showing first ${shown.length}:\n` +
             shown.map(l => `[L${l.line_number}] ${l.content.trimEnd()}`).join('\n');
         }
       } catch (e) {
@@ -269,8 +275,8 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
 
     // 3. 导出指令
     if (cmd === 'export' || cmd === 'exp') {
-      const displayIndices = isLeft ? store.filteredIndices : store.rightFilteredIndices;
-      if (displayIndices.length === 0) return { success: false, message: '当前视图无内容可导出' };
+      const displayResult = isLeft ? store.filterResult : store.rightFilterResult;
+      if (!displayResult || displayResult.count === 0) return { success: false, message: '当前视图无内容可导出' };
       
       try {
         const path = await save({
@@ -279,7 +285,7 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
         });
 
         if (path) {
-          await invoke('save_filtered_logs', { path, indices: displayIndices });
+          await invoke('save_filtered_logs', { resultId: displayResult.id, path });
           return { success: true, message: '导出成功', action: 'export' };
         }
         return { success: false }; // 用户取消
@@ -293,7 +299,7 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
       try {
         const path = await open({
           multiple: false,
-          filters: [{ name: 'Log Files', extensions: ['log', 'txt', 'out', 'txt*'] }]
+          filters: [{ name: 'Log Files', extensions: ['log', 'txt', 'out', 'txt*', 'gz', 'zst', 'xz', 'bz2', 'zip', 'tar', 'tgz'] }]
         });
         if (path && typeof path === 'string') {
           await loadLogFile(path);
@@ -318,6 +324,13 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
   if (mode === 'time') {
     if (!cmd) return { success: false };
 
+    // "from~to" 添加时间范围面包屑，两端都可以省略
+    if (cmd.includes('~')) {
+      if (isLeft) store.addRefinementFilter('@' + cmd);
+      else store.addRightRefinementFilter('@' + cmd);
+      return { success: true, message: `已限定时间范围 ${cmd}`, action: 'filter' };
+    }
+
     try {
       // 获取当前选中的 Session 范围，如果没有选中则搜索全量
       const sessionRanges = store.selectedSessionIds.length > 0
@@ -326,11 +339,18 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
             .map(s => [s.startLine, s.endLine])
         : null;
 
-      // 调用后端查找第一个匹配的行号 (0-based)
-      const targetIdx = await invoke<number | null>('find_first_occurrence', {
-        query: cmd,
+      // 按每行时间戳二分查找首个不早于该时间的行 (0-based)；输入无法解析为时间时退回文本查找
+      const targetIdx = await invoke<number | null>('find_line_at_time', {
+        handle: getActiveHandle(),
+        time: cmd,
+        timestampRegex: store.timestampRegex,
         lineRanges: sessionRanges
-      });
+      }).catch(() => invoke<number | null>('find_first_occurrence', {
+        handle: getActiveHandle(),
+        query: cmd,
+        lineRanges: sessionRanges,
+        options: store.searchOptions
+      }));
 
       if (targetIdx !== null) {
         if (isLeft) {
@@ -340,7 +360,7 @@ export const processCommand = async (input: string, mode: 'command' | 'time' | '
         } else {
           store.setRightScrollTargetLine(targetIdx);
         }
-        return { success: true, message: `已定位到包含 "${cmd}" 的行`, action: 'jump' };
+        return { success: true, message: `已定位到 ${cmd}`, action: 'jump' };
       } else {
         return { success: false, message: `未找到时间标记: ${cmd}` };
       }
@@ -1,10 +1,28 @@
-import { useLogStore } from '../store';
+import { useLogStore, LogFile, ArchiveMember, LevelRuns, createJob, isCancelledError } from '../store';
 import { invoke } from '@tauri-apps/api/core';
 
+interface FileInfo {
+  handle: number;
+  name: string;
+  size: number;
+  lines: number;
+  sessions: number;
+  encoding: string;
+}
+
+// 可手动指定的编码，空字符串表示自动识别
+export const ENCODING_OPTIONS = ['', 'utf-8', 'utf-16le', 'utf-16be', 'gb18030', 'big5', 'shift_jis', 'latin1'];
+
+// zip / tar 以及带外层压缩的 tar 包
+const ARCHIVE_PATTERN = /\.(zip|tar|tgz|tbz2|txz|tar\.(gz|zst|xz|bz2))$/i;
+
+export const isArchivePath = (filePath: string) => ARCHIVE_PATTERN.test(filePath);
+
 /**
  * 核心文件加载函数：处理文件解析、索引建立和 Session 切分
+ * encoding 不传时沿用该文件之前手动指定的编码
  */
-export const loadLogFile = async (filePath: string) => {
+export const loadLogFile = async (filePath: string, encoding?: string) => {
   const { 
     files, 
     addFile, 
@@ -12,85 +30,221 @@ export const loadLogFile = async (filePath: string) => {
     bootMarkerRegex, 
     logLevelRegex,
     timestampRegex,
+    timestampOptions,
     timeGapThreshold
   } = useLogStore.getState();
   
   try {
     // 检查文件是否已加载
     const existingFile = files.find(f => f.path === filePath);
+    const encodingOverride = encoding ?? existingFile?.encodingOverride ?? '';
+
+    // 压缩包成员已经在后端建好索引，只需重新切分会话
+    if (existingFile?.archiveMember) {
+      setCurrentFile(existingFile.id);
+      await loadDocumentContent(existingFile.handle);
+      return;
+    }
+
+    // 轮转日志组：在原句柄上重新拼接
+    if (existingFile?.rotatedSet) {
+      await openRotatedLogs(filePath, encodingOverride);
+      return;
+    }
+
+    // 压缩包：先列出成员，由用户选择要打开的日志
+    if (isArchivePath(filePath)) {
+      const members = await invoke<ArchiveMember[]>('list_archive_members', { path: filePath });
+      useLogStore.setState({ archiveBrowser: { path: filePath, members } });
+      return;
+    }
     
-    // 调用 Rust 后端解析文件基本信息
-    const fileInfo = await invoke<{
-      name: string;
-      size: number;
-      lines: number;
-      sessions: number;
-    }>('parse_log_file', { 
+    // 调用 Rust 后端解析文件基本信息（作为可取消的后台任务）
+    const jobId = await createJob();
+    useLogStore.setState({ loadJobId: jobId });
+    const fileInfo = await invoke<FileInfo>('parse_log_file', { 
       path: filePath,
       bootRegex: bootMarkerRegex,
-      levelRegex: logLevelRegex
-    });
+      levelRegex: logLevelRegex,
+      // 启用时间间隙切分时，时间戳在建索引时一并解析
+      timestampRegex: timeGapThreshold > 0 ? timestampRegex : null,
+      handle: existingFile ? existingFile.handle : null,
+      encoding: encodingOverride || null,
+      timestampOptions,
+      jobId
+    }).finally(() => useLogStore.setState({ loadJobId: null }));
 
     const fileId = existingFile ? existingFile.id : Date.now().toString();
     
     if (!existingFile) {
       addFile({
         id: fileId,
+        handle: fileInfo.handle,
         name: fileInfo.name,
         path: filePath,
         size: fileInfo.size,
         lines: fileInfo.lines,
         sessions: fileInfo.sessions,
+        encoding: fileInfo.encoding,
+        encodingOverride,
       });
     } else {
       // 更新现有文件信息
       useLogStore.setState(state => ({
         files: state.files.map(f => f.id === fileId ? {
           ...f,
+          handle: fileInfo.handle,
           size: fileInfo.size,
           lines: fileInfo.lines,
           sessions: fileInfo.sessions,
+          encoding: fileInfo.encoding,
+          encodingOverride,
         } : f)
       }));
       setCurrentFile(fileId);
     }
 
-    // 加载日志内容
-    const result = await invoke<{
-      sessions: Array<{
-        id: number;
-        start_line: number;
-        end_line: number;
-        boot_marker: string;
-      }>;
-      line_count: number;
-      levels: (string|null)[];
-    }>('parse_log_content', { 
-      path: filePath,
+    await loadDocumentContent(fileInfo.handle);
+  } catch (error) {
+    useLogStore.setState({ decompressProgress: null });
+    if (isCancelledError(error)) return;
+    console.error('Failed to load file:', error);
+    throw error;
+  }
+};
+
+/**
+ * 打开压缩包中的若干成员，每个成员作为独立文件加入列表，并激活第一个
+ */
+export const openArchiveMembers = async (archivePath: string, members: string[]) => {
+  const { addFile, setCurrentFile, bootMarkerRegex, logLevelRegex } = useLogStore.getState();
+  if (members.length === 0) return;
+
+  const infos = await invoke<FileInfo[]>('open_archive_members', {
+    path: archivePath,
+    members,
+    bootRegex: bootMarkerRegex,
+    levelRegex: logLevelRegex
+  });
+
+  const baseId = Date.now();
+  const added: LogFile[] = infos.map((info, i) => ({
+    id: `${baseId}-${i}`,
+    handle: info.handle,
+    name: info.name,
+    path: `${archivePath}!${members[i]}`,
+    size: info.size,
+    lines: info.lines,
+    sessions: info.sessions,
+    encoding: info.encoding,
+    archiveMember: true,
+  }));
+  added.forEach(f => addFile(f));
+  useLogStore.setState({ archiveBrowser: null });
+
+  if (added.length > 0) {
+    setCurrentFile(added[0].id);
+    await loadDocumentContent(added[0].handle);
+  }
+};
+
+/**
+ * 打开轮转日志组（目录或 app.log* 这样的通配符），多个文件拼接为一个文档
+ */
+export const openRotatedLogs = async (spec: string, encoding?: string) => {
+  const { files, addFile, setCurrentFile, bootMarkerRegex, logLevelRegex, timestampRegex, timestampOptions } = useLogStore.getState();
+  const existingFile = files.find(f => f.path === spec);
+  const encodingOverride = encoding ?? existingFile?.encodingOverride ?? '';
+
+  try {
+    const fileInfo = await invoke<FileInfo>('open_rotated_logs', {
+      spec,
       bootRegex: bootMarkerRegex,
       levelRegex: logLevelRegex,
-      timestampRegex: timestampRegex,
-      timeGapThreshold: timeGapThreshold
+      timestampRegex,
+      handle: existingFile ? existingFile.handle : null,
+      encoding: encodingOverride || null,
+      timestampOptions
     });
 
-    useLogStore.getState().setParsedLog({
-      sessions: result.sessions.map(s => ({
-        id: s.id,
-        startLine: s.start_line,
-        endLine: s.end_line,
-        bootMarker: s.boot_marker,
-      })),
-      levels: result.levels,
-      line_count: result.line_count
-    });
+    if (!existingFile) {
+      const fileId = Date.now().toString();
+      addFile({
+        id: fileId,
+        handle: fileInfo.handle,
+        name: fileInfo.name,
+        path: spec,
+        size: fileInfo.size,
+        lines: fileInfo.lines,
+        sessions: fileInfo.sessions,
+        encoding: fileInfo.encoding,
+        encodingOverride,
+        rotatedSet: true,
+      });
+    } else {
+      useLogStore.setState(state => ({
+        files: state.files.map(f => f.id === existingFile.id ? {
+          ...f,
+          name: fileInfo.name,
+          size: fileInfo.size,
+          lines: fileInfo.lines,
+          sessions: fileInfo.sessions,
+          encoding: fileInfo.encoding,
+          encodingOverride,
+        } : f)
+      }));
+      setCurrentFile(existingFile.id);
+    }
 
-    // 打开新文件时，默认关闭“仅限所选会话”搜索
-    useLogStore.setState({ 
-      searchOnlySelectedSessions: false,
-      selectedSessionIds: [] 
-    });
+    await loadDocumentContent(fileInfo.handle);
   } catch (error) {
-    console.error('Failed to load file:', error);
+    useLogStore.setState({ decompressProgress: null });
+    console.error('Failed to open rotated logs:', error);
     throw error;
   }
 };
+
+// 加载日志内容：按当前配置切分会话并拉取级别
+export const loadDocumentContent = async (handle: number) => {
+  const { bootMarkerRegex, logLevelRegex, timestampRegex, timeGapThreshold, searchIndexEnabled } = useLogStore.getState();
+
+  // 加载日志内容
+  const result = await invoke<{
+    sessions: Array<{
+      id: number;
+      start_line: number;
+      end_line: number;
+      boot_marker: string;
+    }>;
+    line_count: number;
+    levels: LevelRuns;
+  }>('parse_log_content', { 
+    handle,
+    bootRegex: bootMarkerRegex,
+    levelRegex: logLevelRegex,
+    timestampRegex: timestampRegex,
+    timeGapThreshold: timeGapThreshold
+  });
+
+  useLogStore.getState().setParsedLog({
+    sessions: result.sessions.map(s => ({
+      id: s.id,
+      startLine: s.start_line,
+      endLine: s.end_line,
+      bootMarker: s.boot_marker,
+    })),
+    levels: result.levels,
+    line_count: result.line_count
+  });
+
+  // 打开新文件时，默认关闭“仅限所选会话”搜索
+  useLogStore.setState({ 
+    searchOnlySelectedSessions: false,
+    selectedSessionIds: [] 
+  });
+
+  // 后台建立搜索索引，建好之前搜索照常全量扫描
+  if (searchIndexEnabled) {
+    invoke('build_search_index', { handle }).catch(err => console.warn('Failed to build search index:', err));
+  }
+};